@@ -2,7 +2,7 @@ use std::env;
 
 use askama::Template;
 use axum::{
-    http::StatusCode,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
     response::{Html, IntoResponse},
     routing::get,
     Json, Router,
@@ -46,9 +46,15 @@ async fn root() -> impl IntoResponse {
     Html(template.render().unwrap())
 }
 
-async fn protected(jar: CookieJar) -> impl IntoResponse {
-    let jwt_cookie = match jar.get("jwt") {
-        Some(cookie) => cookie,
+async fn protected(headers: HeaderMap, jar: CookieJar) -> impl IntoResponse {
+    // Machine clients send a bearer token, browsers the jwt cookie
+    let bearer_token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_owned);
+    let token = match bearer_token.or_else(|| jar.get("jwt").map(|c| c.value().to_owned())) {
+        Some(token) => token,
         None => {
             return StatusCode::UNAUTHORIZED.into_response();
         }
@@ -56,8 +62,12 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
 
     let api_client = reqwest::Client::builder().build().unwrap();
 
+    // Only accept tokens that were issued for this service
+    let audience = env::var("APP_AUDIENCE").unwrap_or("app-service".to_owned());
+
     let verify_token_body = serde_json::json!({
-        "token": &jwt_cookie.value(),
+        "token": &token,
+        "audience": audience,
     });
 
     let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
@@ -32,6 +32,19 @@ signupLoginLink.addEventListener("click", (e) => {
 
 // -----------------------------------------------------
 
+// Set by /authorize when an OAuth client sent the user here to log in.
+// Only ever follow it back to /authorize so it can't be used as an open
+// redirect.
+const next = new URLSearchParams(window.location.search).get("next");
+
+function onLoggedIn() {
+    if (next !== null && next.startsWith("/authorize?")) {
+        window.location.assign(next);
+    } else {
+        alert("You have successfully logged in.");
+    }
+}
+
 const loginForm = document.getElementById("login-form");
 const loginButton = document.getElementById("login-form-submit");
 const loginErrAlter = document.getElementById("login-err-alert");
@@ -53,6 +66,11 @@ loginButton.addEventListener("click", (e) => {
             TwoFAForm.email.value = email;
             response.json().then(data => {
                 TwoFAForm.login_attempt_id.value = data.loginAttemptId;
+                TwoFAForm.email_code.placeholder = {
+                    totp: "Code from your authenticator app",
+                    sms: "Code from your text messages",
+                }[data.method] || "Code from your email";
+                TwoFAResend.style.display = data.method === "totp" ? "none" : "block";
             });
 
             loginForm.email.value = "";
@@ -66,7 +84,7 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            onLoggedIn();
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -81,6 +99,124 @@ loginButton.addEventListener("click", (e) => {
     });
 });
 
+// WebAuthn hands us ArrayBuffers; the API speaks base64url
+function fromBase64Url(value) {
+    const base64 = value.replace(/-/g, "+").replace(/_/g, "/");
+    return Uint8Array.from(atob(base64), c => c.charCodeAt(0));
+}
+
+function toBase64Url(buffer) {
+    const base64 = btoa(String.fromCharCode(...new Uint8Array(buffer)));
+    return base64.replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+}
+
+function showLoginError(error_msg) {
+    if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+        loginErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+        loginErrAlter.style.display = "block";
+    } else {
+        loginErrAlter.style.display = "none";
+    }
+}
+
+const forgotPasswordLink = document.getElementById("forgot-password-link");
+
+forgotPasswordLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const email = prompt("Enter the email address of your account:", loginForm.email.value);
+    if (email === null || email === "") {
+        return;
+    }
+
+    fetch('/forgot-password', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ email }),
+    }).then(response => {
+        if (response.ok) {
+            loginErrAlter.style.display = "none";
+            alert("If an account exists for " + email + ", we've emailed it a link to reset the password.");
+        } else {
+            response.json().then(data => showLoginError(data.error));
+        }
+    });
+});
+
+// Set when the user opens the link from a password reset email
+const resetToken = new URLSearchParams(window.location.search).get("reset_token");
+
+if (resetToken !== null) {
+    const newPassword = prompt("Choose a new password:");
+    if (newPassword !== null) {
+        fetch('/reset-password', {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            body: JSON.stringify({ token: resetToken, newPassword }),
+        }).then(response => {
+            if (response.ok) {
+                alert("Your password has been changed. You can now log in with it.");
+            } else {
+                response.json().then(data => showLoginError(data.error));
+            }
+        });
+    }
+}
+
+const passkeyLoginButton = document.getElementById("passkey-login-submit");
+
+if (!window.PublicKeyCredential) {
+    passkeyLoginButton.style.display = "none";
+}
+
+passkeyLoginButton.addEventListener("click", async (e) => {
+    e.preventDefault();
+
+    try {
+        const options = await fetch('/passkeys/login/start', { method: 'POST' })
+            .then(response => response.json());
+
+        const credential = await navigator.credentials.get({
+            publicKey: {
+                challenge: fromBase64Url(options.challenge),
+                rpId: options.rpId,
+                timeout: options.timeout,
+                userVerification: options.userVerification,
+                allowCredentials: [],
+            },
+        });
+
+        const response = await fetch('/passkeys/login/finish', {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            body: JSON.stringify({
+                id: credential.id,
+                response: {
+                    clientDataJSON: toBase64Url(credential.response.clientDataJSON),
+                    authenticatorData: toBase64Url(credential.response.authenticatorData),
+                    signature: toBase64Url(credential.response.signature),
+                },
+            }),
+        });
+
+        if (response.status === 200) {
+            loginErrAlter.style.display = "none";
+            onLoggedIn();
+        } else {
+            response.json().then(data => showLoginError(data.error));
+        }
+    } catch (err) {
+        // The user closed the browser prompt or has no passkey for this site
+        showLoginError("Passkey sign-in was cancelled");
+    }
+});
+
 const signupForm = document.getElementById("signup-form");
 const signupButton = document.getElementById("signup-form-submit");
 const signupErrAlter = document.getElementById("signup-err-alert");
@@ -104,7 +240,17 @@ signupButton.addEventListener("click", (e) => {
             signupForm.password.value = "";
             signupForm.twoFA.checked = false;
             signupErrAlter.style.display = "none";
-            alert("You have successfully created a user.");
+            response.json().then(data => {
+                const created = "You have successfully created a user. "
+                    + "Follow the link we emailed you before logging in.";
+                if (data.recoveryCodes !== undefined) {
+                    alert(created + "\n\n"
+                        + "Keep these recovery codes somewhere safe. Each one can be used once "
+                        + "instead of a verification code:\n\n" + data.recoveryCodes.join("\n"));
+                } else {
+                    alert(created);
+                }
+            });
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
             signupSection.style.display = "none";
@@ -125,6 +271,33 @@ signupButton.addEventListener("click", (e) => {
 const TwoFAForm = document.getElementById("2fa-form");
 const TwoFAButton = document.getElementById("2fa-form-submit");
 const TwoFAErrAlter = document.getElementById("2fa-err-alert");
+const TwoFAResend = document.getElementById("2fa-resend");
+const TwoFAResendLink = document.getElementById("2fa-resend-link");
+
+TwoFAResendLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const email = TwoFAForm.email.value;
+    const loginAttemptId = TwoFAForm.login_attempt_id.value;
+
+    fetch('/resend-2fa', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ email, loginAttemptId }),
+    }).then(response => {
+        if (response.ok) {
+            TwoFAErrAlter.style.display = "none";
+            alert("A new code is on its way.");
+        } else {
+            response.json().then(data => {
+                TwoFAErrAlter.innerHTML = `<span><strong>Error: </strong>${data.error}</span>`;
+                TwoFAErrAlter.style.display = "block";
+            });
+        }
+    });
+});
 
 TwoFAButton.addEventListener("click", (e) => {
     e.preventDefault();
@@ -132,23 +305,34 @@ TwoFAButton.addEventListener("click", (e) => {
     const email = TwoFAForm.email.value;
     const loginAttemptId = TwoFAForm.login_attempt_id.value;
     const TwoFACode = TwoFAForm.email_code.value;
+    const rememberDevice = TwoFAForm.rememberDevice.checked;
 
     fetch('/verify-2fa', {
         method: 'POST',
         headers: {
             'Content-Type': 'application/json',
         },
-        body: JSON.stringify({ email, loginAttemptId, "2FACode": TwoFACode }),
+        body: JSON.stringify({ email, loginAttemptId, "2FACode": TwoFACode, rememberDevice }),
     }).then(response => {
         if (response.ok) {
             TwoFAForm.email.value = "";
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            onLoggedIn();
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
             signupSection.style.display = "none";
+        } else if (response.status === 429) {
+            // The code was invalidated, so the only way on is a new login
+            response.json().then(data => {
+                TwoFAForm.email_code.value = "";
+                TwoFAForm.login_attempt_id.value = "";
+                TwoFAErrAlter.style.display = "none";
+                showLoginError(data.error);
+                loginSection.style.display = "block";
+                twoFASection.style.display = "none";
+            });
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
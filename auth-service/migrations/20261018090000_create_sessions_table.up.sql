@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS sessions(
+   id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   created_at TIMESTAMPTZ NOT NULL,
+   expires_at TIMESTAMPTZ NOT NULL,
+   ip_address TEXT,
+   user_agent TEXT
+);
+
+CREATE INDEX IF NOT EXISTS sessions_email_idx ON sessions(email);
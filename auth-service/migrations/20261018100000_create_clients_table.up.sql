@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS clients(
+   id TEXT NOT NULL PRIMARY KEY,
+   name TEXT NOT NULL,
+   secret_hash TEXT NOT NULL
+);
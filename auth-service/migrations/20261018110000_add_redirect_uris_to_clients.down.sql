@@ -0,0 +1,3 @@
+DELETE FROM clients WHERE secret_hash IS NULL;
+ALTER TABLE clients ALTER COLUMN secret_hash SET NOT NULL;
+ALTER TABLE clients DROP COLUMN IF EXISTS redirect_uris;
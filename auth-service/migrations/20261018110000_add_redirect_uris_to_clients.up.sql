@@ -0,0 +1,4 @@
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS redirect_uris TEXT[] NOT NULL DEFAULT '{}';
+
+-- Public clients have no secret
+ALTER TABLE clients ALTER COLUMN secret_hash DROP NOT NULL;
@@ -0,0 +1,2 @@
+-- Scopes a client may request for itself with the client_credentials grant
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS scopes TEXT[] NOT NULL DEFAULT '{}';
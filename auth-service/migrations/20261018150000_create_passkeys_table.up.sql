@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS passkeys(
+   credential_id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   public_key BYTEA NOT NULL,
+   sign_count BIGINT NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS passkeys_email_idx ON passkeys(email);
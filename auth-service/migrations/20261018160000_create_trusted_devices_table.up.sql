@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS trusted_devices(
+   id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   created_at TIMESTAMPTZ NOT NULL,
+   expires_at TIMESTAMPTZ NOT NULL,
+   ip_address TEXT,
+   user_agent TEXT
+);
+
+CREATE INDEX IF NOT EXISTS trusted_devices_email_idx ON trusted_devices(email);
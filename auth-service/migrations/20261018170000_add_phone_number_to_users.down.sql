@@ -0,0 +1,4 @@
+ALTER TABLE users DROP COLUMN IF EXISTS code_channel;
+ALTER TABLE users DROP COLUMN IF EXISTS pending_phone_code;
+ALTER TABLE users DROP COLUMN IF EXISTS pending_phone_number;
+ALTER TABLE users DROP COLUMN IF EXISTS phone_number;
@@ -0,0 +1,4 @@
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_number TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS pending_phone_number TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS pending_phone_code TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS code_channel TEXT NOT NULL DEFAULT 'email';
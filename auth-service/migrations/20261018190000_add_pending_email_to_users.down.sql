@@ -0,0 +1,19 @@
+ALTER TABLE trusted_devices
+   DROP CONSTRAINT IF EXISTS trusted_devices_email_fkey,
+   ADD CONSTRAINT trusted_devices_email_fkey FOREIGN KEY (email)
+      REFERENCES users(email) ON DELETE CASCADE;
+ALTER TABLE passkeys
+   DROP CONSTRAINT IF EXISTS passkeys_email_fkey,
+   ADD CONSTRAINT passkeys_email_fkey FOREIGN KEY (email)
+      REFERENCES users(email) ON DELETE CASCADE;
+ALTER TABLE totp_secrets
+   DROP CONSTRAINT IF EXISTS totp_secrets_email_fkey,
+   ADD CONSTRAINT totp_secrets_email_fkey FOREIGN KEY (email)
+      REFERENCES users(email) ON DELETE CASCADE;
+ALTER TABLE sessions
+   DROP CONSTRAINT IF EXISTS sessions_email_fkey,
+   ADD CONSTRAINT sessions_email_fkey FOREIGN KEY (email)
+      REFERENCES users(email) ON DELETE CASCADE;
+
+ALTER TABLE users DROP COLUMN IF EXISTS pending_email_token;
+ALTER TABLE users DROP COLUMN IF EXISTS pending_email;
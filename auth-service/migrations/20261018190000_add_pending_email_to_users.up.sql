@@ -0,0 +1,20 @@
+ALTER TABLE users ADD COLUMN IF NOT EXISTS pending_email TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS pending_email_token TEXT;
+
+-- Changing a user's email carries everything keyed by it along
+ALTER TABLE sessions
+   DROP CONSTRAINT IF EXISTS sessions_email_fkey,
+   ADD CONSTRAINT sessions_email_fkey FOREIGN KEY (email)
+      REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE totp_secrets
+   DROP CONSTRAINT IF EXISTS totp_secrets_email_fkey,
+   ADD CONSTRAINT totp_secrets_email_fkey FOREIGN KEY (email)
+      REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE passkeys
+   DROP CONSTRAINT IF EXISTS passkeys_email_fkey,
+   ADD CONSTRAINT passkeys_email_fkey FOREIGN KEY (email)
+      REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE trusted_devices
+   DROP CONSTRAINT IF EXISTS trusted_devices_email_fkey,
+   ADD CONSTRAINT trusted_devices_email_fkey FOREIGN KEY (email)
+      REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
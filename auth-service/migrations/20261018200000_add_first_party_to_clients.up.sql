@@ -0,0 +1,2 @@
+-- Our own apps. Only these may use /authorize, which has no consent screen.
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS first_party BOOLEAN NOT NULL DEFAULT FALSE;
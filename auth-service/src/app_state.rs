@@ -1,33 +1,83 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use crate::domain::{
+    AuthorizationCodeStore, BannedTokenStore, ClientStore, EmailClient, EmailTokenStore,
+    PasskeyChallengeStore, PasskeyStore, RefreshTokenStore, SessionEpochStore, SessionStore,
+    SmsClient, TotpStore, TrustedDeviceStore, TwoFACodeStore, UserStore,
+};
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+pub type SessionEpochStoreType = Arc<RwLock<dyn SessionEpochStore + Send + Sync>>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+pub type ClientStoreType = Arc<RwLock<dyn ClientStore + Send + Sync>>;
+pub type AuthorizationCodeStoreType =
+    Arc<RwLock<dyn AuthorizationCodeStore + Send + Sync>>;
+pub type TotpStoreType = Arc<RwLock<dyn TotpStore + Send + Sync>>;
+pub type PasskeyStoreType = Arc<RwLock<dyn PasskeyStore + Send + Sync>>;
+pub type PasskeyChallengeStoreType = Arc<RwLock<dyn PasskeyChallengeStore + Send + Sync>>;
+pub type TrustedDeviceStoreType = Arc<RwLock<dyn TrustedDeviceStore + Send + Sync>>;
+pub type EmailTokenStoreType = Arc<RwLock<dyn EmailTokenStore + Send + Sync>>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
+pub type SmsClientType = Arc<dyn SmsClient + Send + Sync>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub session_epoch_store: SessionEpochStoreType,
+    pub session_store: SessionStoreType,
+    pub client_store: ClientStoreType,
+    pub authorization_code_store: AuthorizationCodeStoreType,
+    pub totp_store: TotpStoreType,
+    pub passkey_store: PasskeyStoreType,
+    pub passkey_challenge_store: PasskeyChallengeStoreType,
+    pub trusted_device_store: TrustedDeviceStoreType,
+    pub email_token_store: EmailTokenStoreType,
     pub email_client: EmailClientType,
+    pub sms_client: SmsClientType,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
+        refresh_token_store: RefreshTokenStoreType,
+        session_epoch_store: SessionEpochStoreType,
+        session_store: SessionStoreType,
+        client_store: ClientStoreType,
+        authorization_code_store: AuthorizationCodeStoreType,
+        totp_store: TotpStoreType,
+        passkey_store: PasskeyStoreType,
+        passkey_challenge_store: PasskeyChallengeStoreType,
+        trusted_device_store: TrustedDeviceStoreType,
+        email_token_store: EmailTokenStoreType,
         email_client: EmailClientType,
+        sms_client: SmsClientType,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
+            refresh_token_store,
+            session_epoch_store,
+            session_store,
+            client_store,
+            authorization_code_store,
+            totp_store,
+            passkey_store,
+            passkey_challenge_store,
+            trusted_device_store,
+            email_token_store,
             email_client,
+            sms_client,
         }
     }
 }
@@ -1,6 +1,9 @@
-use super::{Email, Password, User};
+use super::{CodeChannel, Email, Password, PhoneNumber, User};
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 use rand::Rng;
+use data_encoding::BASE32_NOPAD;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
@@ -14,30 +17,298 @@ pub trait UserStore {
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError>;
+    async fn set_code_channel(
+        &mut self,
+        email: &Email,
+        channel: CodeChannel,
+    ) -> Result<(), UserStoreError>;
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    /// Holds `new_email` until `change_email` is called with the `token`
+    /// sent to it. Replaces any change already pending.
+    async fn set_pending_email(
+        &mut self,
+        email: &Email,
+        new_email: Email,
+        token: &EmailToken,
+    ) -> Result<(), UserStoreError>;
+    /// Moves the account, with its recovery codes and phone number, to the
+    /// pending address and returns it. Fails with `InvalidCredentials` if
+    /// `token` wasn't sent for the pending change, and with
+    /// `UserAlreadyExists` if the address was taken in the meantime.
+    async fn change_email(
+        &mut self,
+        email: &Email,
+        token: &EmailToken,
+    ) -> Result<Email, UserStoreError>;
+    /// Holds `phone_number` until `confirm_phone_number` is called with the
+    /// `code` texted to it. Replaces any number already pending.
+    async fn set_pending_phone_number(
+        &mut self,
+        email: &Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), UserStoreError>;
+    /// Makes the pending phone number the user's own and returns it. A wrong
+    /// code discards the pending number and fails with `InvalidCredentials`.
+    async fn confirm_phone_number(
+        &mut self,
+        email: &Email,
+        code: &TwoFACode,
+    ) -> Result<PhoneNumber, UserStoreError>;
+    /// Replaces the user's recovery codes; any left from before stop working.
+    async fn set_recovery_codes(
+        &mut self,
+        email: &Email,
+        codes: &[RecoveryCode],
+    ) -> Result<(), UserStoreError>;
+    /// Consumes one of the user's recovery codes. Fails with
+    /// `InvalidCredentials` if it isn't one of them or was already used.
+    async fn use_recovery_code(
+        &mut self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<(), UserStoreError>;
 }
 
+/// Emailed 2FA codes, one per login attempt, so a user can be logging in
+/// on several devices at once.
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
+    /// Replaces any code already stored for the login attempt.
     async fn add_code(
         &mut self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError>;
+    /// Returns the email the code was sent to and the code. Fails with
+    /// `TooManyAttempts` once the code has been invalidated.
     async fn get_code(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode), TwoFACodeStoreError>;
+    /// Counts a wrong guess at the code. After `max_attempts` of them the
+    /// code is invalidated and this fails with `TooManyAttempts`; the user
+    /// has to start a new login attempt.
+    async fn record_failed_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError>;
+    /// Swaps in `code` for the login attempt so it can be emailed again, and
+    /// restarts its expiry. Wrong guesses made so far still count. Fails with
+    /// `ResendTooSoon` within `cooldown_seconds` of the code last being sent and
+    /// with `TooManyResends` once `max_resends` have been made.
+    async fn resend_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        code: TwoFACode,
+        cooldown_seconds: u64,
+        max_resends: u32,
+    ) -> Result<(), TwoFACodeStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: String,
+        epoch: u64,
+        audience: String,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn get_token(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError>;
+    async fn mark_token_used(&mut self, token: &RefreshToken)
+        -> Result<(), RefreshTokenStoreError>;
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError>;
+}
+
+/// Records every login so users can see where they are signed in and end
+/// individual sessions. Revoked and expired sessions are never returned.
+#[async_trait::async_trait]
+pub trait SessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError>;
+    async fn list_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    async fn extend_session(
+        &mut self,
+        id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), SessionStoreError>;
+    async fn revoke_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError>;
+    async fn revoke_all_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError>;
+}
+
+/// Browsers where the user asked to skip 2FA on later logins. Revoked and
+/// expired devices are never returned.
+#[async_trait::async_trait]
+pub trait TrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError>;
+    async fn get_device(&self, id: &str) -> Result<TrustedDevice, TrustedDeviceStoreError>;
+    async fn list_devices(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    ) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError>;
+    async fn revoke_device(&mut self, email: &Email, id: &str)
+        -> Result<(), TrustedDeviceStoreError>;
+    async fn revoke_all_devices(&mut self, email: &Email) -> Result<(), TrustedDeviceStoreError>;
+}
+
+/// OAuth clients and other services allowed to call the back-channel
+/// endpoints. Confidential clients authenticate with a client id and
+/// secret; public clients, such as single-page and native apps, have no
+/// secret and must rely on PKCE.
+#[async_trait::async_trait]
+pub trait ClientStore {
+    async fn add_client(
+        &mut self,
+        client: Client,
+        secret: Option<ClientSecret>,
+    ) -> Result<(), ClientStoreError>;
+    async fn get_client(&self, client_id: &str) -> Result<Client, ClientStoreError>;
+    /// Public clients only validate without a secret, confidential clients
+    /// only with theirs.
+    async fn validate_client(
+        &self,
+        client_id: &str,
+        secret: Option<&ClientSecret>,
+    ) -> Result<Client, ClientStoreError>;
+}
+
+/// Single-use authorization codes handed out by `/authorize` and redeemed
+/// at `/token`.
+#[async_trait::async_trait]
+pub trait AuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError>;
+    /// Returns the grant and removes it, so a code can only be redeemed once.
+    async fn take_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError>;
+}
+
+/// Single-use tokens for links we email, each only good for the purpose it
+/// was issued for.
+#[async_trait::async_trait]
+pub trait EmailTokenStore {
+    async fn add_token(
+        &mut self,
+        token: EmailToken,
+        email: Email,
+        purpose: EmailTokenPurpose,
+        ttl_seconds: u64,
+    ) -> Result<(), EmailTokenStoreError>;
+    /// Returns whose token it is and removes it, so a link only works once.
+    /// Expired tokens and tokens issued for another purpose aren't found.
+    async fn take_token(
+        &mut self,
+        token: &EmailToken,
+        purpose: EmailTokenPurpose,
+    ) -> Result<Email, EmailTokenStoreError>;
+}
+
+/// Authenticator-app secrets for RFC 6238 TOTP. A newly enrolled secret
+/// stays pending until the user proves their app generates matching codes;
+/// until then any previously active secret keeps working.
+#[async_trait::async_trait]
+pub trait TotpStore {
+    async fn set_pending_secret(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), TotpStoreError>;
+    async fn get_pending_secret(&self, email: &Email) -> Result<TotpSecret, TotpStoreError>;
+    /// Makes the pending secret the active one. `time_step` is the step of
+    /// the code that confirmed it, so that code can't be used again.
+    async fn activate_secret(&mut self, email: &Email, time_step: u64)
+        -> Result<(), TotpStoreError>;
+    async fn get_secret(&self, email: &Email) -> Result<TotpSecret, TotpStoreError>;
+    /// Fails with `CodeAlreadyUsed` unless `time_step` is later than every
+    /// step used before, so each code is only accepted once.
+    async fn use_time_step(&mut self, email: &Email, time_step: u64)
+        -> Result<(), TotpStoreError>;
+    /// Forgets the user's active and pending secrets, if any.
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpStoreError>;
+    /// Moves the user's secrets over when their email address changes.
+    async fn change_email(&mut self, email: &Email, new_email: &Email)
+        -> Result<(), TotpStoreError>;
+}
+
+/// WebAuthn credentials (passkeys) registered by users, keyed by the
+/// base64url credential id the authenticator chose.
+#[async_trait::async_trait]
+pub trait PasskeyStore {
+    async fn add_passkey(&mut self, passkey: Passkey) -> Result<(), PasskeyStoreError>;
+    async fn get_passkey(&self, credential_id: &str) -> Result<Passkey, PasskeyStoreError>;
+    async fn list_passkeys(&self, email: &Email) -> Result<Vec<Passkey>, PasskeyStoreError>;
+    async fn update_sign_count(
+        &mut self,
+        credential_id: &str,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError>;
+    /// Moves the user's passkeys over when their email address changes.
+    async fn change_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), PasskeyStoreError>;
+}
+
+/// Outstanding WebAuthn challenges. Each is issued for one ceremony and can
+/// only be answered once.
+#[async_trait::async_trait]
+pub trait PasskeyChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: PasskeyChallenge,
+        ceremony: PasskeyCeremony,
+    ) -> Result<(), PasskeyChallengeStoreError>;
+    async fn take_challenge(
+        &mut self,
+        challenge: &PasskeyChallenge,
+    ) -> Result<PasskeyCeremony, PasskeyChallengeStoreError>;
+}
+
+/// Tracks a per-user session epoch. Every token records the epoch it was
+/// issued in; bumping the epoch invalidates all of them at once.
+#[async_trait::async_trait]
+pub trait SessionEpochStore {
+    async fn get_epoch(&self, email: &Email) -> Result<u64, SessionEpochStoreError>;
+    async fn increment_epoch(&mut self, email: &Email) -> Result<u64, SessionEpochStoreError>;
 }
 
 #[derive(Debug, PartialEq)]
 pub enum TwoFACodeStoreError {
     LoginAttemptIdNotFound,
+    TooManyAttempts,
+    ResendTooSoon,
+    TooManyResends,
     UnexpectedError,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct LoginAttemptId(String);
 
 impl LoginAttemptId {
@@ -91,6 +362,374 @@ impl AsRef<str> for TwoFACode {
     }
 }
 
+/// Single-use stand-in for the second factor, for users who lost access to
+/// it. Holds 80 random bits, shown as `XXXX-XXXX-XXXX-XXXX`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoveryCode(String);
+
+impl RecoveryCode {
+    /// Accepts the code as shown, or typed without dashes or in lowercase.
+    pub fn parse(code: String) -> Result<Self, String> {
+        let code: String = code
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_uppercase();
+        if code.len() != 16 || BASE32_NOPAD.decode(code.as_bytes()).is_err() {
+            return Err("Invalid recovery code".to_owned());
+        }
+
+        Ok(Self(code))
+    }
+
+    pub fn formatted(&self) -> String {
+        self.0
+            .as_bytes()
+            .chunks(4)
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        Self(BASE32_NOPAD.encode(&rand::thread_rng().gen::<[u8; 10]>()))
+    }
+}
+
+impl AsRef<str> for RecoveryCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefreshToken(String);
+
+impl RefreshToken {
+    pub fn parse(token: String) -> Result<Self, String> {
+        if Uuid::parse_str(&token).is_err() {
+            return Err("Invalid refresh token".to_owned());
+        }
+
+        Ok(Self(token))
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        let token = Uuid::new_v4().to_string();
+        Self(token)
+    }
+}
+
+impl AsRef<str> for RefreshToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A refresh token belongs to a family: every token minted by rotating it
+/// shares the `family_id` of the token issued at login.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefreshTokenRecord {
+    pub email: Email,
+    pub family_id: String,
+    pub epoch: u64,
+    /// App the access tokens minted from it are issued to.
+    pub audience: String,
+    pub used: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RefreshTokenStoreError {
+    TokenNotFound,
+    UnexpectedError,
+}
+
+/// A single login. The id is the `jti` of every access token issued for it
+/// and the family id of its refresh tokens.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub email: Email,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SessionStoreError {
+    SessionNotFound,
+    UnexpectedError,
+}
+
+/// A browser trusted to skip 2FA. The id is the `jti` of its device cookie.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrustedDevice {
+    pub id: String,
+    pub email: Email,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TrustedDeviceStoreError {
+    DeviceNotFound,
+    UnexpectedError,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Client {
+    pub id: String,
+    pub name: String,
+    /// Where `/authorize` may send the user back to. Compared verbatim.
+    pub redirect_uris: Vec<String>,
+    /// Scopes the client may request for itself with the
+    /// `client_credentials` grant.
+    pub scopes: Vec<String>,
+    /// One of our own apps. `/authorize` has no consent screen, so users
+    /// are only ever sent back to these.
+    pub first_party: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientSecret(String);
+
+impl ClientSecret {
+    /// Client secrets are machine-generated, so anything short is a typo or
+    /// a guess.
+    pub fn parse(secret: String) -> Result<Self, String> {
+        if secret.len() < 32 {
+            return Err("Invalid client secret".to_owned());
+        }
+
+        Ok(Self(secret))
+    }
+}
+
+impl AsRef<str> for ClientSecret {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ClientStoreError {
+    ClientAlreadyExists,
+    ClientNotFound,
+    InvalidCredentials,
+    UnexpectedError,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthorizationCode(String);
+
+impl AuthorizationCode {
+    pub fn parse(code: String) -> Result<Self, String> {
+        if code.is_empty() {
+            return Err("Invalid authorization code".to_owned());
+        }
+
+        Ok(Self(code))
+    }
+}
+
+impl Default for AuthorizationCode {
+    fn default() -> Self {
+        let code = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        Self(code)
+    }
+}
+
+impl AsRef<str> for AuthorizationCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// What the user agreed to at `/authorize`, and what the client has to
+/// prove at `/token` to redeem it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthorizationGrant {
+    pub client_id: String,
+    pub redirect_uri: String,
+    /// S256 PKCE challenge.
+    pub code_challenge: String,
+    pub scope: Option<String>,
+    /// OpenID Connect nonce, echoed in the ID token.
+    pub nonce: Option<String>,
+    pub email: Email,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AuthorizationCodeStoreError {
+    CodeNotFound,
+    UnexpectedError,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmailToken(String);
+
+impl EmailToken {
+    pub fn parse(token: String) -> Result<Self, String> {
+        if token.is_empty() {
+            return Err("Invalid email token".to_owned());
+        }
+
+        Ok(Self(token))
+    }
+}
+
+impl Default for EmailToken {
+    fn default() -> Self {
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        Self(token)
+    }
+}
+
+impl AsRef<str> for EmailToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EmailTokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+    ChangeEmail,
+}
+
+impl AsRef<str> for EmailTokenPurpose {
+    fn as_ref(&self) -> &str {
+        match self {
+            EmailTokenPurpose::VerifyEmail => "verify_email",
+            EmailTokenPurpose::ResetPassword => "reset_password",
+            EmailTokenPurpose::ChangeEmail => "change_email",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EmailTokenStoreError {
+    TokenNotFound,
+    UnexpectedError,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TotpSecret(Vec<u8>);
+
+impl TotpSecret {
+    /// Parses the base32 form users type into their authenticator app.
+    pub fn parse(secret: String) -> Result<Self, String> {
+        let bytes = BASE32_NOPAD
+            .decode(secret.trim_end_matches('=').as_bytes())
+            .map_err(|_| "Invalid TOTP secret".to_owned())?;
+        // RFC 4226 requires at least 128 bits
+        if bytes.len() < 16 {
+            return Err("Invalid TOTP secret".to_owned());
+        }
+
+        Ok(Self(bytes))
+    }
+
+    pub fn encode(&self) -> String {
+        BASE32_NOPAD.encode(&self.0)
+    }
+}
+
+impl Default for TotpSecret {
+    /// 160 bits, the size RFC 4226 recommends for HMAC-SHA1.
+    fn default() -> Self {
+        Self(rand::thread_rng().gen::<[u8; 20]>().to_vec())
+    }
+}
+
+impl AsRef<[u8]> for TotpSecret {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TotpStoreError {
+    SecretNotFound,
+    CodeAlreadyUsed,
+    UnexpectedError,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Passkey {
+    pub credential_id: String,
+    pub email: Email,
+    /// Uncompressed SEC1 P-256 point; only ES256 credentials are accepted.
+    pub public_key: Vec<u8>,
+    /// Authenticators bump this on every use, so a count that goes
+    /// backwards means the credential was cloned.
+    pub sign_count: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PasskeyStoreError {
+    PasskeyAlreadyExists,
+    PasskeyNotFound,
+    UnexpectedError,
+}
+
+/// Base64url of 32 random bytes, as the browser echoes it back in the
+/// client data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasskeyChallenge(String);
+
+impl PasskeyChallenge {
+    pub fn parse(challenge: String) -> Result<Self, String> {
+        if challenge.is_empty() {
+            return Err("Invalid passkey challenge".to_owned());
+        }
+
+        Ok(Self(challenge))
+    }
+}
+
+impl Default for PasskeyChallenge {
+    fn default() -> Self {
+        Self(URL_SAFE_NO_PAD.encode(rand::thread_rng().gen::<[u8; 32]>()))
+    }
+}
+
+impl AsRef<str> for PasskeyChallenge {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// What a challenge was issued for. Registration challenges belong to the
+/// signed-in user; login challenges to whoever answers them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PasskeyCeremony {
+    Registration(Email),
+    Authentication,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PasskeyChallengeStoreError {
+    ChallengeNotFound,
+    UnexpectedError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SessionEpochStoreError {
+    UnexpectedError,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum UserStoreError {
     UserAlreadyExists,
@@ -6,4 +6,51 @@ pub enum AuthAPIError {
     MissingToken,
     InvalidToken,
     MalformedToken,
+    SessionNotFound,
+    DeviceNotFound,
+    EmailNotVerified,
+    PhoneNumberNotVerified,
+    InvalidClient,
+    PasskeyAlreadyExists,
+    TooManyAttempts,
+    ResendTooSoon,
+    TooManyResends,
+    UnknownAudience,
+    TwoFANotEnabled,
+}
+
+/// Errors from the OAuth and OpenID Connect endpoints, reported with the
+/// error codes of RFC 6749 and RFC 6750 so standard clients understand them.
+#[derive(Debug, PartialEq)]
+pub enum OAuthError {
+    InvalidRequest,
+    InvalidClient,
+    InvalidGrant,
+    InvalidScope,
+    /// RFC 8707: the requested audience is not one we issue tokens for.
+    InvalidTarget,
+    InvalidToken,
+    InsufficientScope,
+    UnauthorizedClient,
+    UnsupportedGrantType,
+    UnsupportedResponseType,
+    ServerError,
+}
+
+impl OAuthError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidRequest => "invalid_request",
+            OAuthError::InvalidClient => "invalid_client",
+            OAuthError::InvalidGrant => "invalid_grant",
+            OAuthError::InvalidScope => "invalid_scope",
+            OAuthError::InvalidTarget => "invalid_target",
+            OAuthError::InvalidToken => "invalid_token",
+            OAuthError::InsufficientScope => "insufficient_scope",
+            OAuthError::UnauthorizedClient => "unauthorized_client",
+            OAuthError::UnsupportedGrantType => "unsupported_grant_type",
+            OAuthError::UnsupportedResponseType => "unsupported_response_type",
+            OAuthError::ServerError => "server_error",
+        }
+    }
 }
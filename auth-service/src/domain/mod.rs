@@ -4,10 +4,14 @@ pub mod password;
 pub mod error;
 pub mod user;
 pub mod email_client;
+pub mod phone_number;
+pub mod sms_client;
 
 pub use data_stores::*;
 pub use email::*;
 pub use error::*;
 pub use user::*;
 pub use password::*;
-pub use email_client::*;
\ No newline at end of file
+pub use email_client::*;
+pub use phone_number::*;
+pub use sms_client::*;
@@ -0,0 +1,68 @@
+/// A phone number in E.164 form, e.g. `+15555550123`, the only form SMS
+/// providers reliably accept.
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub struct PhoneNumber(String);
+
+impl PhoneNumber {
+    pub fn parse(s: String) -> Result<PhoneNumber, String> {
+        // Accept the spaces, dashes and brackets people type, but store
+        // only the digits
+        let normalized: String = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '(' | ')'))
+            .collect();
+
+        let valid = match normalized.strip_prefix('+') {
+            Some(digits) => {
+                (8..=15).contains(&digits.len())
+                    && !digits.starts_with('0')
+                    && digits.chars().all(|c| c.is_ascii_digit())
+            }
+            None => false,
+        };
+
+        if valid {
+            Ok(Self(normalized))
+        } else {
+            Err(format!("{} is not a valid phone number.", s))
+        }
+    }
+}
+
+impl AsRef<str> for PhoneNumber {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PhoneNumber;
+
+    #[test]
+    fn empty_string_is_rejected() {
+        assert!(PhoneNumber::parse("".to_owned()).is_err());
+    }
+
+    #[test]
+    fn number_without_country_code_is_rejected() {
+        assert!(PhoneNumber::parse("5555550123".to_owned()).is_err());
+    }
+
+    #[test]
+    fn number_with_letters_is_rejected() {
+        assert!(PhoneNumber::parse("+1555CALLNOW".to_owned()).is_err());
+    }
+
+    #[test]
+    fn number_of_wrong_length_is_rejected() {
+        assert!(PhoneNumber::parse("+1234567".to_owned()).is_err());
+        assert!(PhoneNumber::parse("+1234567890123456".to_owned()).is_err());
+    }
+
+    #[test]
+    fn formatting_is_stripped() {
+        let phone_number = PhoneNumber::parse("+1 (555) 555-0123".to_owned()).unwrap();
+        assert_eq!(phone_number.as_ref(), "+15555550123");
+    }
+}
@@ -0,0 +1,6 @@
+use super::PhoneNumber;
+
+#[async_trait::async_trait]
+pub trait SmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<(), String>;
+}
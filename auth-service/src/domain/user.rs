@@ -1,10 +1,71 @@
-use super::{Email, Password};
+use super::{Email, Password, PhoneNumber};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    /// Only ever set once the user proved they receive texts at it.
+    pub phone_number: Option<PhoneNumber>,
+    pub code_channel: CodeChannel,
+    /// Set once the user followed the link emailed at signup.
+    pub email_verified: bool,
+}
+
+/// How a user proves the second factor at `/verify-2fa`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TwoFAMethod {
+    Email,
+    Sms,
+    Totp,
+}
+
+impl TwoFAMethod {
+    pub fn parse(s: &str) -> Result<TwoFAMethod, String> {
+        match s {
+            "email" => Ok(Self::Email),
+            "sms" => Ok(Self::Sms),
+            "totp" => Ok(Self::Totp),
+            _ => Err(format!("{} is not a 2FA method.", s)),
+        }
+    }
+}
+
+impl AsRef<str> for TwoFAMethod {
+    fn as_ref(&self) -> &str {
+        match self {
+            TwoFAMethod::Email => "email",
+            TwoFAMethod::Sms => "sms",
+            TwoFAMethod::Totp => "totp",
+        }
+    }
+}
+
+/// Where a user prefers 2FA codes we generate to be sent.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CodeChannel {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl CodeChannel {
+    pub fn parse(s: &str) -> Result<CodeChannel, String> {
+        match s {
+            "email" => Ok(Self::Email),
+            "sms" => Ok(Self::Sms),
+            _ => Err(format!("{} is not a code channel.", s)),
+        }
+    }
+}
+
+impl AsRef<str> for CodeChannel {
+    fn as_ref(&self) -> &str {
+        match self {
+            CodeChannel::Email => "email",
+            CodeChannel::Sms => "sms",
+        }
+    }
 }
 
 impl User {
@@ -13,6 +74,18 @@ impl User {
             email,
             password,
             requires_2fa,
+            phone_number: None,
+            code_channel: CodeChannel::default(),
+            email_verified: false,
+        }
+    }
+
+    /// How codes we generate reach the user. Texts need a verified phone
+    /// number, so without one they fall back to email.
+    pub fn code_method(&self) -> TwoFAMethod {
+        match (self.code_channel, &self.phone_number) {
+            (CodeChannel::Sms, Some(_)) => TwoFAMethod::Sms,
+            _ => TwoFAMethod::Email,
         }
     }
 }
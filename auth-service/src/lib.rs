@@ -1,18 +1,25 @@
-use std::error::Error;
+use std::{error::Error, net::SocketAddr};
 
 use app_state::AppState;
 use axum::{
-    http::{Method, StatusCode},
+    http::{header::WWW_AUTHENTICATE, Method, StatusCode},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    middleware::AddExtension,
     serve::Serve,
     Json, Router,
 };
-use domain::AuthAPIError;
+use domain::{AuthAPIError, OAuthError};
 use redis::{Client, RedisResult};
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, PgPool};
 use tower_http::{cors::CorsLayer, services::ServeDir};
+use utils::{
+    auth::KEY_RING,
+    constants::{JWT_ISSUER, OIDC_ENABLED},
+    oidc::check_oidc_config,
+};
 
 pub mod app_state;
 pub mod domain;
@@ -21,7 +28,10 @@ pub mod services;
 pub mod utils;
 
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     pub address: String,
 }
 
@@ -36,21 +46,75 @@ impl Application {
         let cors = CorsLayer::new()
             .allow_origin(allowed_origins)
             .allow_credentials(true)
-            .allow_methods([Method::GET, Method::POST]);
+            .allow_methods([Method::GET, Method::POST, Method::DELETE]);
         
-        let router = Router::new()
+        let mut router = Router::new()
             .nest_service("/", ServeDir::new("assets"))
             .route("/verify-token", post(routes::verify_token))
+            .route("/introspect", post(routes::introspect))
+            .route("/authorize", get(routes::authorize))
+            .route("/token", post(routes::token))
             .route("/signup", post(routes::signup))
             .route("/login", post(routes::login))
             .route("/logout", post(routes::logout))
+            .route("/logout-all", post(routes::logout_all))
             .route("/verify-2fa", post(routes::verify_2fa))
-            .with_state(app_state)
-            .layer(cors);
+            .route(
+                "/verify-email",
+                get(routes::verify_email_link).post(routes::verify_email),
+            )
+            .route("/resend-2fa", post(routes::resend_2fa))
+            .route("/forgot-password", post(routes::forgot_password))
+            .route("/reset-password", post(routes::reset_password))
+            .route("/change-password", post(routes::change_password))
+            .route("/change-email", post(routes::change_email))
+            .route(
+                "/change-email/confirm",
+                get(routes::confirm_email_change_link).post(routes::confirm_email_change_request),
+            )
+            .route("/2fa/enable", post(routes::enable_2fa))
+            .route("/2fa/disable", post(routes::disable_2fa))
+            .route("/2fa/method", post(routes::change_2fa_method))
+            .route("/2fa/totp/enroll", post(routes::enroll_totp))
+            .route("/2fa/totp/confirm", post(routes::confirm_totp))
+            .route("/phone-number", post(routes::add_phone_number))
+            .route("/phone-number/verify", post(routes::verify_phone_number))
+            .route("/passkeys/register/start", post(routes::start_passkey_registration))
+            .route("/passkeys/register/finish", post(routes::finish_passkey_registration))
+            .route("/passkeys/login/start", post(routes::start_passkey_login))
+            .route("/passkeys/login/finish", post(routes::finish_passkey_login))
+            .route(
+                "/2fa/recovery-codes",
+                post(routes::regenerate_recovery_codes),
+            )
+            .route("/refresh", post(routes::refresh))
+            .route("/sessions", get(routes::list_sessions))
+            .route("/sessions/:id", delete(routes::revoke_session))
+            .route("/trusted-devices", get(routes::list_trusted_devices))
+            .route("/trusted-devices/:id", delete(routes::revoke_trusted_device))
+            .route("/.well-known/jwks.json", get(routes::jwks));
+
+        // Refuse to start as an OpenID provider that relying parties can't
+        // verify
+        if *OIDC_ENABLED {
+            check_oidc_config(KEY_RING.signing_key().algorithm(), &JWT_ISSUER)?;
+            router = router
+                .route("/userinfo", get(routes::userinfo).post(routes::userinfo))
+                .route(
+                    "/.well-known/openid-configuration",
+                    get(routes::openid_configuration),
+                );
+        }
+
+        let router = router.with_state(app_state).layer(cors);
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // Connection info lets sessions record the client's address
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         Ok(Application { server, address })
     }
@@ -69,6 +133,8 @@ pub struct ErrorResponse {
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
+        // Clients authenticate with HTTP Basic, so tell them when they fail to
+        let challenge_client = matches!(self, AuthAPIError::InvalidClient);
         let (status, error_message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
@@ -79,14 +145,62 @@ impl IntoResponse for AuthAPIError {
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
             AuthAPIError::MalformedToken => (StatusCode::UNPROCESSABLE_ENTITY, "Malformed Token"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::DeviceNotFound => (StatusCode::NOT_FOUND, "Device not found"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email address not verified"),
+            AuthAPIError::PhoneNumberNotVerified => {
+                (StatusCode::BAD_REQUEST, "Phone number not verified")
+            }
+            AuthAPIError::InvalidClient => (StatusCode::UNAUTHORIZED, "Invalid client"),
+            AuthAPIError::PasskeyAlreadyExists => (StatusCode::CONFLICT, "Passkey already registered"),
+            AuthAPIError::TooManyAttempts => {
+                (StatusCode::TOO_MANY_REQUESTS, "Too many failed attempts, please log in again")
+            }
+            AuthAPIError::ResendTooSoon => {
+                (StatusCode::TOO_MANY_REQUESTS, "Please wait before requesting another code")
+            }
+            AuthAPIError::UnknownAudience => (StatusCode::BAD_REQUEST, "Unknown audience"),
+            AuthAPIError::TwoFANotEnabled => {
+                (StatusCode::BAD_REQUEST, "Two-factor authentication is not enabled")
+            }
+            AuthAPIError::TooManyResends => {
+                (StatusCode::TOO_MANY_REQUESTS, "Too many codes requested, please log in again")
+            }
         };
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
         });
+        if challenge_client {
+            return (status, [(WWW_AUTHENTICATE, "Basic")], body).into_response();
+        }
         (status, body).into_response()
     }
 }
 
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            OAuthError::InvalidClient | OAuthError::InvalidToken => StatusCode::UNAUTHORIZED,
+            OAuthError::InsufficientScope => StatusCode::FORBIDDEN,
+            OAuthError::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        let challenge = match self {
+            OAuthError::InvalidClient => Some("Basic".to_owned()),
+            OAuthError::InvalidToken | OAuthError::InsufficientScope => {
+                Some(format!("Bearer error=\"{}\"", self.code()))
+            }
+            _ => None,
+        };
+        let body = Json(ErrorResponse {
+            error: self.code().to_owned(),
+        });
+        match challenge {
+            Some(challenge) => (status, [(WWW_AUTHENTICATE, challenge)], body).into_response(),
+            None => (status, body).into_response(),
+        }
+    }
+}
 
 pub async fn get_postgres_pool(url: &str) -> Result<PgPool, sqlx::Error> {
     PgPoolOptions::new().max_connections(5).connect(url).await
@@ -3,9 +3,9 @@ use sqlx::PgPool;
 use tokio::sync::RwLock;
 
 use auth_service::{
-    app_state::{AppState, EmailClientType}, get_postgres_pool, get_redis_client, services::{
-        mock_email_client::MockEmailClient, postgres_user_store::PostgresUserStore, redis_banned_token_store::RedisBannedTokenStore, redis_two_fa_code_store::RedisTwoFACodeStore
-    }, utils::constants::{prod, DATABASE_URL, REDIS_HOST_NAME}, Application
+    app_state::{AppState, EmailClientType, SmsClientType}, get_postgres_pool, get_redis_client, services::{
+        http_sms_client::HttpSmsClient, mock_email_client::MockEmailClient, mock_sms_client::MockSmsClient, postgres_client_store::PostgresClientStore, postgres_passkey_store::PostgresPasskeyStore, postgres_session_store::PostgresSessionStore, postgres_totp_store::PostgresTotpStore, postgres_trusted_device_store::PostgresTrustedDeviceStore, postgres_user_store::PostgresUserStore, redis_authorization_code_store::RedisAuthorizationCodeStore, redis_banned_token_store::RedisBannedTokenStore, redis_email_token_store::RedisEmailTokenStore, redis_passkey_challenge_store::RedisPasskeyChallengeStore, redis_refresh_token_store::RedisRefreshTokenStore, redis_session_epoch_store::RedisSessionEpochStore, redis_two_fa_code_store::RedisTwoFACodeStore
+    }, utils::{auth::KEY_RING, constants::{prod, DATABASE_URL, REDIS_HOST_NAME, SMS_PROVIDER_AUTH_TOKEN, SMS_PROVIDER_TIMEOUT_MILLISECONDS, SMS_PROVIDER_URL, SMS_SENDER}}, Application
 };
 
 fn configure_redis() -> redis::Connection {
@@ -17,18 +17,51 @@ fn configure_redis() -> redis::Connection {
 
 #[tokio::main]
 async fn main() {
+    // Fail fast on a misconfigured signing key rather than on the first login
+    lazy_static::initialize(&KEY_RING);
+
     let pg_pool = configure_postgresql().await;
 
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+    let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.clone())));
+    let client_store = Arc::new(RwLock::new(PostgresClientStore::new(pg_pool.clone())));
+    let totp_store = Arc::new(RwLock::new(PostgresTotpStore::new(pg_pool.clone())));
+    let passkey_store = Arc::new(RwLock::new(PostgresPasskeyStore::new(pg_pool.clone())));
+    let trusted_device_store = Arc::new(RwLock::new(PostgresTrustedDeviceStore::new(pg_pool)));
     let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
     let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let refresh_token_store = Arc::new(RwLock::new(RedisRefreshTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let session_epoch_store = Arc::new(RwLock::new(RedisSessionEpochStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let authorization_code_store = Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let passkey_challenge_store = Arc::new(RwLock::new(RedisPasskeyChallengeStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let email_token_store = Arc::new(RwLock::new(RedisEmailTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
     let email_client: EmailClientType = Arc::new(MockEmailClient {});
+    let sms_client: SmsClientType = match SMS_PROVIDER_URL.as_ref() {
+        Some(url) => Arc::new(HttpSmsClient::new(
+            url.to_owned(),
+            SMS_SENDER.to_owned(),
+            SMS_PROVIDER_AUTH_TOKEN.to_owned(),
+            std::time::Duration::from_millis(SMS_PROVIDER_TIMEOUT_MILLISECONDS),
+        )),
+        None => Arc::new(MockSmsClient {}),
+    };
 
     let app_state = AppState::new(
         user_store,
         banned_token_store,
         two_fa_code_store,
+        refresh_token_store,
+        session_epoch_store,
+        session_store,
+        client_store,
+        authorization_code_store,
+        totp_store,
+        passkey_store,
+        passkey_challenge_store,
+        trusted_device_store,
+        email_token_store,
         email_client,
+        sms_client,
     );
 
     let app = Application::build(app_state, prod::APP_ADDRESS)
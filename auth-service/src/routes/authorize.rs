@@ -0,0 +1,155 @@
+use axum::{
+    extract::{Query, RawQuery, State},
+    response::Redirect,
+};
+use serde::Deserialize;
+use url::{form_urlencoded, Url};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthorizationCode, AuthorizationGrant, OAuthError},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        client_info::ClientInfo,
+        constants::OIDC_ENABLED,
+        oidc::{has_scope, is_supported_scope, OPENID_SCOPE},
+    },
+};
+
+/// OAuth 2.0 and OpenID Connect authorization endpoint (authorization code
+/// grant with mandatory PKCE S256). Users who are not logged in are sent to the
+/// login UI, which brings them back here once they are.
+///
+/// There is no consent screen: a logged-in user is sent straight back with a
+/// code. That is only safe for our own apps, so third-party clients are
+/// turned away with `unauthorized_client`.
+pub async fn authorize(
+    State(state): State<AppState>,
+    user: Option<AuthenticatedUser>,
+    client: ClientInfo,
+    RawQuery(query): RawQuery,
+    Query(request): Query<AuthorizeRequest>,
+) -> Result<Redirect, OAuthError> {
+    // Until the redirect URI is known to belong to the client, errors are
+    // shown to the user rather than sent to a URI we can't trust
+    let client_id = request.client_id.ok_or(OAuthError::InvalidRequest)?;
+    let registered_client = state
+        .client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+        .map_err(|_| OAuthError::InvalidRequest)?;
+
+    let redirect_uri = request
+        .redirect_uri
+        .filter(|uri| registered_client.redirect_uris.contains(uri))
+        .ok_or(OAuthError::InvalidRequest)?;
+    let oauth_state = request.state.as_deref();
+
+    if !registered_client.first_party {
+        return redirect_back(
+            &redirect_uri,
+            &[("error", OAuthError::UnauthorizedClient.code())],
+            oauth_state,
+        );
+    }
+
+    if request.response_type.as_deref() != Some("code") {
+        return redirect_back(
+            &redirect_uri,
+            &[("error", OAuthError::UnsupportedResponseType.code())],
+            oauth_state,
+        );
+    }
+
+    let scope = request.scope.as_deref();
+    if !scope.is_none_or(is_supported_scope) || (!*OIDC_ENABLED && has_scope(scope, OPENID_SCOPE)) {
+        return redirect_back(
+            &redirect_uri,
+            &[("error", OAuthError::InvalidScope.code())],
+            oauth_state,
+        );
+    }
+
+    let code_challenge = match (request.code_challenge, request.code_challenge_method.as_deref()) {
+        (Some(challenge), Some("S256")) if challenge.len() == PKCE_S256_CHALLENGE_LENGTH => {
+            challenge
+        }
+        _ => {
+            return redirect_back(
+                &redirect_uri,
+                &[("error", OAuthError::InvalidRequest.code())],
+                oauth_state,
+            )
+        }
+    };
+
+    let Some(user) = user else {
+        let next = format!("/authorize?{}", query.unwrap_or_default());
+        let login_query = form_urlencoded::Serializer::new(String::new())
+            .append_pair("next", &next)
+            .finish();
+        return Ok(Redirect::to(&format!("/?{}", login_query)));
+    };
+
+    let code = AuthorizationCode::default();
+    let grant = AuthorizationGrant {
+        client_id: registered_client.id,
+        redirect_uri: redirect_uri.clone(),
+        code_challenge,
+        scope: request.scope,
+        nonce: request.nonce,
+        email: user.email,
+        ip_address: client.ip_address,
+        user_agent: client.user_agent,
+    };
+
+    if state
+        .authorization_code_store
+        .write()
+        .await
+        .add_code(code.clone(), grant)
+        .await
+        .is_err()
+    {
+        return redirect_back(
+            &redirect_uri,
+            &[("error", OAuthError::ServerError.code())],
+            oauth_state,
+        );
+    }
+
+    redirect_back(&redirect_uri, &[("code", code.as_ref())], oauth_state)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeRequest {
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub nonce: Option<String>,
+}
+
+/// Length of a base64url-encoded SHA-256 digest without padding.
+const PKCE_S256_CHALLENGE_LENGTH: usize = 43;
+
+fn redirect_back(
+    redirect_uri: &str,
+    params: &[(&str, &str)],
+    oauth_state: Option<&str>,
+) -> Result<Redirect, OAuthError> {
+    let mut url = Url::parse(redirect_uri).map_err(|_| OAuthError::InvalidRequest)?;
+    {
+        let mut query = url.query_pairs_mut();
+        query.extend_pairs(params);
+        if let Some(oauth_state) = oauth_state {
+            query.append_pair("state", oauth_state);
+        }
+    }
+    Ok(Redirect::to(url.as_str()))
+}
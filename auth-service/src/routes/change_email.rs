@@ -0,0 +1,223 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, EmailToken, EmailTokenPurpose, EmailTokenStoreError, UserStoreError,
+    },
+    utils::{authenticated_user::AuthenticatedUser, constants::PUBLIC_URL},
+};
+
+use super::two_fa_settings::confirm_password;
+
+/// Long enough for the email to be read the next day.
+const EMAIL_CHANGE_TTL_SECONDS: u64 = 24 * 60 * 60;
+
+/// Starts moving the account to `newEmail`. Nothing changes until the link
+/// emailed there is followed; the old address is told about the request.
+pub async fn change_email(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let new_email =
+        Email::parse(request.new_email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    if new_email == email {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    confirm_password(&email, request.password, &state).await?;
+
+    match state.user_store.read().await.get_user(&new_email).await {
+        Ok(_) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(UserStoreError::UserNotFound) => {}
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    // The token maps back to the current address; the users row remembers
+    // which one was sent last, so only the newest link can commit a change
+    let token = EmailToken::default();
+    state
+        .email_token_store
+        .write()
+        .await
+        .add_token(
+            token.clone(),
+            email.clone(),
+            EmailTokenPurpose::ChangeEmail,
+            EMAIL_CHANGE_TTL_SECONDS,
+        )
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    state
+        .user_store
+        .write()
+        .await
+        .set_pending_email(&email, new_email.clone(), &token)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let content = format!(
+        "Confirm your new email address by opening {}/change-email/confirm?token={}",
+        *PUBLIC_URL,
+        token.as_ref()
+    );
+    state
+        .email_client
+        .send_email(&new_email, "Confirm your new email address", &content)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let content = format!(
+        "Someone asked to move your account to {}. It only happens once the link \
+         sent there is opened. If this wasn't you, change your password right away.",
+        new_email.as_ref()
+    );
+    state
+        .email_client
+        .send_email(&email, "Email change requested", &content)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Where the link emailed to the new address lands. Browsers open it with GET.
+pub async fn confirm_email_change_link(
+    State(state): State<AppState>,
+    Query(request): Query<ConfirmEmailChangeRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    confirm_email_change(request.token, &state).await?;
+
+    Ok((
+        StatusCode::OK,
+        "Your email address is changed. Log in again with the new one.",
+    ))
+}
+
+/// Same as following the link, for clients that take the token themselves.
+pub async fn confirm_email_change_request(
+    State(state): State<AppState>,
+    Json(request): Json<ConfirmEmailChangeRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    confirm_email_change(request.token, &state).await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn confirm_email_change(token: String, state: &AppState) -> Result<(), AuthAPIError> {
+    let token = EmailToken::parse(token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let email = match state
+        .email_token_store
+        .write()
+        .await
+        .take_token(&token, EmailTokenPurpose::ChangeEmail)
+        .await
+    {
+        Ok(email) => email,
+        Err(EmailTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(EmailTokenStoreError::UnexpectedError) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    let new_email = {
+        let mut user_store = state.user_store.write().await;
+        let new_email = match user_store.change_email(&email, &token).await {
+            Ok(new_email) => new_email,
+            // Superseded by a later request, or the account is gone
+            Err(UserStoreError::InvalidCredentials | UserStoreError::UserNotFound) => {
+                return Err(AuthAPIError::InvalidToken)
+            }
+            Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+            Err(_) => return Err(AuthAPIError::UnexpectedError),
+        };
+        // Following the link proved the user reads mail at the address
+        user_store.mark_email_verified(&new_email).await.ok();
+        new_email
+    };
+
+    // The account has moved, so nothing below fails the request. Logins for
+    // the old address can't start new sessions from here on, see
+    // `record_session`.
+    //
+    // Tokens carry the old address, so every session is logged out rather
+    // than migrated. Either the epoch or the revoked session is enough to
+    // stop them, and refresh tokens go with both. Sessions and trusted
+    // devices are revoked under both addresses, as stores keyed by email may
+    // or may not have carried them over.
+    state
+        .session_epoch_store
+        .write()
+        .await
+        .increment_epoch(&email)
+        .await
+        .ok();
+    {
+        let mut session_store = state.session_store.write().await;
+        session_store.revoke_all_sessions(&email).await.ok();
+        session_store.revoke_all_sessions(&new_email).await.ok();
+    }
+    {
+        let mut trusted_device_store = state.trusted_device_store.write().await;
+        trusted_device_store.revoke_all_devices(&email).await.ok();
+        trusted_device_store
+            .revoke_all_devices(&new_email)
+            .await
+            .ok();
+    }
+
+    state
+        .totp_store
+        .write()
+        .await
+        .change_email(&email, &new_email)
+        .await
+        .ok();
+    state
+        .passkey_store
+        .write()
+        .await
+        .change_email(&email, &new_email)
+        .await
+        .ok();
+
+    let content = format!(
+        "Your account was moved to {} and every device was logged out. \
+         If this wasn't you, contact support right away.",
+        new_email.as_ref()
+    );
+    state
+        .email_client
+        .send_email(&email, "Email address changed", &content)
+        .await
+        .ok();
+    state
+        .email_client
+        .send_email(
+            &new_email,
+            "Email address changed",
+            "Your account now uses this email address. Log in again with it.",
+        )
+        .await
+        .ok();
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct ChangeEmailRequest {
+    pub password: String,
+    #[serde(rename = "newEmail")]
+    pub new_email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmEmailChangeRequest {
+    pub token: String,
+}
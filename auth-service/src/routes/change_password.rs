@@ -0,0 +1,176 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, Password},
+    routes::TokenResponse,
+    utils::{
+        auth::{generate_auth_cookie, generate_refresh_cookie, Claims},
+        authenticated_user::AuthenticatedUser,
+    },
+};
+
+use super::two_fa_settings::confirm_password;
+
+/// Changes the password of a logged-in user who knows the current one.
+/// With `revokeOtherSessions`, every other device is logged out and the
+/// session making the change gets new tokens to stay logged in.
+pub async fn change_password(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    AuthenticatedUser { email, claims, .. }: AuthenticatedUser,
+    Json(request): Json<ChangePasswordRequest>,
+) -> (CookieJar, Result<Response, AuthAPIError>) {
+    let new_password = match Password::parse(request.new_password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    if let Err(e) = confirm_password(&email, request.current_password, &state).await {
+        return (jar, Err(e));
+    }
+
+    if state
+        .user_store
+        .write()
+        .await
+        .update_password(&email, new_password)
+        .await
+        .is_err()
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let cookies = if request.revoke_other_sessions {
+        match log_out_other_sessions(&email, &claims, &state).await {
+            Ok(cookies) => Some(cookies),
+            Err(e) => return (jar, Err(e)),
+        }
+    } else {
+        None
+    };
+
+    // The password is already changed, so a lost notice doesn't fail the request
+    state
+        .email_client
+        .send_email(
+            &email,
+            "Password changed",
+            "The password for your account was changed. \
+             If this wasn't you, reset it right away.",
+        )
+        .await
+        .ok();
+
+    let Some((auth_cookie, refresh_cookie)) = cookies else {
+        return (jar, Ok(StatusCode::OK.into_response()));
+    };
+
+    let token = TokenResponse {
+        token: auth_cookie.value().to_owned(),
+        refresh_token: refresh_cookie.value().to_owned(),
+    };
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    if request.return_token {
+        return (
+            updated_jar,
+            Ok((StatusCode::OK, Json(token)).into_response()),
+        );
+    }
+
+    (updated_jar, Ok(StatusCode::OK.into_response()))
+}
+
+/// Bumps the session epoch so every token issued so far stops working and
+/// revokes every session but the current one. Returns fresh auth and
+/// refresh cookies for the current session.
+async fn log_out_other_sessions(
+    email: &Email,
+    claims: &Claims,
+    state: &AppState,
+) -> Result<(Cookie<'static>, Cookie<'static>), AuthAPIError> {
+    let epoch = state
+        .session_epoch_store
+        .write()
+        .await
+        .increment_epoch(email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    revoke_other_sessions(email, &claims.jti, state).await?;
+
+    let auth_cookie = generate_auth_cookie(email, epoch, &claims.jti, &claims.aud)
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    let refresh_cookie = generate_refresh_cookie(
+        email,
+        &claims.jti,
+        epoch,
+        &claims.aud,
+        state.refresh_token_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok((auth_cookie, refresh_cookie))
+}
+
+/// Revokes every session but `current_session_id` the way `DELETE
+/// /sessions/:id` would, so their access and refresh tokens stop working.
+async fn revoke_other_sessions(
+    email: &Email,
+    current_session_id: &str,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .list_sessions(email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    for session in sessions
+        .into_iter()
+        .filter(|session| session.id != current_session_id)
+    {
+        state
+            .session_store
+            .write()
+            .await
+            .revoke_session(email, &session.id)
+            .await
+            .map_err(|_| AuthAPIError::UnexpectedError)?;
+        // The session id doubles as its refresh token family
+        state
+            .refresh_token_store
+            .write()
+            .await
+            .revoke_family(&session.id)
+            .await
+            .map_err(|_| AuthAPIError::UnexpectedError)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+    #[serde(rename = "revokeOtherSessions", default)]
+    pub revoke_other_sessions: bool,
+    /// With `revoke_other_sessions`, respond with the new tokens in the
+    /// body, for callers that don't use cookies.
+    #[serde(rename = "returnToken", default)]
+    pub return_token: bool,
+}
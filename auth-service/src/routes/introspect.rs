@@ -0,0 +1,85 @@
+use axum::{extract::State, Form, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    utils::{
+        auth::{validate_token, ExpectedAudience},
+        authenticated_client::AuthenticatedClient,
+    },
+};
+
+/// Token introspection (RFC 7662). Lets registered services learn who a
+/// token belongs to without decoding it themselves. Invalid, expired,
+/// banned and revoked tokens are all reported as `{"active": false}`.
+pub async fn introspect(
+    State(state): State<AppState>,
+    _client: AuthenticatedClient,
+    Form(request): Form<IntrospectionRequest>,
+) -> Json<IntrospectionResponse> {
+    let claims = match validate_token(
+        &request.token,
+        ExpectedAudience::Any,
+        state.banned_token_store.clone(),
+        state.session_epoch_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => claims,
+        Err(_) => return Json(IntrospectionResponse::default()),
+    };
+
+    // Client credentials tokens aren't tied to a login session
+    let sid = (!claims.is_client_token()).then(|| claims.jti.clone());
+
+    Json(IntrospectionResponse {
+        active: true,
+        token_type: Some("Bearer".to_owned()),
+        scope: claims.scope,
+        client_id: claims.client_id,
+        sub: Some(claims.sub),
+        iss: Some(claims.iss),
+        aud: Some(claims.aud),
+        exp: Some(claims.exp),
+        iat: Some(claims.iat),
+        nbf: Some(claims.nbf),
+        sid,
+        jti: Some(claims.jti),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    /// Accepted for compatibility; only access tokens can be introspected.
+    pub token_type_hint: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Id of the session the token belongs to, as listed by `/sessions`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
@@ -0,0 +1,10 @@
+use axum::Json;
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::utils::auth::KEY_RING;
+
+/// Publishes the public half of every key in the key ring so relying
+/// services can verify tokens locally. HMAC keys are never published.
+pub async fn jwks() -> Json<JwkSet> {
+    Json(KEY_RING.jwks())
+}
@@ -1,15 +1,28 @@
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuthAPIError, Email, LoginAttemptId, Password, TotpStoreError, TrustedDeviceStoreError,
+        TwoFACode, TwoFAMethod, User,
+    },
+    utils::{
+        auth::{
+            first_party_audience, generate_pre_auth_cookie, start_session,
+            validate_trusted_device_token,
+        },
+        client_info::ClientInfo,
+        constants::TRUSTED_DEVICE_COOKIE_NAME,
+    },
 };
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
 use serde::{Deserialize, Serialize};
 
+use super::verify_email::send_verification_email;
+
 pub async fn login(
     State(state): State<AppState>,
     jar: CookieJar,
+    client: ClientInfo,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let email = match Email::parse(request.email.clone()) {
@@ -22,32 +35,98 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let user_store = &state.user_store.read().await;
+    let audience = match first_party_audience(request.audience.as_deref()) {
+        Some(audience) => audience,
+        None => return (jar, Err(AuthAPIError::UnknownAudience)),
+    };
+
+    // Not held any longer, as the steps below take other stores' locks
+    let user = {
+        let user_store = state.user_store.read().await;
 
-    if user_store.validate_user(&email, &password).await.is_err() {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
-    }
+        if user_store.validate_user(&email, &password).await.is_err() {
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
 
-    let user = match user_store.get_user(&email).await {
-        Ok(user) => user,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        match user_store.get_user(&email).await {
+            Ok(user) => user,
+            Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        }
     };
 
-    let auth_cookie = match generate_auth_cookie(&user.email) {
-        Ok(cookie) => cookie,
+    // Only after the password, so this doesn't reveal which addresses
+    // have accounts. A fresh link helps if the first one got lost.
+    if !user.email_verified {
+        if let Err(e) = send_verification_email(&user.email, &state).await {
+            return (jar, Err(e));
+        }
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
+    // Enrolling an authenticator app turns 2FA on
+    let two_fa_method = match state.totp_store.read().await.get_secret(&user.email).await {
+        Ok(_) => Some(TwoFAMethod::Totp),
+        Err(TotpStoreError::SecretNotFound) => user.requires_2fa.then(|| user.code_method()),
         Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let two_fa_method = match two_fa_method {
+        Some(method) => match is_trusted_device(&user.email, &jar, &state).await {
+            Ok(true) => None,
+            Ok(false) => Some(method),
+            Err(e) => return (jar, Err(e)),
+        },
+        None => None,
+    };
 
-    match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, updated_jar.clone()).await,
-        false => handle_no_2fa(&user.email, updated_jar.clone()).await,
+    match two_fa_method {
+        Some(method) => handle_2fa(&user, method, request.return_token, &state, jar).await,
+        None => {
+            handle_no_2fa(
+                &user.email,
+                &audience,
+                request.return_token,
+                client,
+                &state,
+                jar,
+            )
+            .await
+        }
     }
 }
 
-async fn handle_2fa(
+/// Whether the request carries a device cookie the user still trusts.
+async fn is_trusted_device(
     email: &Email,
+    jar: &CookieJar,
+    state: &AppState,
+) -> Result<bool, AuthAPIError> {
+    let claims = match jar
+        .get(TRUSTED_DEVICE_COOKIE_NAME)
+        .map(|cookie| validate_trusted_device_token(cookie.value()))
+    {
+        Some(Ok(claims)) => claims,
+        // A missing, forged or expired cookie just means asking for 2FA
+        _ => return Ok(false),
+    };
+
+    match state
+        .trusted_device_store
+        .read()
+        .await
+        .get_device(&claims.jti)
+        .await
+    {
+        Ok(device) => Ok(device.email == *email),
+        Err(TrustedDeviceStoreError::DeviceNotFound) => Ok(false),
+        Err(TrustedDeviceStoreError::UnexpectedError) => Err(AuthAPIError::UnexpectedError),
+    }
+}
+
+async fn handle_2fa(
+    user: &User,
+    method: TwoFAMethod,
+    return_token: bool,
     state: &AppState,
     jar: CookieJar,
 ) -> (
@@ -55,41 +134,108 @@ async fn handle_2fa(
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
     let login_attempt_id = LoginAttemptId::default();
-    let two_fa_code = TwoFACode::default();
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
-    if let Err(_) = two_fa_code_store
-        .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError));
+    if let Err(e) = send_code(user, method, &login_attempt_id, state).await {
+        return (jar, Err(e));
     }
 
-    let email_client = state.email_client.clone();
-    if let Err(_) = email_client
-        .send_email(email, "2FA required", &two_fa_code.as_ref())
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError));
-    }
+    // No session yet: only a token that lets this login attempt reach /verify-2fa
+    let pre_auth_cookie = match generate_pre_auth_cookie(&user.email, &login_attempt_id) {
+        Ok(cookie) => cookie,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+
+    let pre_auth_token = return_token.then(|| pre_auth_cookie.value().to_owned());
+    let updated_jar = jar.add(pre_auth_cookie);
+
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
         message: "2FA required".to_owned(),
         login_attempt_id: login_attempt_id.as_ref().to_string(),
+        method: method.as_ref().to_owned(),
+        pre_auth_token,
     }));
 
-    (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
+    (updated_jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
+}
+
+async fn send_code(
+    user: &User,
+    method: TwoFAMethod,
+    login_attempt_id: &LoginAttemptId,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let two_fa_code = TwoFACode::default();
+
+    state
+        .two_fa_code_store
+        .write()
+        .await
+        .add_code(
+            user.email.clone(),
+            login_attempt_id.clone(),
+            two_fa_code.clone(),
+        )
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    // Authenticator apps make their own codes. The stored one is never sent
+    // and only counts wrong guesses against the login attempt.
+    if method == TwoFAMethod::Totp {
+        return Ok(());
+    }
+
+    deliver_code(user, method, &two_fa_code, state).await
+}
+
+/// Sends a 2FA code by email or text, as `method` says.
+pub(crate) async fn deliver_code(
+    user: &User,
+    method: TwoFAMethod,
+    two_fa_code: &TwoFACode,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let result = match (method, &user.phone_number) {
+        (TwoFAMethod::Sms, Some(phone_number)) => {
+            let content = format!("Your verification code is {}", two_fa_code.as_ref());
+            state.sms_client.send_sms(phone_number, &content).await
+        }
+        (TwoFAMethod::Email, _) => {
+            state
+                .email_client
+                .send_email(&user.email, "2FA required", two_fa_code.as_ref())
+                .await
+        }
+        _ => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    result.map_err(|_| AuthAPIError::UnexpectedError)
 }
 
 async fn handle_no_2fa(
     email: &Email,
+    audience: &str,
+    return_token: bool,
+    client: ClientInfo,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
+    let (auth_cookie, refresh_cookie) = match start_session(email, audience, client, state).await {
+        Ok(cookies) => cookies,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+
+    let token = return_token.then(|| TokenResponse {
+        token: auth_cookie.value().to_owned(),
+        refresh_token: refresh_cookie.value().to_owned(),
+    });
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
     (
-        jar,
-        Ok((StatusCode::OK, axum::Json(LoginResponse::RegularAuth))),
+        updated_jar,
+        Ok((StatusCode::OK, axum::Json(LoginResponse::RegularAuth(token)))),
     )
 }
 
@@ -97,6 +243,14 @@ async fn handle_no_2fa(
 pub struct LoginRequest {
     email: String,
     password: String,
+    /// Also return the token in the response body, for clients that can't
+    /// use cookies.
+    #[serde(default, rename = "returnToken")]
+    return_token: bool,
+    /// App the session is for, one of `JWT_AUDIENCE`. Defaults to the
+    /// first of them.
+    #[serde(default)]
+    audience: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -109,7 +263,7 @@ pub struct TwoFAResponse {
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum LoginResponse {
-    RegularAuth,
+    RegularAuth(Option<TokenResponse>),
     TwoFactorAuth(TwoFactorAuthResponse),
 }
 
@@ -118,4 +272,23 @@ pub struct TwoFactorAuthResponse {
     pub message: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    /// Where the code comes from: `email`, `sms` or `totp`.
+    pub method: String,
+    /// Stands in for the pre-auth cookie when the client asked for tokens
+    /// in the body. Send it to `/verify-2fa` as a bearer token.
+    #[serde(
+        rename = "preAuthToken",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pre_auth_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub token: String,
+    /// Send it to `/refresh` in the body in place of the refresh token
+    /// cookie.
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
 }
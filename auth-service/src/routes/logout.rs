@@ -3,40 +3,62 @@ use axum_extra::extract::{cookie, CookieJar};
 
 use crate::{
     app_state::AppState,
-    domain::AuthAPIError,
-    utils::{auth::{validate_token}, constants::JWT_COOKIE_NAME},
+    domain::{AuthAPIError, SessionStoreError},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
 };
 
 pub async fn logout(
     State(state): State<AppState>,
     jar: CookieJar,
+    AuthenticatedUser {
+        email,
+        claims,
+        token,
+    }: AuthenticatedUser,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    
-    let cookie = match jar.get(JWT_COOKIE_NAME) {
-        Some(cookie) => cookie,
-        None => return (jar, Err(AuthAPIError::MissingToken)),
-    };
+    if state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(token)
+        .await
+        .is_err()
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
 
-    let token = cookie.value().to_owned();
+    // End the session and its refresh token family so it can't be revived
+    match state
+        .session_store
+        .write()
+        .await
+        .revoke_session(&email, &claims.jti)
+        .await
+    {
+        Ok(()) | Err(SessionStoreError::SessionNotFound) => {}
+        Err(SessionStoreError::UnexpectedError) => {
+            return (jar, Err(AuthAPIError::UnexpectedError));
+        }
+    }
 
-    let _ = match validate_token(&token, state.banned_token_store.clone()).await {
-        Ok(claims) => claims,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
-    };
-    
     if state
-        .banned_token_store
+        .refresh_token_store
         .write()
         .await
-        .add_token(token.to_owned())
+        .revoke_family(&claims.jti)
         .await
         .is_err()
     {
         return (jar, Err(AuthAPIError::UnexpectedError));
     }
 
-    // Remove jwt cookie
-    let jar = jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME));
+    // Remove jwt and refresh token cookies
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
 
     (jar, Ok(StatusCode::OK))
 }
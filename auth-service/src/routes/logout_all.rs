@@ -0,0 +1,47 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::{cookie, CookieJar};
+
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
+};
+
+/// Logs the user out on every device by bumping their session epoch, which
+/// invalidates all access and refresh tokens issued so far.
+pub async fn logout_all(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    if state
+        .session_epoch_store
+        .write()
+        .await
+        .increment_epoch(&email)
+        .await
+        .is_err()
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    if state
+        .session_store
+        .write()
+        .await
+        .revoke_all_sessions(&email)
+        .await
+        .is_err()
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+
+    (jar, Ok(StatusCode::OK))
+}
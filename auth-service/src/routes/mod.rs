@@ -1,11 +1,51 @@
+mod authorize;
+mod change_email;
+mod change_password;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod openid_configuration;
+mod passkeys;
+mod phone_number;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
+mod reset_password;
+mod sessions;
 mod signup;
+mod token;
+mod totp;
+mod trusted_devices;
+mod two_fa_settings;
+mod userinfo;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
 
+pub use authorize::*;
+pub use change_email::*;
+pub use change_password::*;
+pub use introspect::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use logout_all::*;
+pub use openid_configuration::*;
+pub use passkeys::*;
+pub use phone_number::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use resend_2fa::*;
+pub use reset_password::*;
+pub use sessions::*;
 pub use signup::*;
+pub use token::*;
+pub use totp::*;
+pub use trusted_devices::*;
+pub use two_fa_settings::*;
+pub use userinfo::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
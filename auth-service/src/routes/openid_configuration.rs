@@ -0,0 +1,56 @@
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{
+    auth::{CLIENT_CREDENTIALS_GRANT, KEY_RING},
+    constants::JWT_ISSUER,
+    oidc::{endpoint_url, SUPPORTED_SCOPES},
+};
+
+/// OpenID Connect discovery document, so off-the-shelf clients can
+/// configure themselves from our issuer URL alone.
+pub async fn openid_configuration() -> Json<ProviderMetadata> {
+    let signing_algorithm = format!("{:?}", KEY_RING.signing_key().algorithm());
+
+    Json(ProviderMetadata {
+        issuer: JWT_ISSUER.to_owned(),
+        authorization_endpoint: endpoint_url("/authorize"),
+        token_endpoint: endpoint_url("/token"),
+        userinfo_endpoint: endpoint_url("/userinfo"),
+        jwks_uri: endpoint_url("/.well-known/jwks.json"),
+        scopes_supported: SUPPORTED_SCOPES.iter().map(|s| s.to_string()).collect(),
+        response_types_supported: vec!["code".to_owned()],
+        grant_types_supported: vec![
+            "authorization_code".to_owned(),
+            CLIENT_CREDENTIALS_GRANT.to_owned(),
+        ],
+        subject_types_supported: vec!["public".to_owned()],
+        id_token_signing_alg_values_supported: vec![signing_algorithm],
+        token_endpoint_auth_methods_supported: vec![
+            "client_secret_basic".to_owned(),
+            "none".to_owned(),
+        ],
+        code_challenge_methods_supported: vec!["S256".to_owned()],
+        claims_supported: ["iss", "sub", "aud", "exp", "iat", "nonce", "email"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub scopes_supported: Vec<String>,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
@@ -0,0 +1,379 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, Passkey, PasskeyCeremony, PasskeyChallenge, PasskeyStoreError},
+    routes::TokenResponse,
+    utils::{
+        auth::{first_party_audience, start_session},
+        authenticated_user::AuthenticatedUser,
+        client_info::ClientInfo,
+        constants::WEBAUTHN_RP_ID,
+        webauthn::{
+            parse_attestation_object, parse_authenticator_data, parse_client_data,
+            verify_signature, WebAuthnError, CREATE_CEREMONY, ES256, GET_CEREMONY,
+        },
+    },
+};
+
+/// How long the browser gives the user to complete a ceremony.
+const CEREMONY_TIMEOUT_MILLISECONDS: u64 = 300_000;
+const RELYING_PARTY_NAME: &str = "Auth Service";
+const PUBLIC_KEY_CREDENTIAL_TYPE: &str = "public-key";
+
+/// Options for `navigator.credentials.create()` to add a passkey to the
+/// signed-in user's account.
+pub async fn start_passkey_registration(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let existing_passkeys = state
+        .passkey_store
+        .read()
+        .await
+        .list_passkeys(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let challenge = PasskeyChallenge::default();
+    state
+        .passkey_challenge_store
+        .write()
+        .await
+        .add_challenge(
+            challenge.clone(),
+            PasskeyCeremony::Registration(email.clone()),
+        )
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(Json(PasskeyCreationOptions {
+        challenge: challenge.as_ref().to_owned(),
+        rp: RelyingParty {
+            id: WEBAUTHN_RP_ID.to_owned(),
+            name: RELYING_PARTY_NAME.to_owned(),
+        },
+        user: PasskeyUser {
+            // An opaque handle, so the authenticator doesn't store the email
+            id: URL_SAFE_NO_PAD.encode(Sha256::digest(email.as_ref().as_bytes())),
+            name: email.as_ref().to_owned(),
+            display_name: email.as_ref().to_owned(),
+        },
+        pub_key_cred_params: vec![CredentialParameters {
+            credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(),
+            alg: ES256,
+        }],
+        timeout: CEREMONY_TIMEOUT_MILLISECONDS,
+        attestation: "none".to_owned(),
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "required".to_owned(),
+            user_verification: "required".to_owned(),
+        },
+        exclude_credentials: existing_passkeys
+            .into_iter()
+            .map(|passkey| CredentialDescriptor {
+                credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(),
+                id: passkey.credential_id,
+            })
+            .collect(),
+    }))
+}
+
+pub async fn finish_passkey_registration(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Json(request): Json<PasskeyRegistrationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let client_data_json = decode(&request.response.client_data_json)?;
+    let attestation_object = decode(&request.response.attestation_object)?;
+
+    let challenge =
+        parse_client_data(&client_data_json, CREATE_CEREMONY).map_err(rejected_response)?;
+    take_challenge(
+        &state,
+        &challenge,
+        &PasskeyCeremony::Registration(email.clone()),
+    )
+    .await?;
+
+    let authenticator_data =
+        parse_attestation_object(&attestation_object).map_err(rejected_response)?;
+    if !authenticator_data.user_verified {
+        return Err(rejected_response(WebAuthnError::UserNotVerified));
+    }
+    let credential = authenticator_data
+        .credential
+        .ok_or(AuthAPIError::InvalidCredentials)?;
+
+    let credential_id = URL_SAFE_NO_PAD.encode(&credential.credential_id);
+    if credential_id != request.id {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let passkey = Passkey {
+        credential_id,
+        email,
+        public_key: credential.public_key,
+        sign_count: authenticator_data.sign_count,
+        created_at: Utc::now(),
+    };
+    match state.passkey_store.write().await.add_passkey(passkey).await {
+        Ok(()) => Ok(StatusCode::CREATED),
+        Err(PasskeyStoreError::PasskeyAlreadyExists) => Err(AuthAPIError::PasskeyAlreadyExists),
+        Err(_) => Err(AuthAPIError::UnexpectedError),
+    }
+}
+
+/// Options for `navigator.credentials.get()`. No credentials are listed, so
+/// the browser offers whichever passkeys it holds for us.
+pub async fn start_passkey_login(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let challenge = PasskeyChallenge::default();
+    state
+        .passkey_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge.clone(), PasskeyCeremony::Authentication)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(Json(PasskeyRequestOptions {
+        challenge: challenge.as_ref().to_owned(),
+        rp_id: WEBAUTHN_RP_ID.to_owned(),
+        timeout: CEREMONY_TIMEOUT_MILLISECONDS,
+        user_verification: "required".to_owned(),
+        allow_credentials: Vec::new(),
+    }))
+}
+
+/// Passwordless login. A passkey with user verification is already two
+/// factors, so this starts a session directly, like `/login` does for users
+/// without 2FA.
+pub async fn finish_passkey_login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(request): Json<PasskeyLoginRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let audience = match first_party_audience(request.audience.as_deref()) {
+        Some(audience) => audience,
+        None => return (jar, Err(AuthAPIError::UnknownAudience)),
+    };
+
+    let email = match verify_assertion(&state, &request).await {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let (auth_cookie, refresh_cookie) = match start_session(&email, &audience, client, &state).await
+    {
+        Ok(cookies) => cookies,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+
+    let token = TokenResponse {
+        token: auth_cookie.value().to_owned(),
+        refresh_token: refresh_cookie.value().to_owned(),
+    };
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    if request.return_token {
+        return (
+            updated_jar,
+            Ok((StatusCode::OK, Json(token)).into_response()),
+        );
+    }
+
+    (updated_jar, Ok(StatusCode::OK.into_response()))
+}
+
+async fn verify_assertion(
+    state: &AppState,
+    request: &PasskeyLoginRequest,
+) -> Result<Email, AuthAPIError> {
+    let client_data_json = decode(&request.response.client_data_json)?;
+    let authenticator_data = decode(&request.response.authenticator_data)?;
+    let signature = decode(&request.response.signature)?;
+
+    let challenge =
+        parse_client_data(&client_data_json, GET_CEREMONY).map_err(rejected_response)?;
+    take_challenge(state, &challenge, &PasskeyCeremony::Authentication).await?;
+
+    let passkey = match state
+        .passkey_store
+        .read()
+        .await
+        .get_passkey(&request.id)
+        .await
+    {
+        Ok(passkey) => passkey,
+        Err(PasskeyStoreError::PasskeyNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    let parsed_data = parse_authenticator_data(&authenticator_data).map_err(rejected_response)?;
+    if !parsed_data.user_verified {
+        return Err(rejected_response(WebAuthnError::UserNotVerified));
+    }
+    verify_signature(
+        &passkey.public_key,
+        &authenticator_data,
+        &client_data_json,
+        &signature,
+    )
+    .map_err(rejected_response)?;
+
+    // Authenticators that don't count always report zero; for the rest a
+    // counter that didn't move forward means a cloned credential
+    if (parsed_data.sign_count != 0 || passkey.sign_count != 0)
+        && parsed_data.sign_count <= passkey.sign_count
+    {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+    state
+        .passkey_store
+        .write()
+        .await
+        .update_sign_count(&passkey.credential_id, parsed_data.sign_count)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(passkey.email)
+}
+
+/// Each challenge answers exactly one ceremony, whether or not the rest of
+/// the response checks out.
+async fn take_challenge(
+    state: &AppState,
+    challenge: &PasskeyChallenge,
+    expected: &PasskeyCeremony,
+) -> Result<(), AuthAPIError> {
+    let ceremony = state
+        .passkey_challenge_store
+        .write()
+        .await
+        .take_challenge(challenge)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    if ceremony != *expected {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    Ok(())
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, AuthAPIError> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| AuthAPIError::InvalidCredentials)
+}
+
+fn rejected_response(e: WebAuthnError) -> AuthAPIError {
+    match e {
+        WebAuthnError::MalformedResponse => AuthAPIError::InvalidCredentials,
+        _ => AuthAPIError::IncorrectCredentials,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyCreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: PasskeyUser,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    pub timeout: u64,
+    pub attestation: String,
+    pub authenticator_selection: AuthenticatorSelection,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyUser {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyRequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: u64,
+    pub user_verification: String,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+}
+
+/// A `PublicKeyCredential` from `navigator.credentials.create()`, with
+/// binary fields base64url-encoded.
+#[derive(Deserialize)]
+pub struct PasskeyRegistrationRequest {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+/// A `PublicKeyCredential` from `navigator.credentials.get()`, with binary
+/// fields base64url-encoded.
+#[derive(Deserialize)]
+pub struct PasskeyLoginRequest {
+    pub id: String,
+    pub response: AssertionResponse,
+    #[serde(default, rename = "returnToken")]
+    pub return_token: bool,
+    /// App the session is for, as in `/login`.
+    #[serde(default)]
+    pub audience: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
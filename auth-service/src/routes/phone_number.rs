@@ -0,0 +1,85 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, PhoneNumber, TwoFACode, UserStoreError},
+    utils::authenticated_user::AuthenticatedUser,
+};
+
+use super::two_fa_settings::{confirm_password, notify};
+
+/// Texts a code to a new phone number. The number isn't used for anything
+/// until `/phone-number/verify` confirms the code.
+pub async fn add_phone_number(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Json(request): Json<AddPhoneNumberRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let phone_number =
+        PhoneNumber::parse(request.phone_number).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    confirm_password(&email, request.password, &state).await?;
+
+    let code = TwoFACode::default();
+    state
+        .user_store
+        .write()
+        .await
+        .set_pending_phone_number(&email, phone_number.clone(), code.clone())
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let content = format!("Your phone number verification code is {}", code.as_ref());
+    state
+        .sms_client
+        .send_sms(&phone_number, &content)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Confirms the code texted by `/phone-number`, after which 2FA codes can be
+/// sent to the number. A wrong code means starting over.
+pub async fn verify_phone_number(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Json(request): Json<VerifyPhoneNumberRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let code = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let result = state
+        .user_store
+        .write()
+        .await
+        .confirm_phone_number(&email, &code)
+        .await;
+    match result {
+        Ok(_) => {}
+        Err(UserStoreError::InvalidCredentials) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    notify(
+        &email,
+        "Phone number changed",
+        "A new phone number was verified for your account.",
+        &state,
+    )
+    .await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct AddPhoneNumberRequest {
+    pub password: String,
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyPhoneNumberRequest {
+    pub code: String,
+}
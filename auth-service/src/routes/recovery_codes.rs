@@ -0,0 +1,94 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, RecoveryCode, TotpStoreError},
+    utils::authenticated_user::AuthenticatedUser,
+};
+
+use super::two_fa_settings::{confirm_password, notify};
+
+/// How many recovery codes a user gets at a time.
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Issues a fresh set of recovery codes to a user with 2FA turned on, once
+/// they confirm their password. The previous set stops working.
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Json(request): Json<RegenerateRecoveryCodesRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    confirm_password(&email, request.password, &state).await?;
+
+    if !has_2fa(&email, &state).await? {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+
+    let recovery_codes = issue_recovery_codes(&email, &state).await?;
+
+    notify(
+        &email,
+        "Recovery codes regenerated",
+        "New recovery codes were issued for your account and the old ones no longer work.",
+        &state,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(RecoveryCodesResponse { recovery_codes }),
+    ))
+}
+
+/// Whether logins ask for a second factor, either from an authenticator app
+/// or a sent code.
+async fn has_2fa(email: &Email, state: &AppState) -> Result<bool, AuthAPIError> {
+    match state.totp_store.read().await.get_secret(email).await {
+        Ok(_) => return Ok(true),
+        Err(TotpStoreError::SecretNotFound) => {}
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(user.requires_2fa)
+}
+
+/// Generates and stores a new set of recovery codes, returned in the form
+/// users should write down. They are only ever shown this once.
+pub(crate) async fn issue_recovery_codes(
+    email: &Email,
+    state: &AppState,
+) -> Result<Vec<String>, AuthAPIError> {
+    let codes: Vec<RecoveryCode> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| RecoveryCode::default())
+        .collect();
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_recovery_codes(email, &codes)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(codes.iter().map(RecoveryCode::formatted).collect())
+}
+
+#[derive(Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
@@ -0,0 +1,156 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::{cookie, CookieJar};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, RefreshToken, SessionStoreError},
+    routes::TokenResponse,
+    utils::{
+        auth::{generate_auth_cookie, generate_refresh_cookie, session_expiry},
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
+};
+
+pub async fn refresh(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    request: Option<Json<RefreshRequest>>,
+) -> (CookieJar, Result<Response, AuthAPIError>) {
+    // Clients that got their tokens in the body send the refresh token
+    // back the same way, and get the rotated ones in the body too.
+    let (token, return_token) = match (request, jar.get(REFRESH_TOKEN_COOKIE_NAME)) {
+        (Some(Json(request)), _) => (request.refresh_token, true),
+        (None, Some(cookie)) => (cookie.value().to_owned(), false),
+        (None, None) => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let token = match RefreshToken::parse(token) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let (record, reused) = {
+        let mut refresh_token_store = state.refresh_token_store.write().await;
+
+        let record = match refresh_token_store.get_token(&token).await {
+            Ok(record) => record,
+            Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+        };
+
+        let current_epoch = match state
+            .session_epoch_store
+            .read()
+            .await
+            .get_epoch(&record.email)
+            .await
+        {
+            Ok(epoch) => epoch,
+            Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+        };
+
+        // A rotated token showing up again means it was stolen, so nobody in
+        // this family gets to refresh any more. The same goes for families
+        // issued before the user logged out everywhere.
+        let reused = record.used || record.epoch < current_epoch;
+        let result = if reused {
+            refresh_token_store.revoke_family(&record.family_id).await
+        } else {
+            refresh_token_store.mark_token_used(&token).await
+        };
+        if result.is_err() {
+            return (jar, Err(AuthAPIError::UnexpectedError));
+        }
+
+        (record, reused)
+    };
+
+    if reused {
+        // Access tokens already minted from the family die with the
+        // session. It may be gone already, e.g. after logging out.
+        if let Err(SessionStoreError::UnexpectedError) = state
+            .session_store
+            .write()
+            .await
+            .revoke_session(&record.email, &record.family_id)
+            .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError));
+        }
+        let jar = jar
+            .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+            .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+        return (jar, Err(AuthAPIError::InvalidToken));
+    }
+
+    // The family id is the session id. Keep the session alive for as long as
+    // the new refresh token, unless it was revoked in the meantime.
+    let expires_at = match session_expiry(chrono::Utc::now()) {
+        Ok(expires_at) => expires_at,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+    match state
+        .session_store
+        .write()
+        .await
+        .extend_session(&record.family_id, expires_at)
+        .await
+    {
+        Ok(()) => {}
+        Err(SessionStoreError::SessionNotFound) => {
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+        Err(SessionStoreError::UnexpectedError) => {
+            return (jar, Err(AuthAPIError::UnexpectedError));
+        }
+    }
+
+    let auth_cookie = match generate_auth_cookie(
+        &record.email,
+        record.epoch,
+        &record.family_id,
+        &record.audience,
+    ) {
+        Ok(cookie) => cookie,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+
+    let refresh_cookie = match generate_refresh_cookie(
+        &record.email,
+        &record.family_id,
+        record.epoch,
+        &record.audience,
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+    };
+
+    let token = TokenResponse {
+        token: auth_cookie.value().to_owned(),
+        refresh_token: refresh_cookie.value().to_owned(),
+    };
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    if return_token {
+        return (
+            updated_jar,
+            Ok((StatusCode::OK, Json(token)).into_response()),
+        );
+    }
+
+    (updated_jar, Ok(StatusCode::OK.into_response()))
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    #[serde(rename = "refreshToken")]
+    refresh_token: String,
+}
@@ -0,0 +1,76 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, LoginAttemptId, TotpStoreError, TwoFACode, TwoFACodeStoreError},
+    routes::{authorize_login_attempt, deliver_code},
+    utils::constants::{TWO_FA_MAX_RESENDS, TWO_FA_RESEND_COOLDOWN_SECONDS},
+};
+
+/// Sends a fresh 2FA code for a login attempt whose first email or text got
+/// lost. The previous code stops working.
+pub async fn resend_2fa(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<Resend2FARequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let login_attempt_id = LoginAttemptId::parse(request.login_attempt_id)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    authorize_login_attempt(&headers, &email, &login_attempt_id)?;
+
+    // Authenticator apps make their own codes, there is nothing to send
+    match state.totp_store.read().await.get_secret(&email).await {
+        Ok(_) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(TotpStoreError::SecretNotFound) => {}
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    let two_fa_code = TwoFACode::default();
+    let result = state
+        .two_fa_code_store
+        .write()
+        .await
+        .resend_code(
+            &login_attempt_id,
+            two_fa_code.clone(),
+            *TWO_FA_RESEND_COOLDOWN_SECONDS,
+            *TWO_FA_MAX_RESENDS,
+        )
+        .await;
+    match result {
+        Ok(()) => {}
+        // No code for this attempt, e.g. it expired
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            return Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(TwoFACodeStoreError::TooManyAttempts) => return Err(AuthAPIError::TooManyAttempts),
+        Err(TwoFACodeStoreError::ResendTooSoon) => return Err(AuthAPIError::ResendTooSoon),
+        Err(TwoFACodeStoreError::TooManyResends) => return Err(AuthAPIError::TooManyResends),
+        Err(TwoFACodeStoreError::UnexpectedError) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    deliver_code(&user, user.code_method(), &two_fa_code, &state).await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct Resend2FARequest {
+    pub email: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
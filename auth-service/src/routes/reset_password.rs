@@ -0,0 +1,146 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, EmailToken, EmailTokenPurpose, EmailTokenStoreError, Password,
+        UserStoreError,
+    },
+    utils::constants::PUBLIC_URL,
+};
+
+/// Short, since anyone who gets hold of the link can take over the account.
+const PASSWORD_RESET_TTL_SECONDS: u64 = 60 * 60;
+
+/// Emails a link for choosing a new password. Responds the same whether or
+/// not the account exists, so it can't be used to find out which do.
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user = state.user_store.read().await.get_user(&email).await;
+    match user {
+        Ok(_) => {
+            // Failing only for accounts that exist would give them away
+            send_reset_email(&email, &state).await.ok();
+        }
+        Err(UserStoreError::UserNotFound) => {}
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Sets the password with the token from a reset link, then logs the user
+/// out everywhere in case someone else knew the old one.
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let token = EmailToken::parse(request.token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    // Checked first so a password that's too short doesn't use up the link
+    let password =
+        Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let email = match state
+        .email_token_store
+        .write()
+        .await
+        .take_token(&token, EmailTokenPurpose::ResetPassword)
+        .await
+    {
+        Ok(email) => email,
+        Err(EmailTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(EmailTokenStoreError::UnexpectedError) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    {
+        let mut user_store = state.user_store.write().await;
+        user_store
+            .update_password(&email, password)
+            .await
+            .map_err(|e| match e {
+                // The account moved to another address since the link was sent
+                UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+                _ => AuthAPIError::UnexpectedError,
+            })?;
+        // Following the link proved the user reads mail at the address
+        user_store
+            .mark_email_verified(&email)
+            .await
+            .map_err(|_| AuthAPIError::UnexpectedError)?;
+    }
+
+    state
+        .session_epoch_store
+        .write()
+        .await
+        .increment_epoch(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    state
+        .session_store
+        .write()
+        .await
+        .revoke_all_sessions(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    // The password is already changed, so a lost notice doesn't fail the reset
+    state
+        .email_client
+        .send_email(
+            &email,
+            "Password changed",
+            "The password for your account was reset and every device was logged out. \
+             If this wasn't you, reset it again right away.",
+        )
+        .await
+        .ok();
+
+    Ok(StatusCode::OK)
+}
+
+async fn send_reset_email(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+    let token = EmailToken::default();
+    state
+        .email_token_store
+        .write()
+        .await
+        .add_token(
+            token.clone(),
+            email.clone(),
+            EmailTokenPurpose::ResetPassword,
+            PASSWORD_RESET_TTL_SECONDS,
+        )
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let content = format!(
+        "Choose a new password by opening {}/?reset_token={} within the next hour. \
+         If you didn't ask for this, you can ignore this email.",
+        *PUBLIC_URL,
+        token.as_ref()
+    );
+    state
+        .email_client
+        .send_email(email, "Reset your password", &content)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
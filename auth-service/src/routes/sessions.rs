@@ -0,0 +1,109 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::{cookie, CookieJar};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Session, SessionStoreError},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
+};
+
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, claims, .. }: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .list_sessions(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let response: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|session| SessionResponse::new(session, &claims.jti))
+        .collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    AuthenticatedUser { email, claims, .. }: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    match state
+        .session_store
+        .write()
+        .await
+        .revoke_session(&email, &id)
+        .await
+    {
+        Ok(()) => {}
+        Err(SessionStoreError::SessionNotFound) => {
+            return (jar, Err(AuthAPIError::SessionNotFound))
+        }
+        Err(SessionStoreError::UnexpectedError) => {
+            return (jar, Err(AuthAPIError::UnexpectedError))
+        }
+    }
+
+    // The session id doubles as its refresh token family
+    if state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_family(&id)
+        .await
+        .is_err()
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    // Revoking the session this request was made from is a logout
+    let jar = if id == claims.jti {
+        jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+            .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME))
+    } else {
+        jar
+    };
+
+    (jar, Ok(StatusCode::NO_CONTENT))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub current: bool,
+}
+
+impl SessionResponse {
+    fn new(session: Session, current_session_id: &str) -> Self {
+        Self {
+            current: session.id == current_session_id,
+            id: session.id,
+            created_at: session.created_at.to_rfc3339(),
+            expires_at: session.expires_at.to_rfc3339(),
+            ip_address: session.ip_address,
+            user_agent: session.user_agent,
+        }
+    }
+}
@@ -6,6 +6,8 @@ use crate::{
     domain::{AuthAPIError, Email, Password, User},
 };
 
+use super::{recovery_codes::issue_recovery_codes, verify_email::send_verification_email};
+
 pub async fn signup(
     State(state): State<AppState>,
     Json(request): Json<SignupRequest>,
@@ -15,20 +17,32 @@ pub async fn signup(
     let password =
         Password::parse(request.password.clone()).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let user = User::new(email, password, request.requires_2fa);
+    let user = User::new(email.clone(), password, request.requires_2fa);
 
-    let mut user_store = state.user_store.write().await;
+    {
+        let mut user_store = state.user_store.write().await;
 
-    if user_store.get_user(&user.email).await.is_ok() {
-        return Err(AuthAPIError::UserAlreadyExists);
-    }
+        if user_store.get_user(&user.email).await.is_ok() {
+            return Err(AuthAPIError::UserAlreadyExists);
+        }
 
-    if user_store.add_user(user).await.is_err() {
-        return Err(AuthAPIError::UnexpectedError);
+        if user_store.add_user(user).await.is_err() {
+            return Err(AuthAPIError::UnexpectedError);
+        }
     }
 
+    // The account can't log in until the link is followed
+    send_verification_email(&email, &state).await?;
+
+    // Signing up with 2FA enrolls the user in it
+    let recovery_codes = match request.requires_2fa {
+        true => issue_recovery_codes(&email, &state).await?,
+        false => Vec::new(),
+    };
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes,
     });
 
     Ok((StatusCode::CREATED, response))
@@ -45,4 +59,11 @@ pub struct SignupRequest {
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct SignupResponse {
     pub message: String,
+    /// Only issued to users who signed up with 2FA.
+    #[serde(
+        rename = "recoveryCodes",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub recovery_codes: Vec<String>,
 }
\ No newline at end of file
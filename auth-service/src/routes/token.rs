@@ -0,0 +1,249 @@
+use axum::{
+    extract::State,
+    http::{
+        header::{CACHE_CONTROL, PRAGMA},
+        HeaderMap,
+    },
+    response::IntoResponse,
+    Form, Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthorizationCode, AuthorizationCodeStoreError, Client, ClientStoreError, OAuthError,
+    },
+    utils::{
+        auth::{
+            first_party_audience, generate_client_access_token, generate_client_credentials_token,
+            record_session, GenerateTokenError, CLIENT_CREDENTIALS_GRANT, TOKEN_TTL_SECONDS,
+        },
+        authenticated_client::basic_credentials,
+        client_info::ClientInfo,
+        oidc::{generate_id_token, has_scope, OPENID_SCOPE},
+    },
+};
+
+/// OAuth 2.0 token endpoint. Confidential clients authenticate with HTTP
+/// Basic; public clients only send their `client_id` and rely on PKCE, so
+/// they can't use the `client_credentials` grant.
+pub async fn token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<TokenRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let (client, confidential) =
+        authenticate_client(&state, &headers, request.client_id.as_deref()).await?;
+
+    let response = match request.grant_type.as_deref() {
+        Some("authorization_code") => authorization_code_grant(&state, &client, request).await?,
+        Some(CLIENT_CREDENTIALS_GRANT) if confidential => client_credentials_grant(
+            &client,
+            request.scope.as_deref(),
+            request.audience.as_deref(),
+        )?,
+        Some(CLIENT_CREDENTIALS_GRANT) => return Err(OAuthError::UnauthorizedClient),
+        Some(_) => return Err(OAuthError::UnsupportedGrantType),
+        None => return Err(OAuthError::InvalidRequest),
+    };
+
+    Ok((
+        [(CACHE_CONTROL, "no-store"), (PRAGMA, "no-cache")],
+        Json(response),
+    ))
+}
+
+/// Returns the client and whether it proved itself with a secret.
+async fn authenticate_client(
+    state: &AppState,
+    headers: &HeaderMap,
+    form_client_id: Option<&str>,
+) -> Result<(Client, bool), OAuthError> {
+    let (client_id, secret) =
+        match basic_credentials(headers).map_err(|_| OAuthError::InvalidClient)? {
+            Some((client_id, _)) if form_client_id.is_some_and(|id| id != client_id) => {
+                return Err(OAuthError::InvalidRequest)
+            }
+            Some((client_id, secret)) => (client_id, Some(secret)),
+            None => (
+                form_client_id.ok_or(OAuthError::InvalidClient)?.to_owned(),
+                None,
+            ),
+        };
+
+    state
+        .client_store
+        .read()
+        .await
+        .validate_client(&client_id, secret.as_ref())
+        .await
+        .map(|client| (client, secret.is_some()))
+        .map_err(|e| match e {
+            ClientStoreError::UnexpectedError => OAuthError::ServerError,
+            _ => OAuthError::InvalidClient,
+        })
+}
+
+async fn authorization_code_grant(
+    state: &AppState,
+    client: &Client,
+    request: TokenRequest,
+) -> Result<AccessTokenResponse, OAuthError> {
+    let (Some(code), Some(redirect_uri), Some(code_verifier)) =
+        (request.code, request.redirect_uri, request.code_verifier)
+    else {
+        return Err(OAuthError::InvalidRequest);
+    };
+    let code = AuthorizationCode::parse(code).map_err(|_| OAuthError::InvalidRequest)?;
+
+    let grant = state
+        .authorization_code_store
+        .write()
+        .await
+        .take_code(&code)
+        .await
+        .map_err(|e| match e {
+            AuthorizationCodeStoreError::CodeNotFound => OAuthError::InvalidGrant,
+            AuthorizationCodeStoreError::UnexpectedError => OAuthError::ServerError,
+        })?;
+
+    if grant.client_id != client.id
+        || grant.redirect_uri != redirect_uri
+        || !verify_pkce(&code_verifier, &grant.code_challenge)
+    {
+        return Err(OAuthError::InvalidGrant);
+    }
+
+    // Without a refresh token the session ends with the access token
+    let expires_at = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
+        .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+        .ok_or(OAuthError::ServerError)?;
+    let client_info = ClientInfo {
+        ip_address: grant.ip_address,
+        user_agent: grant.user_agent,
+    };
+    // The account may have moved to another address since the code was issued
+    let (session, epoch) = record_session(&grant.email, client_info, expires_at, state)
+        .await
+        .map_err(|e| match e {
+            GenerateTokenError::UserNotFound => OAuthError::InvalidGrant,
+            _ => OAuthError::ServerError,
+        })?;
+
+    let access_token = generate_client_access_token(
+        &grant.email,
+        epoch,
+        &session.id,
+        &client.id,
+        grant.scope.as_deref(),
+    )
+    .map_err(|_| OAuthError::ServerError)?;
+
+    let id_token = if has_scope(grant.scope.as_deref(), OPENID_SCOPE) {
+        let id_token = generate_id_token(
+            &grant.email,
+            &client.id,
+            grant.nonce.as_deref(),
+            grant.scope.as_deref(),
+        )
+        .map_err(|_| OAuthError::ServerError)?;
+        Some(id_token)
+    } else {
+        None
+    };
+
+    Ok(AccessTokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        scope: grant.scope,
+        id_token,
+    })
+}
+
+/// Grants the requested scopes, or all of the client's scopes when none
+/// are requested. Asking for a scope the client wasn't registered with
+/// fails the whole request. The token is for the app named by `audience`,
+/// as `/login` takes it.
+fn client_credentials_grant(
+    client: &Client,
+    requested_scope: Option<&str>,
+    requested_audience: Option<&str>,
+) -> Result<AccessTokenResponse, OAuthError> {
+    let audience = first_party_audience(requested_audience).ok_or(OAuthError::InvalidTarget)?;
+
+    let scopes: Vec<&str> = match requested_scope {
+        Some(scope) => scope.split_whitespace().collect(),
+        None => client.scopes.iter().map(String::as_str).collect(),
+    };
+    if !scopes.iter().all(|scope| client.scopes.iter().any(|s| s == scope)) {
+        return Err(OAuthError::InvalidScope);
+    }
+    let scope = (!scopes.is_empty()).then(|| scopes.join(" "));
+
+    let access_token = generate_client_credentials_token(&client.id, scope.as_deref(), &audience)
+        .map_err(|_| OAuthError::ServerError)?;
+
+    Ok(AccessTokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        scope,
+        id_token: None,
+    })
+}
+
+/// RFC 7636: the verifier is 43 to 128 unreserved characters, and its
+/// base64url-encoded SHA-256 digest must match the challenge.
+fn verify_pkce(code_verifier: &str, code_challenge: &str) -> bool {
+    let well_formed = (43..=128).contains(&code_verifier.len())
+        && code_verifier
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-._~".contains(&b));
+
+    well_formed && URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes())) == code_challenge
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub client_id: Option<String>,
+    pub code_verifier: Option<String>,
+    pub scope: Option<String>,
+    /// App a `client_credentials` token is for, one of `JWT_AUDIENCE`.
+    pub audience: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Only issued when the `openid` scope was granted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_pkce;
+
+    #[test]
+    fn test_verify_pkce() {
+        // Example from RFC 7636, appendix B
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+        assert!(verify_pkce(verifier, challenge));
+        assert!(!verify_pkce(verifier, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cN"));
+        assert!(!verify_pkce("too-short", challenge));
+    }
+}
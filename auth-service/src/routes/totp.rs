@@ -0,0 +1,114 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, TotpSecret, TotpStoreError, TwoFACode},
+    routes::RecoveryCodesResponse,
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        constants::TOTP_DRIFT_STEPS,
+        totp::{current_time_step, provisioning_uri, verify_code},
+    },
+};
+
+use super::{
+    recovery_codes::issue_recovery_codes,
+    two_fa_settings::{confirm_password, notify},
+};
+
+/// Starts authenticator-app enrollment. The new secret only replaces the
+/// current one, if any, once a code from it is confirmed.
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let secret = TotpSecret::default();
+
+    state
+        .totp_store
+        .write()
+        .await
+        .set_pending_secret(&email, secret.clone())
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(TotpEnrollmentResponse {
+            secret: secret.encode(),
+            otpauth_uri: provisioning_uri(&secret, &email),
+        }),
+    ))
+}
+
+/// Activates the pending secret once the user shows their app produces
+/// codes for it and confirms their password. From then on every login asks
+/// for a TOTP code instead of an emailed one. Responds with a new set of
+/// recovery codes.
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let code = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    confirm_password(&email, request.password, &state).await?;
+
+    activate_pending_secret(&email, &code, &state).await?;
+
+    let recovery_codes = issue_recovery_codes(&email, &state).await?;
+
+    notify(
+        &email,
+        "Authenticator app added",
+        "An authenticator app was set up for two-factor authentication on your account.",
+        &state,
+    )
+    .await?;
+
+    Ok((StatusCode::OK, Json(RecoveryCodesResponse { recovery_codes })))
+}
+
+/// Makes the secret from `/2fa/totp/enroll` the active one, provided `code`
+/// was generated from it.
+pub(crate) async fn activate_pending_secret(
+    email: &Email,
+    code: &TwoFACode,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let mut totp_store = state.totp_store.write().await;
+
+    let secret = match totp_store.get_pending_secret(email).await {
+        Ok(secret) => secret,
+        Err(TotpStoreError::SecretNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    let time_step = verify_code(
+        &secret,
+        code.as_ref(),
+        current_time_step(),
+        *TOTP_DRIFT_STEPS,
+    )
+    .ok_or(AuthAPIError::IncorrectCredentials)?;
+
+    totp_store
+        .activate_secret(email, time_step)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpEnrollmentResponse {
+    /// Base32, for typing into an authenticator app by hand.
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub password: String,
+    pub code: String,
+}
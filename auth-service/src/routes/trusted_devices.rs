@@ -0,0 +1,107 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::{cookie, CookieJar};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, TrustedDevice, TrustedDeviceStoreError},
+    utils::{
+        auth::validate_trusted_device_token, authenticated_user::AuthenticatedUser,
+        constants::TRUSTED_DEVICE_COOKIE_NAME,
+    },
+};
+
+pub async fn list_trusted_devices(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let devices = state
+        .trusted_device_store
+        .read()
+        .await
+        .list_devices(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let current_device_id = current_device_id(&jar);
+    let response: Vec<TrustedDeviceResponse> = devices
+        .into_iter()
+        .map(|device| TrustedDeviceResponse::new(device, current_device_id.as_deref()))
+        .collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// The browser stops skipping 2FA. Its sessions are left alone.
+pub async fn revoke_trusted_device(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    match state
+        .trusted_device_store
+        .write()
+        .await
+        .revoke_device(&email, &id)
+        .await
+    {
+        Ok(()) => {}
+        Err(TrustedDeviceStoreError::DeviceNotFound) => {
+            return (jar, Err(AuthAPIError::DeviceNotFound))
+        }
+        Err(TrustedDeviceStoreError::UnexpectedError) => {
+            return (jar, Err(AuthAPIError::UnexpectedError))
+        }
+    }
+
+    // No use keeping a cookie that no longer does anything
+    let jar = if current_device_id(&jar).as_deref() == Some(id.as_str()) {
+        jar.remove(cookie::Cookie::from(TRUSTED_DEVICE_COOKIE_NAME))
+    } else {
+        jar
+    };
+
+    (jar, Ok(StatusCode::NO_CONTENT))
+}
+
+fn current_device_id(jar: &CookieJar) -> Option<String> {
+    let cookie = jar.get(TRUSTED_DEVICE_COOKIE_NAME)?;
+    validate_trusted_device_token(cookie.value())
+        .ok()
+        .map(|claims| claims.jti)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrustedDeviceResponse {
+    pub id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    /// Whether this is the browser the request came from.
+    pub current: bool,
+}
+
+impl TrustedDeviceResponse {
+    fn new(device: TrustedDevice, current_device_id: Option<&str>) -> Self {
+        Self {
+            current: current_device_id == Some(device.id.as_str()),
+            id: device.id,
+            created_at: device.created_at.to_rfc3339(),
+            expires_at: device.expires_at.to_rfc3339(),
+            ip_address: device.ip_address,
+            user_agent: device.user_agent,
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, CodeChannel, Email, Password, TwoFACode, TwoFAMethod},
+    routes::RecoveryCodesResponse,
+    utils::authenticated_user::AuthenticatedUser,
+};
+
+use super::{recovery_codes::issue_recovery_codes, totp::activate_pending_secret};
+
+/// Turns on 2FA with codes sent by email and responds with a new set of
+/// recovery codes. Users who already confirmed an authenticator app keep
+/// using it.
+pub async fn enable_2fa(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Json(request): Json<Enable2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    confirm_password(&email, request.password, &state).await?;
+
+    set_requires_2fa(&email, true, &state).await?;
+    let recovery_codes = issue_recovery_codes(&email, &state).await?;
+
+    notify(
+        &email,
+        "Two-factor authentication enabled",
+        "Two-factor authentication was turned on for your account.",
+        &state,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(RecoveryCodesResponse { recovery_codes }),
+    ))
+}
+
+/// Turns 2FA off. Also forgets the user's authenticator app, recovery codes
+/// and trusted devices, so turning it back on starts from scratch.
+pub async fn disable_2fa(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Json(request): Json<Disable2FARequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    confirm_password(&email, request.password, &state).await?;
+
+    state
+        .totp_store
+        .write()
+        .await
+        .remove_secret(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_recovery_codes(&email, &[])
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    state
+        .trusted_device_store
+        .write()
+        .await
+        .revoke_all_devices(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    set_code_channel(&email, CodeChannel::Email, &state).await?;
+    set_requires_2fa(&email, false, &state).await?;
+
+    notify(
+        &email,
+        "Two-factor authentication disabled",
+        "Two-factor authentication was turned off for your account.",
+        &state,
+    )
+    .await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Switches between emailed codes, texted codes and an authenticator app,
+/// turning 2FA on if it was off. Switching to `sms` needs a phone number
+/// verified through `/phone-number`. Switching to `totp` takes a `code` from
+/// the secret handed out by `/2fa/totp/enroll` and responds with a new set of
+/// recovery codes.
+pub async fn change_2fa_method(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Json(request): Json<Change2FAMethodRequest>,
+) -> Result<Response, AuthAPIError> {
+    let method =
+        TwoFAMethod::parse(&request.method).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let code = match (method, request.code) {
+        (TwoFAMethod::Totp, Some(code)) => {
+            Some(TwoFACode::parse(code).map_err(|_| AuthAPIError::InvalidCredentials)?)
+        }
+        (TwoFAMethod::Totp, None) => return Err(AuthAPIError::InvalidCredentials),
+        (TwoFAMethod::Email | TwoFAMethod::Sms, _) => None,
+    };
+
+    confirm_password(&email, request.password, &state).await?;
+
+    let response = match (method, code) {
+        (TwoFAMethod::Totp, Some(code)) => {
+            activate_pending_secret(&email, &code, &state).await?;
+            let recovery_codes = issue_recovery_codes(&email, &state).await?;
+
+            (
+                StatusCode::OK,
+                Json(RecoveryCodesResponse { recovery_codes }),
+            )
+                .into_response()
+        }
+        _ => {
+            let channel = match method {
+                TwoFAMethod::Sms => {
+                    let user = state
+                        .user_store
+                        .read()
+                        .await
+                        .get_user(&email)
+                        .await
+                        .map_err(|_| AuthAPIError::UnexpectedError)?;
+                    if user.phone_number.is_none() {
+                        return Err(AuthAPIError::PhoneNumberNotVerified);
+                    }
+                    CodeChannel::Sms
+                }
+                _ => CodeChannel::Email,
+            };
+            set_code_channel(&email, channel, &state).await?;
+
+            // Without a secret, logins fall back to emailed or texted codes
+            state
+                .totp_store
+                .write()
+                .await
+                .remove_secret(&email)
+                .await
+                .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+            StatusCode::OK.into_response()
+        }
+    };
+
+    set_requires_2fa(&email, true, &state).await?;
+
+    notify(
+        &email,
+        "Two-factor authentication method changed",
+        match method {
+            TwoFAMethod::Email => {
+                "Two-factor authentication codes for your account will now be sent by email."
+            }
+            TwoFAMethod::Sms => {
+                "Two-factor authentication codes for your account will now be sent by text message."
+            }
+            TwoFAMethod::Totp => {
+                "Two-factor authentication for your account now uses an authenticator app."
+            }
+        },
+        &state,
+    )
+    .await?;
+
+    Ok(response)
+}
+
+/// Settings changes need the password again, not just a session, so a
+/// stolen or unattended session can't lower the account's protection.
+pub(crate) async fn confirm_password(
+    email: &Email,
+    password: String,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let password = Password::parse(password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    state
+        .user_store
+        .read()
+        .await
+        .validate_user(email, &password)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)
+}
+
+async fn set_code_channel(
+    email: &Email,
+    channel: CodeChannel,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    state
+        .user_store
+        .write()
+        .await
+        .set_code_channel(email, channel)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)
+}
+
+async fn set_requires_2fa(
+    email: &Email,
+    requires_2fa: bool,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    state
+        .user_store
+        .write()
+        .await
+        .set_requires_2fa(email, requires_2fa)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)
+}
+
+/// Lets the owner notice if someone else changed their 2FA settings.
+pub(crate) async fn notify(
+    email: &Email,
+    subject: &str,
+    content: &str,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let content = format!(
+        "{} If this wasn't you, change your password right away.",
+        content
+    );
+
+    state
+        .email_client
+        .send_email(email, subject, &content)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)
+}
+
+#[derive(Deserialize)]
+pub struct Enable2FARequest {
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct Disable2FARequest {
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct Change2FAMethodRequest {
+    pub password: String,
+    /// `email`, `sms` or `totp`.
+    pub method: String,
+    /// A code from the enrolled authenticator app, when switching to `totp`.
+    pub code: Option<String>,
+}
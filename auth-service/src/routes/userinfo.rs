@@ -0,0 +1,70 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{Email, OAuthError, UserStoreError},
+    utils::{
+        auth::{validate_token, ExpectedAudience},
+        authenticated_user::AuthToken,
+        oidc::{has_scope, EMAIL_SCOPE, OPENID_SCOPE},
+    },
+};
+
+/// OpenID Connect UserInfo endpoint. Only answers for access tokens issued
+/// to an OAuth client on a user's behalf with the `openid` scope, and only
+/// with the claims their scopes allow.
+pub async fn userinfo(
+    State(state): State<AppState>,
+    AuthToken(token): AuthToken,
+) -> Result<Json<UserInfoResponse>, OAuthError> {
+    // Client access tokens are issued to the client itself, so there is no
+    // one audience to expect here. The token must still name its client as
+    // its audience.
+    let claims = validate_token(
+        &token,
+        ExpectedAudience::Any,
+        state.banned_token_store.clone(),
+        state.session_epoch_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    .map_err(|_| OAuthError::InvalidToken)?;
+
+    if claims.is_client_token() {
+        return Err(OAuthError::InvalidToken);
+    }
+    // First-party logins were granted no scopes, OAuth or otherwise
+    if claims.client_id.as_deref() != Some(claims.aud.as_str()) {
+        return Err(OAuthError::InsufficientScope);
+    }
+
+    let scope = claims.scope.as_deref();
+    if !has_scope(scope, OPENID_SCOPE) {
+        return Err(OAuthError::InsufficientScope);
+    }
+
+    let email = Email::parse(claims.sub.clone()).map_err(|_| OAuthError::InvalidToken)?;
+    let stored_user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => OAuthError::InvalidToken,
+            _ => OAuthError::ServerError,
+        })?;
+
+    Ok(Json(UserInfoResponse {
+        sub: stored_user.email.as_ref().to_owned(),
+        email: has_scope(scope, EMAIL_SCOPE).then(|| stored_user.email.as_ref().to_owned()),
+    }))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
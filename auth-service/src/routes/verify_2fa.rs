@@ -1,19 +1,38 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use axum_extra::extract::CookieJar;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::{cookie, CookieJar};
 use serde::Deserialize;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode}, utils::auth::generate_auth_cookie,
+    domain::{
+        AuthAPIError, Email, LoginAttemptId, RecoveryCode, TotpSecret, TotpStoreError, TwoFACode,
+        TwoFACodeStoreError, UserStoreError,
+    },
+    routes::TokenResponse,
+    utils::{
+        auth::{
+            first_party_audience, start_session, trust_device, validate_pre_auth_token,
+            GenerateTokenError,
+        },
+        authenticated_user::token_from_headers,
+        client_info::ClientInfo,
+        constants::{PRE_AUTH_COOKIE_NAME, TOTP_DRIFT_STEPS, TWO_FA_MAX_ATTEMPTS},
+        totp::{current_time_step, verify_code},
+    },
 };
 
 pub async fn verify_2fa(
     State(state): State<AppState>,
     jar: CookieJar,
+    headers: HeaderMap,
+    client: ClientInfo,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
-
     let email = match Email::parse(request.email.clone()) {
         Ok(email) => email,
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
@@ -22,38 +41,231 @@ pub async fn verify_2fa(
         Ok(login_attempt_id) => login_attempt_id,
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
-    let two_fa_code = match TwoFACode::parse(request.two_fa_code.clone()) {
-        Ok(two_fa_code) => two_fa_code,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    // A recovery code can stand in for the 2FA code
+    let code = match TwoFACode::parse(request.two_fa_code.clone()) {
+        Ok(two_fa_code) => SubmittedCode::TwoFA(two_fa_code),
+        Err(_) => match RecoveryCode::parse(request.two_fa_code.clone()) {
+            Ok(recovery_code) => SubmittedCode::Recovery(recovery_code),
+            Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        },
+    };
+
+    let audience = match first_party_audience(request.audience.as_deref()) {
+        Some(audience) => audience,
+        None => return (jar, Err(AuthAPIError::UnknownAudience)),
+    };
+
+    if let Err(e) = authorize_login_attempt(&headers, &email, &login_attempt_id) {
+        return (jar, Err(e));
+    }
+
+    if let Err(e) = check_code(&state, &email, &login_attempt_id, &code).await {
+        return (jar, Err(e));
+    }
+
+    // Fails if the account moved to another address during the login, so
+    // it comes before the device is trusted
+    let (auth_cookie, refresh_cookie) =
+        match start_session(&email, &audience, client.clone(), &state).await {
+            Ok(cookies) => cookies,
+            Err(GenerateTokenError::UserNotFound) => {
+                return (jar, Err(AuthAPIError::IncorrectCredentials))
+            }
+            Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+        };
+
+    let device_cookie = if request.remember_device {
+        match trust_device(&email, client, &state).await {
+            Ok(cookie) => Some(cookie),
+            Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+        }
+    } else {
+        None
+    };
+
+    let token = TokenResponse {
+        token: auth_cookie.value().to_owned(),
+        refresh_token: refresh_cookie.value().to_owned(),
+    };
+    let mut updated_jar = jar
+        .remove(cookie::Cookie::from(PRE_AUTH_COOKIE_NAME))
+        .add(auth_cookie)
+        .add(refresh_cookie);
+    if let Some(device_cookie) = device_cookie {
+        updated_jar = updated_jar.add(device_cookie);
+    }
+
+    if request.return_token {
+        return (
+            updated_jar,
+            Ok((StatusCode::OK, Json(token)).into_response()),
+        );
+    }
+
+    (updated_jar, Ok(StatusCode::OK.into_response()))
+}
+
+/// Only the client that passed the password step for a login attempt may
+/// act on it.
+pub(crate) fn authorize_login_attempt(
+    headers: &HeaderMap,
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+) -> Result<(), AuthAPIError> {
+    let pre_auth_token = token_from_headers(headers, PRE_AUTH_COOKIE_NAME)?;
+    let pre_auth_claims =
+        validate_pre_auth_token(&pre_auth_token).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    if pre_auth_claims.sub != email.as_ref()
+        || pre_auth_claims.login_attempt_id != login_attempt_id.as_ref()
+    {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    Ok(())
+}
+
+enum SubmittedCode {
+    TwoFA(TwoFACode),
+    Recovery(RecoveryCode),
+}
+
+/// Every login attempt has an entry in the 2FA code store, even when the
+/// code comes from an authenticator app, so wrong guesses count against it
+/// whichever kind of code is tried. A correct code uses the attempt up.
+///
+/// The code store is not locked while the code is verified, since that
+/// takes other stores' locks and `/login` takes them the other way round.
+async fn check_code(
+    state: &AppState,
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+    code: &SubmittedCode,
+) -> Result<(), AuthAPIError> {
+    let (code_email, sent_code) = get_login_attempt(state, login_attempt_id).await?;
+
+    if code_email != *email {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let verified = match code {
+        SubmittedCode::TwoFA(two_fa_code) => {
+            verify_two_fa_code(state, email, &sent_code, two_fa_code).await?
+        }
+        SubmittedCode::Recovery(recovery_code) => {
+            use_recovery_code(state, email, recovery_code).await?
+        }
     };
 
-    let code_tuple = match two_fa_code_store
-        .get_code(&email)
-        .await {
-            Ok(code_tuple) => code_tuple,
-            Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+
+    if !verified {
+        // Six digits could otherwise be enumerated before the code expires
+        return match two_fa_code_store
+            .record_failed_attempt(login_attempt_id, *TWO_FA_MAX_ATTEMPTS)
+            .await
+        {
+            Err(TwoFACodeStoreError::TooManyAttempts) => Err(AuthAPIError::TooManyAttempts),
+            Err(TwoFACodeStoreError::UnexpectedError) => Err(AuthAPIError::UnexpectedError),
+            _ => Err(AuthAPIError::IncorrectCredentials),
         };
+    }
 
-    if code_tuple.0 != login_attempt_id {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    // Checked again, as a concurrent request may have used the attempt up
+    // or invalidated it while the code was verified
+    match two_fa_code_store.get_code(login_attempt_id).await {
+        Ok(_) => {}
+        Err(TwoFACodeStoreError::TooManyAttempts) => return Err(AuthAPIError::TooManyAttempts),
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
     }
-    if code_tuple.1 != two_fa_code {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+
+    two_fa_code_store
+        .remove_code(login_attempt_id)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)
+}
+
+async fn get_login_attempt(
+    state: &AppState,
+    login_attempt_id: &LoginAttemptId,
+) -> Result<(Email, TwoFACode), AuthAPIError> {
+    match state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(login_attempt_id)
+        .await
+    {
+        Ok(code_tuple) => Ok(code_tuple),
+        Err(TwoFACodeStoreError::TooManyAttempts) => Err(AuthAPIError::TooManyAttempts),
+        Err(_) => Err(AuthAPIError::IncorrectCredentials),
     }
+}
 
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
-        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+async fn verify_two_fa_code(
+    state: &AppState,
+    email: &Email,
+    sent_code: &TwoFACode,
+    two_fa_code: &TwoFACode,
+) -> Result<bool, AuthAPIError> {
+    let totp_secret = match state.totp_store.read().await.get_secret(email).await {
+        Ok(secret) => Some(secret),
+        Err(TotpStoreError::SecretNotFound) => None,
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    // Users with an authenticator app aren't sent codes
+    match totp_secret {
+        Some(secret) => verify_totp_code(state, email, &secret, two_fa_code).await,
+        None => Ok(sent_code == two_fa_code),
+    }
+}
+
+async fn use_recovery_code(
+    state: &AppState,
+    email: &Email,
+    recovery_code: &RecoveryCode,
+) -> Result<bool, AuthAPIError> {
+    match state
+        .user_store
+        .write()
+        .await
+        .use_recovery_code(email, recovery_code)
+        .await
+    {
+        Ok(()) => Ok(true),
+        Err(UserStoreError::UnexpectedError) => Err(AuthAPIError::UnexpectedError),
+        Err(_) => Ok(false),
+    }
+}
 
-    let _ = match two_fa_code_store.remove_code(&email).await {
-        Ok(_) => (),
-        Err(_) => return (updated_jar, Err(AuthAPIError::UnexpectedError)),
+async fn verify_totp_code(
+    state: &AppState,
+    email: &Email,
+    secret: &TotpSecret,
+    two_fa_code: &TwoFACode,
+) -> Result<bool, AuthAPIError> {
+    let Some(time_step) = verify_code(
+        secret,
+        two_fa_code.as_ref(),
+        current_time_step(),
+        *TOTP_DRIFT_STEPS,
+    ) else {
+        return Ok(false);
     };
 
-    (updated_jar, Ok(StatusCode::OK.into_response()))
+    // A code seen once, even by another login attempt, is spent
+    match state
+        .totp_store
+        .write()
+        .await
+        .use_time_step(email, time_step)
+        .await
+    {
+        Ok(()) => Ok(true),
+        Err(TotpStoreError::UnexpectedError) => Err(AuthAPIError::UnexpectedError),
+        Err(_) => Ok(false),
+    }
 }
 
 #[derive(Deserialize)]
@@ -63,4 +275,12 @@ pub struct Verify2FARequest {
     pub login_attempt_id: String,
     #[serde(rename = "2FACode")]
     pub two_fa_code: String,
+    #[serde(default, rename = "returnToken")]
+    pub return_token: bool,
+    /// Set a device cookie so later logins from this browser skip 2FA.
+    #[serde(default, rename = "rememberDevice")]
+    pub remember_device: bool,
+    /// App the session is for, as in `/login`.
+    #[serde(default)]
+    pub audience: Option<String>,
 }
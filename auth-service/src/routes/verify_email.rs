@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, EmailToken, EmailTokenPurpose, EmailTokenStoreError, UserStoreError,
+    },
+    utils::constants::PUBLIC_URL,
+};
+
+/// Long enough for the email to be read the next day.
+const EMAIL_VERIFICATION_TTL_SECONDS: u64 = 24 * 60 * 60;
+
+/// Where the link emailed at signup lands. Browsers open it with GET.
+pub async fn verify_email_link(
+    State(state): State<AppState>,
+    Query(request): Query<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    confirm_email(request.token, &state).await?;
+
+    Ok((
+        StatusCode::OK,
+        "Your email address is verified. You can now log in.",
+    ))
+}
+
+/// Same as following the link, for clients that take the token themselves.
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyEmailRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    confirm_email(request.token, &state).await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn confirm_email(token: String, state: &AppState) -> Result<(), AuthAPIError> {
+    let token = EmailToken::parse(token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let email = match state
+        .email_token_store
+        .write()
+        .await
+        .take_token(&token, EmailTokenPurpose::VerifyEmail)
+        .await
+    {
+        Ok(email) => email,
+        Err(EmailTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(EmailTokenStoreError::UnexpectedError) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    state
+        .user_store
+        .write()
+        .await
+        .mark_email_verified(&email)
+        .await
+        .map_err(|e| match e {
+            // The account moved to another address since the link was sent
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            _ => AuthAPIError::UnexpectedError,
+        })
+}
+
+/// Emails a link that proves the user reads mail at `email`. Links sent
+/// earlier keep working until they expire.
+pub(crate) async fn send_verification_email(
+    email: &Email,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let token = EmailToken::default();
+    state
+        .email_token_store
+        .write()
+        .await
+        .add_token(
+            token.clone(),
+            email.clone(),
+            EmailTokenPurpose::VerifyEmail,
+            EMAIL_VERIFICATION_TTL_SECONDS,
+        )
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let content = format!(
+        "Confirm your email address by opening {}/verify-email?token={}",
+        *PUBLIC_URL,
+        token.as_ref()
+    );
+    state
+        .email_client
+        .send_email(email, "Verify your email address", &content)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
@@ -2,18 +2,34 @@ use axum::{extract::State, http::StatusCode, Json};
 use serde::Deserialize;
 
 use crate::{app_state::AppState, domain::AuthAPIError};
-use crate::utils::auth::validate_token;
+use crate::utils::{
+    auth::{validate_token, ExpectedAudience},
+    constants::JWT_AUDIENCE,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct VerifyTokenRequest {
     pub token: String,
+    /// The calling app. Only tokens issued to it are accepted. Defaults to
+    /// the first app in `JWT_AUDIENCE`.
+    pub audience: Option<String>,
 }
 
 pub async fn verify_token(
     State(state): State<AppState>,
     Json(request): Json<VerifyTokenRequest>,
 ) -> Result<StatusCode, AuthAPIError> {
-    match validate_token(&request.token, state.banned_token_store.clone()).await {
+    let audience = request.audience.as_deref().unwrap_or(&JWT_AUDIENCE[0]);
+
+    match validate_token(
+        &request.token,
+        ExpectedAudience::Exactly(audience),
+        state.banned_token_store.clone(),
+        state.session_epoch_store.clone(),
+        state.session_store.clone(),
+    )
+    .await
+    {
         Ok(_) => Ok(StatusCode::OK),
         Err(_) => Err(AuthAPIError::InvalidToken),
     }
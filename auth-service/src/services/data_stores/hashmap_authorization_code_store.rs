@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use crate::domain::data_stores::{
+    AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError, AuthorizationGrant,
+};
+
+#[derive(Default)]
+pub struct HashmapAuthorizationCodeStore {
+    codes: HashMap<String, AuthorizationGrant>,
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for HashmapAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        self.codes.insert(code.as_ref().to_owned(), grant);
+        Ok(())
+    }
+
+    async fn take_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        self.codes
+            .remove(code.as_ref())
+            .ok_or(AuthorizationCodeStoreError::CodeNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+
+    #[tokio::test]
+    async fn test_code_can_only_be_taken_once() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+        let grant = AuthorizationGrant {
+            client_id: "app".to_owned(),
+            redirect_uri: "https://app.example.com/callback".to_owned(),
+            code_challenge: "challenge".to_owned(),
+            scope: None,
+            nonce: None,
+            email: Email::parse("test@example.com".to_owned()).unwrap(),
+            ip_address: None,
+            user_agent: None,
+        };
+
+        store.add_code(code.clone(), grant.clone()).await.unwrap();
+
+        assert_eq!(store.take_code(&code).await, Ok(grant));
+        assert_eq!(
+            store.take_code(&code).await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+}
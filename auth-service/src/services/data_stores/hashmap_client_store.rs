@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::domain::data_stores::{Client, ClientSecret, ClientStore, ClientStoreError};
+
+#[derive(Default)]
+pub struct HashmapClientStore {
+    clients: HashMap<String, (Client, Option<ClientSecret>)>,
+}
+
+#[async_trait::async_trait]
+impl ClientStore for HashmapClientStore {
+    async fn add_client(
+        &mut self,
+        client: Client,
+        secret: Option<ClientSecret>,
+    ) -> Result<(), ClientStoreError> {
+        if self.clients.contains_key(&client.id) {
+            return Err(ClientStoreError::ClientAlreadyExists);
+        }
+        self.clients.insert(client.id.clone(), (client, secret));
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<Client, ClientStoreError> {
+        match self.clients.get(client_id) {
+            Some((client, _)) => Ok(client.clone()),
+            None => Err(ClientStoreError::ClientNotFound),
+        }
+    }
+
+    async fn validate_client(
+        &self,
+        client_id: &str,
+        secret: Option<&ClientSecret>,
+    ) -> Result<Client, ClientStoreError> {
+        match self.clients.get(client_id) {
+            Some((client, stored_secret)) if stored_secret.as_ref() == secret => {
+                Ok(client.clone())
+            }
+            Some(_) => Err(ClientStoreError::InvalidCredentials),
+            None => Err(ClientStoreError::ClientNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(id: &str) -> Client {
+        Client {
+            id: id.to_owned(),
+            name: "App service".to_owned(),
+            redirect_uris: vec!["https://app.example.com/callback".to_owned()],
+            scopes: vec!["reports:read".to_owned()],
+            first_party: true,
+        }
+    }
+
+    fn secret(s: &str) -> ClientSecret {
+        ClientSecret::parse(s.repeat(32)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_client() {
+        let mut store = HashmapClientStore::default();
+
+        assert!(store.add_client(client("app"), Some(secret("a"))).await.is_ok());
+        assert_eq!(
+            store.add_client(client("app"), Some(secret("b"))).await,
+            Err(ClientStoreError::ClientAlreadyExists)
+        );
+        assert_eq!(store.get_client("app").await, Ok(client("app")));
+    }
+
+    #[tokio::test]
+    async fn test_validate_confidential_client() {
+        let mut store = HashmapClientStore::default();
+        store.add_client(client("app"), Some(secret("a"))).await.unwrap();
+
+        assert_eq!(
+            store.validate_client("app", Some(&secret("a"))).await,
+            Ok(client("app"))
+        );
+        assert_eq!(
+            store.validate_client("app", Some(&secret("b"))).await,
+            Err(ClientStoreError::InvalidCredentials)
+        );
+        assert_eq!(
+            store.validate_client("app", None).await,
+            Err(ClientStoreError::InvalidCredentials)
+        );
+        assert_eq!(
+            store.validate_client("unknown", Some(&secret("a"))).await,
+            Err(ClientStoreError::ClientNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_public_client() {
+        let mut store = HashmapClientStore::default();
+        store.add_client(client("spa"), None).await.unwrap();
+
+        assert_eq!(store.validate_client("spa", None).await, Ok(client("spa")));
+        assert_eq!(
+            store.validate_client("spa", Some(&secret("a"))).await,
+            Err(ClientStoreError::InvalidCredentials)
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::domain::{
+    data_stores::{EmailToken, EmailTokenPurpose, EmailTokenStore, EmailTokenStoreError},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashmapEmailTokenStore {
+    tokens: HashMap<(EmailTokenPurpose, String), (Email, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl EmailTokenStore for HashmapEmailTokenStore {
+    async fn add_token(
+        &mut self,
+        token: EmailToken,
+        email: Email,
+        purpose: EmailTokenPurpose,
+        ttl_seconds: u64,
+    ) -> Result<(), EmailTokenStoreError> {
+        let expires_at = Utc::now() + Duration::seconds(ttl_seconds as i64);
+        self.tokens
+            .insert((purpose, token.as_ref().to_owned()), (email, expires_at));
+        Ok(())
+    }
+
+    async fn take_token(
+        &mut self,
+        token: &EmailToken,
+        purpose: EmailTokenPurpose,
+    ) -> Result<Email, EmailTokenStoreError> {
+        match self.tokens.remove(&(purpose, token.as_ref().to_owned())) {
+            Some((email, expires_at)) if Utc::now() < expires_at => Ok(email),
+            _ => Err(EmailTokenStoreError::TokenNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_token_can_only_be_taken_once() {
+        let mut store = HashmapEmailTokenStore::default();
+        let token = EmailToken::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+
+        store
+            .add_token(
+                token.clone(),
+                email.clone(),
+                EmailTokenPurpose::VerifyEmail,
+                60,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store
+                .take_token(&token, EmailTokenPurpose::VerifyEmail)
+                .await,
+            Ok(email)
+        );
+        assert_eq!(
+            store
+                .take_token(&token, EmailTokenPurpose::VerifyEmail)
+                .await,
+            Err(EmailTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_not_found() {
+        let mut store = HashmapEmailTokenStore::default();
+        let token = EmailToken::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+
+        store
+            .add_token(token.clone(), email, EmailTokenPurpose::VerifyEmail, 0)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store
+                .take_token(&token, EmailTokenPurpose::VerifyEmail)
+                .await,
+            Err(EmailTokenStoreError::TokenNotFound)
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use crate::domain::data_stores::{
+    PasskeyCeremony, PasskeyChallenge, PasskeyChallengeStore, PasskeyChallengeStoreError,
+};
+
+#[derive(Default)]
+pub struct HashmapPasskeyChallengeStore {
+    challenges: HashMap<String, PasskeyCeremony>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyChallengeStore for HashmapPasskeyChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: PasskeyChallenge,
+        ceremony: PasskeyCeremony,
+    ) -> Result<(), PasskeyChallengeStoreError> {
+        self.challenges
+            .insert(challenge.as_ref().to_owned(), ceremony);
+        Ok(())
+    }
+
+    async fn take_challenge(
+        &mut self,
+        challenge: &PasskeyChallenge,
+    ) -> Result<PasskeyCeremony, PasskeyChallengeStoreError> {
+        self.challenges
+            .remove(challenge.as_ref())
+            .ok_or(PasskeyChallengeStoreError::ChallengeNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+
+    #[tokio::test]
+    async fn test_challenge_can_only_be_taken_once() {
+        let mut store = HashmapPasskeyChallengeStore::default();
+        let challenge = PasskeyChallenge::default();
+        let ceremony =
+            PasskeyCeremony::Registration(Email::parse("test@example.com".to_owned()).unwrap());
+
+        store
+            .add_challenge(challenge.clone(), ceremony.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(store.take_challenge(&challenge).await, Ok(ceremony));
+        assert_eq!(
+            store.take_challenge(&challenge).await,
+            Err(PasskeyChallengeStoreError::ChallengeNotFound)
+        );
+    }
+}
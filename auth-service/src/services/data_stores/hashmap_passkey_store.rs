@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{Passkey, PasskeyStore, PasskeyStoreError},
+    email::Email,
+};
+
+#[derive(Default)]
+pub struct HashmapPasskeyStore {
+    passkeys: HashMap<String, Passkey>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for HashmapPasskeyStore {
+    async fn add_passkey(&mut self, passkey: Passkey) -> Result<(), PasskeyStoreError> {
+        if self.passkeys.contains_key(&passkey.credential_id) {
+            return Err(PasskeyStoreError::PasskeyAlreadyExists);
+        }
+        self.passkeys.insert(passkey.credential_id.clone(), passkey);
+        Ok(())
+    }
+
+    async fn get_passkey(&self, credential_id: &str) -> Result<Passkey, PasskeyStoreError> {
+        self.passkeys
+            .get(credential_id)
+            .cloned()
+            .ok_or(PasskeyStoreError::PasskeyNotFound)
+    }
+
+    async fn list_passkeys(&self, email: &Email) -> Result<Vec<Passkey>, PasskeyStoreError> {
+        let mut passkeys: Vec<Passkey> = self
+            .passkeys
+            .values()
+            .filter(|passkey| &passkey.email == email)
+            .cloned()
+            .collect();
+        passkeys.sort_by_key(|passkey| passkey.created_at);
+        Ok(passkeys)
+    }
+
+    async fn update_sign_count(
+        &mut self,
+        credential_id: &str,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError> {
+        let passkey = self
+            .passkeys
+            .get_mut(credential_id)
+            .ok_or(PasskeyStoreError::PasskeyNotFound)?;
+        passkey.sign_count = sign_count;
+        Ok(())
+    }
+
+    async fn change_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), PasskeyStoreError> {
+        for passkey in self
+            .passkeys
+            .values_mut()
+            .filter(|passkey| &passkey.email == email)
+        {
+            passkey.email = new_email.clone();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn passkey(credential_id: &str, email: &str) -> Passkey {
+        Passkey {
+            credential_id: credential_id.to_owned(),
+            email: Email::parse(email.to_owned()).unwrap(),
+            public_key: vec![4; 65],
+            sign_count: 0,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_passkey() {
+        let mut store = HashmapPasskeyStore::default();
+
+        assert_eq!(
+            store.add_passkey(passkey("a", "test@example.com")).await,
+            Ok(())
+        );
+        assert_eq!(
+            store.add_passkey(passkey("a", "other@example.com")).await,
+            Err(PasskeyStoreError::PasskeyAlreadyExists)
+        );
+        assert_eq!(
+            store.get_passkey("b").await,
+            Err(PasskeyStoreError::PasskeyNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_passkeys() {
+        let mut store = HashmapPasskeyStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        store
+            .add_passkey(passkey("a", "test@example.com"))
+            .await
+            .unwrap();
+        store
+            .add_passkey(passkey("b", "test@example.com"))
+            .await
+            .unwrap();
+        store
+            .add_passkey(passkey("c", "other@example.com"))
+            .await
+            .unwrap();
+
+        let ids: Vec<String> = store
+            .list_passkeys(&email)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|passkey| passkey.credential_id)
+            .collect();
+        assert_eq!(ids, vec!["a".to_owned(), "b".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn test_update_sign_count() {
+        let mut store = HashmapPasskeyStore::default();
+        store
+            .add_passkey(passkey("a", "test@example.com"))
+            .await
+            .unwrap();
+
+        store.update_sign_count("a", 5).await.unwrap();
+
+        assert_eq!(store.get_passkey("a").await.unwrap().sign_count, 5);
+        assert_eq!(
+            store.update_sign_count("b", 5).await,
+            Err(PasskeyStoreError::PasskeyNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_change_email() {
+        let mut store = HashmapPasskeyStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let new_email = Email::parse("new@example.com".to_owned()).unwrap();
+        store
+            .add_passkey(passkey("a", "test@example.com"))
+            .await
+            .unwrap();
+        store
+            .add_passkey(passkey("b", "other@example.com"))
+            .await
+            .unwrap();
+
+        store.change_email(&email, &new_email).await.unwrap();
+
+        assert!(store.list_passkeys(&email).await.unwrap().is_empty());
+        assert_eq!(store.list_passkeys(&new_email).await.unwrap().len(), 1);
+        assert_eq!(
+            store.get_passkey("b").await.unwrap().email.as_ref(),
+            "other@example.com"
+        );
+    }
+}
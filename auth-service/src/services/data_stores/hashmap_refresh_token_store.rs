@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::{
+    data_stores::{RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError},
+    email::Email,
+};
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+    tokens: HashMap<String, RefreshTokenRecord>,
+    revoked_families: HashSet<String>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: String,
+        epoch: u64,
+        audience: String,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let record = RefreshTokenRecord {
+            email,
+            family_id,
+            epoch,
+            audience,
+            used: false,
+        };
+        self.tokens.insert(token.as_ref().to_owned(), record);
+        Ok(())
+    }
+
+    async fn get_token(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        match self.tokens.get(token.as_ref()) {
+            Some(record) if !self.revoked_families.contains(&record.family_id) => {
+                Ok(record.clone())
+            }
+            _ => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn mark_token_used(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        match self.tokens.get_mut(token.as_ref()) {
+            Some(record) => {
+                record.used = true;
+                Ok(())
+            }
+            None => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        self.revoked_families.insert(family_id.to_owned());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = RefreshToken::default();
+
+        let result = store
+            .add_token(
+                token.clone(),
+                email.clone(),
+                "family".to_owned(),
+                0,
+                "app".to_owned(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        let result = store.get_token(&token).await;
+        assert_eq!(
+            result,
+            Ok(RefreshTokenRecord {
+                email,
+                family_id: "family".to_owned(),
+                epoch: 0,
+                audience: "app".to_owned(),
+                used: false,
+            })
+        );
+
+        let result = store.get_token(&RefreshToken::default()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_mark_token_used() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = RefreshToken::default();
+        store
+            .add_token(
+                token.clone(),
+                email,
+                "family".to_owned(),
+                0,
+                "app".to_owned(),
+            )
+            .await
+            .unwrap();
+
+        let result = store.mark_token_used(&token).await;
+        assert!(result.is_ok());
+        assert!(store.get_token(&token).await.unwrap().used);
+
+        let result = store.mark_token_used(&RefreshToken::default()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let first = RefreshToken::default();
+        let second = RefreshToken::default();
+        let other = RefreshToken::default();
+        store
+            .add_token(
+                first.clone(),
+                email.clone(),
+                "family".to_owned(),
+                0,
+                "app".to_owned(),
+            )
+            .await
+            .unwrap();
+        store
+            .add_token(
+                second.clone(),
+                email.clone(),
+                "family".to_owned(),
+                0,
+                "app".to_owned(),
+            )
+            .await
+            .unwrap();
+        store
+            .add_token(
+                other.clone(),
+                email,
+                "other".to_owned(),
+                0,
+                "app".to_owned(),
+            )
+            .await
+            .unwrap();
+
+        let result = store.revoke_family("family").await;
+        assert!(result.is_ok());
+
+        assert_eq!(
+            store.get_token(&first).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+        assert_eq!(
+            store.get_token(&second).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+        assert!(store.get_token(&other).await.is_ok());
+    }
+}
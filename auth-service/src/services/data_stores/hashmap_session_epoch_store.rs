@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{SessionEpochStore, SessionEpochStoreError},
+    email::Email,
+};
+
+#[derive(Default)]
+pub struct HashmapSessionEpochStore {
+    epochs: HashMap<Email, u64>,
+}
+
+#[async_trait::async_trait]
+impl SessionEpochStore for HashmapSessionEpochStore {
+    async fn get_epoch(&self, email: &Email) -> Result<u64, SessionEpochStoreError> {
+        Ok(self.epochs.get(email).copied().unwrap_or_default())
+    }
+
+    async fn increment_epoch(&mut self, email: &Email) -> Result<u64, SessionEpochStoreError> {
+        let epoch = self.epochs.entry(email.clone()).or_default();
+        *epoch += 1;
+        Ok(*epoch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_epoch_defaults_to_zero() {
+        let store = HashmapSessionEpochStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+
+        assert_eq!(store.get_epoch(&email).await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_increment_epoch() {
+        let mut store = HashmapSessionEpochStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+
+        assert_eq!(store.increment_epoch(&email).await, Ok(1));
+        assert_eq!(store.increment_epoch(&email).await, Ok(2));
+        assert_eq!(store.get_epoch(&email).await, Ok(2));
+        assert_eq!(store.get_epoch(&other).await, Ok(0));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::domain::{
+    data_stores::{Session, SessionStore, SessionStoreError},
+    email::Email,
+};
+
+#[derive(Default)]
+pub struct HashmapSessionStore {
+    sessions: HashMap<String, Session>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        match self.sessions.get(id) {
+            Some(session) if session.expires_at > Utc::now() => Ok(session.clone()),
+            _ => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn list_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let now = Utc::now();
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| &session.email == email && session.expires_at > now)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+        Ok(sessions)
+    }
+
+    async fn extend_session(
+        &mut self,
+        id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), SessionStoreError> {
+        match self.sessions.get_mut(id) {
+            Some(session) => {
+                session.expires_at = expires_at;
+                Ok(())
+            }
+            None => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn revoke_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError> {
+        match self.sessions.get(id) {
+            Some(session) if &session.email == email => {
+                self.sessions.remove(id);
+                Ok(())
+            }
+            _ => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn revoke_all_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        self.sessions.retain(|_, session| &session.email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, email: &Email) -> Session {
+        let now = Utc::now();
+        Session {
+            id: id.to_owned(),
+            email: email.clone(),
+            created_at: now,
+            expires_at: now + chrono::Duration::try_hours(1).unwrap(),
+            ip_address: Some("127.0.0.1".to_owned()),
+            user_agent: Some("test".to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_session() {
+        let mut store = HashmapSessionStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let session = session("first", &email);
+
+        let result = store.add_session(session.clone()).await;
+        assert!(result.is_ok());
+
+        assert_eq!(store.get_session("first").await, Ok(session));
+        assert_eq!(
+            store.get_session("missing").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_is_not_returned() {
+        let mut store = HashmapSessionStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        store.add_session(session("first", &email)).await.unwrap();
+
+        store
+            .extend_session("first", Utc::now() - chrono::Duration::try_seconds(1).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_session("first").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert_eq!(store.list_sessions(&email).await, Ok(Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        store.add_session(session("first", &email)).await.unwrap();
+        store.add_session(session("second", &email)).await.unwrap();
+        store.add_session(session("other", &other)).await.unwrap();
+
+        let sessions = store.list_sessions(&email).await.unwrap();
+        let mut ids: Vec<_> = sessions.iter().map(|s| s.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_session() {
+        let mut store = HashmapSessionStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        store.add_session(session("first", &email)).await.unwrap();
+
+        // Sessions can only be revoked by their owner
+        assert_eq!(
+            store.revoke_session(&other, "first").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert!(store.get_session("first").await.is_ok());
+
+        assert!(store.revoke_session(&email, "first").await.is_ok());
+        assert_eq!(
+            store.get_session("first").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        store.add_session(session("first", &email)).await.unwrap();
+        store.add_session(session("second", &email)).await.unwrap();
+        store.add_session(session("other", &other)).await.unwrap();
+
+        assert!(store.revoke_all_sessions(&email).await.is_ok());
+        assert_eq!(store.list_sessions(&email).await, Ok(Vec::new()));
+        assert!(store.get_session("other").await.is_ok());
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{TotpSecret, TotpStore, TotpStoreError},
+    email::Email,
+};
+
+#[derive(Default)]
+pub struct HashmapTotpStore {
+    enrollments: HashMap<Email, TotpEnrollment>,
+}
+
+#[derive(Default)]
+struct TotpEnrollment {
+    secret: Option<TotpSecret>,
+    pending_secret: Option<TotpSecret>,
+    last_used_step: Option<u64>,
+}
+
+#[async_trait::async_trait]
+impl TotpStore for HashmapTotpStore {
+    async fn set_pending_secret(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), TotpStoreError> {
+        self.enrollments.entry(email.clone()).or_default().pending_secret = Some(secret);
+        Ok(())
+    }
+
+    async fn get_pending_secret(&self, email: &Email) -> Result<TotpSecret, TotpStoreError> {
+        self.enrollments
+            .get(email)
+            .and_then(|enrollment| enrollment.pending_secret.clone())
+            .ok_or(TotpStoreError::SecretNotFound)
+    }
+
+    async fn activate_secret(
+        &mut self,
+        email: &Email,
+        time_step: u64,
+    ) -> Result<(), TotpStoreError> {
+        let enrollment = self
+            .enrollments
+            .get_mut(email)
+            .ok_or(TotpStoreError::SecretNotFound)?;
+        let secret = enrollment
+            .pending_secret
+            .take()
+            .ok_or(TotpStoreError::SecretNotFound)?;
+        enrollment.secret = Some(secret);
+        enrollment.last_used_step = Some(time_step);
+        Ok(())
+    }
+
+    async fn get_secret(&self, email: &Email) -> Result<TotpSecret, TotpStoreError> {
+        self.enrollments
+            .get(email)
+            .and_then(|enrollment| enrollment.secret.clone())
+            .ok_or(TotpStoreError::SecretNotFound)
+    }
+
+    async fn use_time_step(
+        &mut self,
+        email: &Email,
+        time_step: u64,
+    ) -> Result<(), TotpStoreError> {
+        let enrollment = self
+            .enrollments
+            .get_mut(email)
+            .filter(|enrollment| enrollment.secret.is_some())
+            .ok_or(TotpStoreError::SecretNotFound)?;
+        if enrollment
+            .last_used_step
+            .is_some_and(|last_used_step| time_step <= last_used_step)
+        {
+            return Err(TotpStoreError::CodeAlreadyUsed);
+        }
+        enrollment.last_used_step = Some(time_step);
+        Ok(())
+    }
+
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpStoreError> {
+        self.enrollments.remove(email);
+        Ok(())
+    }
+
+    async fn change_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), TotpStoreError> {
+        if let Some(enrollment) = self.enrollments.remove(email) {
+            self.enrollments.insert(new_email.clone(), enrollment);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse("test@example.com".to_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pending_secret_is_not_active() {
+        let mut store = HashmapTotpStore::default();
+        let secret = TotpSecret::default();
+
+        store.set_pending_secret(&email(), secret.clone()).await.unwrap();
+
+        assert_eq!(store.get_pending_secret(&email()).await, Ok(secret));
+        assert_eq!(
+            store.get_secret(&email()).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+        assert_eq!(
+            store.use_time_step(&email(), 1).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_activate_secret() {
+        let mut store = HashmapTotpStore::default();
+        let secret = TotpSecret::default();
+
+        assert_eq!(
+            store.activate_secret(&email(), 1).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+
+        store.set_pending_secret(&email(), secret.clone()).await.unwrap();
+        store.activate_secret(&email(), 1).await.unwrap();
+
+        assert_eq!(store.get_secret(&email()).await, Ok(secret));
+        assert_eq!(
+            store.get_pending_secret(&email()).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_use_time_step_rejects_replays() {
+        let mut store = HashmapTotpStore::default();
+        store
+            .set_pending_secret(&email(), TotpSecret::default())
+            .await
+            .unwrap();
+        store.activate_secret(&email(), 10).await.unwrap();
+
+        assert_eq!(
+            store.use_time_step(&email(), 10).await,
+            Err(TotpStoreError::CodeAlreadyUsed)
+        );
+        assert_eq!(store.use_time_step(&email(), 11).await, Ok(()));
+        assert_eq!(
+            store.use_time_step(&email(), 11).await,
+            Err(TotpStoreError::CodeAlreadyUsed)
+        );
+        assert_eq!(
+            store.use_time_step(&email(), 9).await,
+            Err(TotpStoreError::CodeAlreadyUsed)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_secret() {
+        let mut store = HashmapTotpStore::default();
+        store
+            .set_pending_secret(&email(), TotpSecret::default())
+            .await
+            .unwrap();
+        store.activate_secret(&email(), 1).await.unwrap();
+        store
+            .set_pending_secret(&email(), TotpSecret::default())
+            .await
+            .unwrap();
+
+        assert_eq!(store.remove_secret(&email()).await, Ok(()));
+        assert_eq!(
+            store.get_secret(&email()).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+        assert_eq!(
+            store.get_pending_secret(&email()).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+
+        // Removing nothing is fine
+        assert_eq!(store.remove_secret(&email()).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_change_email() {
+        let mut store = HashmapTotpStore::default();
+        let new_email = Email::parse("new@example.com".to_owned()).unwrap();
+        let secret = TotpSecret::default();
+        store
+            .set_pending_secret(&email(), secret.clone())
+            .await
+            .unwrap();
+        store.activate_secret(&email(), 1).await.unwrap();
+
+        assert_eq!(store.change_email(&email(), &new_email).await, Ok(()));
+        assert_eq!(
+            store.get_secret(&email()).await,
+            Err(TotpStoreError::SecretNotFound)
+        );
+        assert_eq!(store.get_secret(&new_email).await, Ok(secret));
+        // The code that confirmed the secret still can't be reused
+        assert_eq!(
+            store.use_time_step(&new_email, 1).await,
+            Err(TotpStoreError::CodeAlreadyUsed)
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{
+    data_stores::{TrustedDevice, TrustedDeviceStore, TrustedDeviceStoreError},
+    email::Email,
+};
+
+#[derive(Default)]
+pub struct HashmapTrustedDeviceStore {
+    devices: HashMap<String, TrustedDevice>,
+}
+
+#[async_trait::async_trait]
+impl TrustedDeviceStore for HashmapTrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError> {
+        self.devices.insert(device.id.clone(), device);
+        Ok(())
+    }
+
+    async fn get_device(&self, id: &str) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+        match self.devices.get(id) {
+            Some(device) if device.expires_at > Utc::now() => Ok(device.clone()),
+            _ => Err(TrustedDeviceStoreError::DeviceNotFound),
+        }
+    }
+
+    async fn list_devices(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError> {
+        let now = Utc::now();
+        let mut devices: Vec<TrustedDevice> = self
+            .devices
+            .values()
+            .filter(|device| &device.email == email && device.expires_at > now)
+            .cloned()
+            .collect();
+        devices.sort_by_key(|device| std::cmp::Reverse(device.created_at));
+        Ok(devices)
+    }
+
+    async fn revoke_device(
+        &mut self,
+        email: &Email,
+        id: &str,
+    ) -> Result<(), TrustedDeviceStoreError> {
+        match self.devices.get(id) {
+            Some(device) if &device.email == email => {
+                self.devices.remove(id);
+                Ok(())
+            }
+            _ => Err(TrustedDeviceStoreError::DeviceNotFound),
+        }
+    }
+
+    async fn revoke_all_devices(&mut self, email: &Email) -> Result<(), TrustedDeviceStoreError> {
+        self.devices.retain(|_, device| &device.email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(id: &str, email: &Email) -> TrustedDevice {
+        let now = Utc::now();
+        TrustedDevice {
+            id: id.to_owned(),
+            email: email.clone(),
+            created_at: now,
+            expires_at: now + chrono::Duration::try_days(30).unwrap(),
+            ip_address: Some("127.0.0.1".to_owned()),
+            user_agent: Some("test".to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_device() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let device = device("laptop", &email);
+
+        let result = store.add_device(device.clone()).await;
+        assert!(result.is_ok());
+
+        assert_eq!(store.get_device("laptop").await, Ok(device));
+        assert_eq!(
+            store.get_device("missing").await,
+            Err(TrustedDeviceStoreError::DeviceNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_device_is_not_returned() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let mut device = device("laptop", &email);
+        device.expires_at = Utc::now() - chrono::Duration::try_seconds(1).unwrap();
+        store.add_device(device).await.unwrap();
+
+        assert_eq!(
+            store.get_device("laptop").await,
+            Err(TrustedDeviceStoreError::DeviceNotFound)
+        );
+        assert_eq!(store.list_devices(&email).await, Ok(Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_device() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        store.add_device(device("laptop", &email)).await.unwrap();
+
+        // Devices can only be revoked by their owner
+        assert_eq!(
+            store.revoke_device(&other, "laptop").await,
+            Err(TrustedDeviceStoreError::DeviceNotFound)
+        );
+        assert!(store.get_device("laptop").await.is_ok());
+
+        assert!(store.revoke_device(&email, "laptop").await.is_ok());
+        assert_eq!(
+            store.get_device("laptop").await,
+            Err(TrustedDeviceStoreError::DeviceNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_devices() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        store.add_device(device("laptop", &email)).await.unwrap();
+        store.add_device(device("phone", &email)).await.unwrap();
+        store.add_device(device("other", &other)).await.unwrap();
+
+        assert_eq!(store.list_devices(&email).await.unwrap().len(), 2);
+
+        assert!(store.revoke_all_devices(&email).await.is_ok());
+        assert_eq!(store.list_devices(&email).await, Ok(Vec::new()));
+        assert!(store.get_device("other").await.is_ok());
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use chrono::{DateTime, Duration, Utc};
+
 use crate::domain::{
     data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
     email::Email,
@@ -7,7 +9,17 @@ use crate::domain::{
 
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: HashMap<LoginAttemptId, TwoFAEntry>,
+}
+
+struct TwoFAEntry {
+    email: Email,
+    /// `None` once too many wrong guesses invalidated it.
+    code: Option<TwoFACode>,
+    failed_attempts: u32,
+    resends: u32,
+    /// When the code was first sent or last resent.
+    last_sent_at: DateTime<Utc>,
 }
 
 #[async_trait::async_trait]
@@ -18,32 +30,92 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        if self.codes.contains_key(&email) {
-            return Err(TwoFACodeStoreError::UnexpectedError);
-        }
-        self.codes.insert(email.clone(), (login_attempt_id, code));
+        self.codes.insert(
+            login_attempt_id,
+            TwoFAEntry {
+                email,
+                code: Some(code),
+                failed_attempts: 0,
+                resends: 0,
+                last_sent_at: Utc::now(),
+            },
+        );
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        match self.codes.get(email) {
-            Some(_) => {
-                self.codes.remove(email);
-                Ok(())
-            }
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        match self.codes.remove(login_attempt_id) {
+            Some(_) => Ok(()),
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
 
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        match self.codes.get(email) {
-            Some(code) => Ok(code.clone()),
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode), TwoFACodeStoreError> {
+        match self.codes.get(login_attempt_id) {
+            Some(TwoFAEntry {
+                email,
+                code: Some(code),
+                ..
+            }) => Ok((email.clone(), code.clone())),
+            Some(_) => Err(TwoFACodeStoreError::TooManyAttempts),
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn record_failed_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let entry = self
+            .codes
+            .get_mut(login_attempt_id)
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        entry.failed_attempts += 1;
+        if entry.failed_attempts >= max_attempts {
+            entry.code = None;
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+
+        Ok(())
+    }
+
+    async fn resend_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        code: TwoFACode,
+        cooldown_seconds: u64,
+        max_resends: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let entry = self
+            .codes
+            .get_mut(login_attempt_id)
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        if entry.code.is_none() {
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+        if entry.resends >= max_resends {
+            return Err(TwoFACodeStoreError::TooManyResends);
+        }
+        let now = Utc::now();
+        let cooldown = Duration::seconds(cooldown_seconds as i64);
+        if now < entry.last_sent_at + cooldown {
+            return Err(TwoFACodeStoreError::ResendTooSoon);
+        }
+
+        entry.code = Some(code);
+        entry.resends += 1;
+        entry.last_sent_at = now;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -62,11 +134,22 @@ mod tests {
             .await;
         assert!(result.is_ok());
 
-        // If we try to add the same code again, it should return an error
+        // A second login attempt for the same user doesn't disturb the first
+        let other_login_attempt_id = LoginAttemptId::default();
+        let other_code = TwoFACode::default();
         let result = code_store
-            .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+            .add_code(
+                email.clone(),
+                other_login_attempt_id.clone(),
+                other_code.clone(),
+            )
             .await;
-        assert_eq!(result, Err(TwoFACodeStoreError::UnexpectedError));
+        assert!(result.is_ok());
+
+        let result = code_store.get_code(&login_attempt_id).await;
+        assert_eq!(result, Ok((email.clone(), code)));
+        let result = code_store.get_code(&other_login_attempt_id).await;
+        assert_eq!(result, Ok((email, other_code)));
     }
 
     #[tokio::test]
@@ -80,13 +163,13 @@ mod tests {
             .await;
         assert!(result.is_ok());
 
-        let result = code_store.remove_code(&email).await;
+        let result = code_store.remove_code(&login_attempt_id).await;
         assert!(result.is_ok());
 
-        let result = code_store.remove_code(&email).await;
+        let result = code_store.remove_code(&login_attempt_id).await;
         assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
 
-        let result = code_store.get_code(&email).await;
+        let result = code_store.get_code(&login_attempt_id).await;
         assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
     }
 
@@ -101,12 +184,128 @@ mod tests {
             .await;
         assert!(result.is_ok());
 
-        let result = code_store.get_code(&email).await;
-        assert_eq!(result, Ok((login_attempt_id, code)));
+        let result = code_store.get_code(&login_attempt_id).await;
+        assert_eq!(result, Ok((email, code)));
+
+        let result = code_store.get_code(&LoginAttemptId::default()).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_record_failed_attempt() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+
+        let result = code_store.record_failed_attempt(&login_attempt_id, 3).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
+        code_store
+            .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+            .await
+            .unwrap();
+
+        assert!(code_store
+            .record_failed_attempt(&login_attempt_id, 3)
+            .await
+            .is_ok());
+        assert!(code_store
+            .record_failed_attempt(&login_attempt_id, 3)
+            .await
+            .is_ok());
+        assert_eq!(
+            code_store.get_code(&login_attempt_id).await,
+            Ok((email.clone(), code.clone()))
+        );
+
+        let result = code_store.record_failed_attempt(&login_attempt_id, 3).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::TooManyAttempts));
+        let result = code_store.get_code(&login_attempt_id).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::TooManyAttempts));
+
+        // The next login attempt starts over
+        let login_attempt_id = LoginAttemptId::default();
+        code_store
+            .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            code_store.get_code(&login_attempt_id).await,
+            Ok((email, code))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resend_code() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        code_store
+            .add_code(email.clone(), login_attempt_id.clone(), TwoFACode::default())
+            .await
+            .unwrap();
 
         let result = code_store
-            .get_code(&Email::parse("thisdoesntexist@example.com".to_owned()).unwrap())
+            .resend_code(&LoginAttemptId::default(), TwoFACode::default(), 0, 2)
             .await;
         assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
+        // The cooldown starts when the code is first sent
+        let result = code_store
+            .resend_code(&login_attempt_id, TwoFACode::default(), 60, 2)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::ResendTooSoon));
+
+        let new_code = TwoFACode::default();
+        let result = code_store
+            .resend_code(&login_attempt_id, new_code.clone(), 0, 2)
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(
+            code_store.get_code(&login_attempt_id).await,
+            Ok((email, new_code))
+        );
+
+        let result = code_store
+            .resend_code(&login_attempt_id, TwoFACode::default(), 60, 2)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::ResendTooSoon));
+
+        let result = code_store
+            .resend_code(&login_attempt_id, TwoFACode::default(), 0, 2)
+            .await;
+        assert!(result.is_ok());
+        let result = code_store
+            .resend_code(&login_attempt_id, TwoFACode::default(), 0, 2)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::TooManyResends));
+    }
+
+    #[tokio::test]
+    async fn test_resend_code_keeps_failed_attempts() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        code_store
+            .add_code(email, login_attempt_id.clone(), TwoFACode::default())
+            .await
+            .unwrap();
+
+        assert!(code_store
+            .record_failed_attempt(&login_attempt_id, 2)
+            .await
+            .is_ok());
+        code_store
+            .resend_code(&login_attempt_id, TwoFACode::default(), 0, 1)
+            .await
+            .unwrap();
+
+        let result = code_store.record_failed_attempt(&login_attempt_id, 2).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::TooManyAttempts));
+        let result = code_store
+            .resend_code(&login_attempt_id, TwoFACode::default(), 0, 1)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::TooManyAttempts));
     }
 }
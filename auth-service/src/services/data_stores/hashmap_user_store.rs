@@ -1,10 +1,16 @@
 use std::collections::HashMap;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use crate::domain::{
+    CodeChannel, Email, EmailToken, Password, PhoneNumber, RecoveryCode, TwoFACode, User,
+    UserStore, UserStoreError,
+};
 
 #[derive(Default)]
 pub struct HashmapUserStore {
     users: HashMap<Email, User>,
+    recovery_codes: HashMap<Email, Vec<RecoveryCode>>,
+    pending_phone_numbers: HashMap<Email, (PhoneNumber, TwoFACode)>,
+    pending_emails: HashMap<Email, (Email, EmailToken)>,
 }
 
 #[async_trait::async_trait]
@@ -40,6 +46,162 @@ impl UserStore for HashmapUserStore {
             None => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.password = password;
+        Ok(())
+    }
+
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.requires_2fa = requires_2fa;
+        Ok(())
+    }
+
+    async fn set_code_channel(
+        &mut self,
+        email: &Email,
+        channel: CodeChannel,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.code_channel = channel;
+        Ok(())
+    }
+
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.email_verified = true;
+        Ok(())
+    }
+
+    async fn set_pending_email(
+        &mut self,
+        email: &Email,
+        new_email: Email,
+        token: &EmailToken,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        self.pending_emails
+            .insert(email.clone(), (new_email, token.clone()));
+        Ok(())
+    }
+
+    async fn change_email(
+        &mut self,
+        email: &Email,
+        token: &EmailToken,
+    ) -> Result<Email, UserStoreError> {
+        let new_email = match self.pending_emails.get(email) {
+            Some((new_email, expected_token)) if expected_token == token => new_email.clone(),
+            _ => return Err(UserStoreError::InvalidCredentials),
+        };
+        if self.users.contains_key(&new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+
+        let mut user = self
+            .users
+            .remove(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        self.pending_emails.remove(email);
+        user.email = new_email.clone();
+        self.users.insert(new_email.clone(), user);
+        if let Some(codes) = self.recovery_codes.remove(email) {
+            self.recovery_codes.insert(new_email.clone(), codes);
+        }
+        if let Some(pending) = self.pending_phone_numbers.remove(email) {
+            self.pending_phone_numbers.insert(new_email.clone(), pending);
+        }
+
+        Ok(new_email)
+    }
+
+    async fn set_pending_phone_number(
+        &mut self,
+        email: &Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        self.pending_phone_numbers
+            .insert(email.clone(), (phone_number, code));
+        Ok(())
+    }
+
+    async fn confirm_phone_number(
+        &mut self,
+        email: &Email,
+        code: &TwoFACode,
+    ) -> Result<PhoneNumber, UserStoreError> {
+        let (phone_number, expected_code) = self
+            .pending_phone_numbers
+            .remove(email)
+            .ok_or(UserStoreError::InvalidCredentials)?;
+        if expected_code != *code {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.phone_number = Some(phone_number.clone());
+        Ok(phone_number)
+    }
+
+    async fn set_recovery_codes(
+        &mut self,
+        email: &Email,
+        codes: &[RecoveryCode],
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        self.recovery_codes.insert(email.clone(), codes.to_vec());
+        Ok(())
+    }
+
+    async fn use_recovery_code(
+        &mut self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<(), UserStoreError> {
+        let codes = self
+            .recovery_codes
+            .get_mut(email)
+            .ok_or(UserStoreError::InvalidCredentials)?;
+        let position = codes
+            .iter()
+            .position(|c| c == code)
+            .ok_or(UserStoreError::InvalidCredentials)?;
+        codes.remove(position);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -53,6 +215,9 @@ mod tests {
             email: Email::parse("test@example.com".to_owned()).unwrap(),
             password: Password::parse("password".to_owned()).unwrap(),
             requires_2fa: false,
+            phone_number: None,
+            code_channel: CodeChannel::Email,
+            email_verified: false,
         };
 
         // Test adding a new user
@@ -73,6 +238,9 @@ mod tests {
             email: email.clone(),
             password: Password::parse("password".to_owned()).unwrap(),
             requires_2fa: false,
+            phone_number: None,
+            code_channel: CodeChannel::Email,
+            email_verified: false,
         };
 
         // Test getting a user that exists
@@ -98,6 +266,9 @@ mod tests {
             email: email.clone(),
             password: password.clone(),
             requires_2fa: false,
+            phone_number: None,
+            code_channel: CodeChannel::Email,
+            email_verified: false,
         };
 
         // Test validating a user that exists with correct password
@@ -120,4 +291,238 @@ mod tests {
 
         assert_eq!(result, Err(UserStoreError::UserNotFound));
     }
+
+    #[tokio::test]
+    async fn test_set_requires_2fa() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+
+        // Test updating a user that doesn't exist
+        let result = user_store.set_requires_2fa(&email, true).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+
+        user_store.users.insert(
+            email.clone(),
+            User::new(
+                email.clone(),
+                Password::parse("password".to_owned()).unwrap(),
+                false,
+            ),
+        );
+
+        // Test turning 2FA on and off again
+        let result = user_store.set_requires_2fa(&email, true).await;
+        assert_eq!(result, Ok(()));
+        assert!(user_store.get_user(&email).await.unwrap().requires_2fa);
+
+        let result = user_store.set_requires_2fa(&email, false).await;
+        assert_eq!(result, Ok(()));
+        assert!(!user_store.get_user(&email).await.unwrap().requires_2fa);
+    }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let old_password = Password::parse("password".to_owned()).unwrap();
+        let new_password = Password::parse("new password".to_owned()).unwrap();
+
+        let result = user_store
+            .update_password(&email, new_password.clone())
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+
+        user_store.users.insert(
+            email.clone(),
+            User::new(email.clone(), old_password.clone(), false),
+        );
+
+        let result = user_store
+            .update_password(&email, new_password.clone())
+            .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            user_store.validate_user(&email, &old_password).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert_eq!(
+            user_store.validate_user(&email, &new_password).await,
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_change_email() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let new_email = Email::parse("new@example.com".to_owned()).unwrap();
+        let password = Password::parse("password".to_owned()).unwrap();
+        let token = EmailToken::default();
+
+        let result = user_store
+            .set_pending_email(&email, new_email.clone(), &token)
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+
+        user_store
+            .add_user(User::new(email.clone(), password.clone(), false))
+            .await
+            .unwrap();
+        user_store
+            .set_pending_email(&email, new_email.clone(), &token)
+            .await
+            .unwrap();
+
+        // Only the token sent for the pending change commits it
+        let result = user_store
+            .change_email(&email, &EmailToken::default())
+            .await;
+        assert_eq!(result, Err(UserStoreError::InvalidCredentials));
+
+        let result = user_store.change_email(&email, &token).await;
+        assert_eq!(result, Ok(new_email.clone()));
+        assert_eq!(
+            user_store.get_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        assert_eq!(
+            user_store.validate_user(&new_email, &password).await,
+            Ok(())
+        );
+
+        let result = user_store.change_email(&new_email, &token).await;
+        assert_eq!(result, Err(UserStoreError::InvalidCredentials));
+    }
+
+    #[tokio::test]
+    async fn test_change_email_to_taken_address() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let new_email = Email::parse("new@example.com".to_owned()).unwrap();
+        let password = Password::parse("password".to_owned()).unwrap();
+        let token = EmailToken::default();
+
+        user_store
+            .add_user(User::new(email.clone(), password.clone(), false))
+            .await
+            .unwrap();
+        user_store
+            .set_pending_email(&email, new_email.clone(), &token)
+            .await
+            .unwrap();
+        // Someone signed up with the address in the meantime
+        user_store
+            .add_user(User::new(new_email.clone(), password, false))
+            .await
+            .unwrap();
+
+        let result = user_store.change_email(&email, &token).await;
+        assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
+        assert!(user_store.get_user(&email).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_mark_email_verified() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+
+        let result = user_store.mark_email_verified(&email).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+
+        user_store.users.insert(
+            email.clone(),
+            User::new(
+                email.clone(),
+                Password::parse("password".to_owned()).unwrap(),
+                false,
+            ),
+        );
+        assert!(!user_store.get_user(&email).await.unwrap().email_verified);
+
+        let result = user_store.mark_email_verified(&email).await;
+        assert_eq!(result, Ok(()));
+        assert!(user_store.get_user(&email).await.unwrap().email_verified);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_phone_number() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let phone_number = PhoneNumber::parse("+15555550123".to_owned()).unwrap();
+        let code = TwoFACode::default();
+
+        // Test setting a number for a user that doesn't exist
+        let result = user_store
+            .set_pending_phone_number(&email, phone_number.clone(), code.clone())
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+
+        user_store.users.insert(
+            email.clone(),
+            User::new(
+                email.clone(),
+                Password::parse("password".to_owned()).unwrap(),
+                true,
+            ),
+        );
+
+        // Test a wrong code discards the pending number
+        user_store
+            .set_pending_phone_number(&email, phone_number.clone(), code.clone())
+            .await
+            .unwrap();
+        let wrong_code = TwoFACode::parse("000000".to_owned()).unwrap();
+        let result = user_store.confirm_phone_number(&email, &wrong_code).await;
+        assert_eq!(result, Err(UserStoreError::InvalidCredentials));
+        let result = user_store.confirm_phone_number(&email, &code).await;
+        assert_eq!(result, Err(UserStoreError::InvalidCredentials));
+        assert_eq!(user_store.get_user(&email).await.unwrap().phone_number, None);
+
+        // Test the right code verifies the number
+        user_store
+            .set_pending_phone_number(&email, phone_number.clone(), code.clone())
+            .await
+            .unwrap();
+        let result = user_store.confirm_phone_number(&email, &code).await;
+        assert_eq!(result, Ok(phone_number.clone()));
+        assert_eq!(
+            user_store.get_user(&email).await.unwrap().phone_number,
+            Some(phone_number)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_use_recovery_code() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let old_codes = vec![RecoveryCode::default(), RecoveryCode::default()];
+        let new_codes = vec![RecoveryCode::default(), RecoveryCode::default()];
+
+        // Test setting codes for a user that doesn't exist
+        let result = user_store.set_recovery_codes(&email, &old_codes).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+
+        user_store.users.insert(
+            email.clone(),
+            User::new(
+                email.clone(),
+                Password::parse("password".to_owned()).unwrap(),
+                true,
+            ),
+        );
+        user_store.set_recovery_codes(&email, &old_codes).await.unwrap();
+
+        // Test each code only works once
+        let result = user_store.use_recovery_code(&email, &old_codes[0]).await;
+        assert_eq!(result, Ok(()));
+        let result = user_store.use_recovery_code(&email, &old_codes[0]).await;
+        assert_eq!(result, Err(UserStoreError::InvalidCredentials));
+
+        // Test replacing the codes invalidates the old ones
+        user_store.set_recovery_codes(&email, &new_codes).await.unwrap();
+        let result = user_store.use_recovery_code(&email, &old_codes[1]).await;
+        assert_eq!(result, Err(UserStoreError::InvalidCredentials));
+        let result = user_store.use_recovery_code(&email, &new_codes[1]).await;
+        assert_eq!(result, Ok(()));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::domain::{PhoneNumber, SmsClient};
+
+/// Sends messages through an SMS provider's REST API: a `POST` of the
+/// message as JSON to `{base_url}/messages`, authenticated with a bearer
+/// token.
+pub struct HttpSmsClient {
+    http_client: Client,
+    base_url: String,
+    sender: String,
+    auth_token: String,
+}
+
+impl HttpSmsClient {
+    pub fn new(base_url: String, sender: String, auth_token: String, timeout: Duration) -> Self {
+        let http_client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            http_client,
+            base_url,
+            sender,
+            auth_token,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for HttpSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<(), String> {
+        let url = format!("{}/messages", self.base_url.trim_end_matches('/'));
+        let request_body = SendSmsRequest {
+            from: &self.sender,
+            to: recipient.as_ref(),
+            body: content,
+        };
+
+        self.http_client
+            .post(url)
+            .bearer_auth(&self.auth_token)
+            .json(&request_body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct SendSmsRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    body: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{bearer_token, body_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn sms_client(base_url: String) -> HttpSmsClient {
+        HttpSmsClient::new(
+            base_url,
+            "auth-service".to_owned(),
+            "test-token".to_owned(),
+            Duration::from_millis(200),
+        )
+    }
+
+    fn phone_number() -> PhoneNumber {
+        PhoneNumber::parse("+15555550123".to_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn send_sms_posts_the_message_to_the_provider() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        Mock::given(method("POST"))
+            .and(path("/messages"))
+            .and(bearer_token("test-token"))
+            .and(header("Content-Type", "application/json"))
+            .and(body_json(serde_json::json!({
+                "from": "auth-service",
+                "to": "+15555550123",
+                "body": "Your code is 123456",
+            })))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = sms_client
+            .send_sms(&phone_number(), "Your code is 123456")
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_sms_fails_if_the_provider_returns_an_error() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = sms_client
+            .send_sms(&phone_number(), "Your code is 123456")
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn send_sms_times_out_if_the_provider_is_too_slow() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201).set_delay(Duration::from_secs(2)))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = sms_client
+            .send_sms(&phone_number(), "Your code is 123456")
+            .await;
+        assert!(result.is_err());
+    }
+}
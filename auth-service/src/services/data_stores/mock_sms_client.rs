@@ -0,0 +1,17 @@
+use crate::domain::{PhoneNumber, SmsClient};
+
+pub struct MockSmsClient;
+
+#[async_trait::async_trait]
+impl SmsClient for MockSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<(), String> {
+        // Like the mock email client, just log the message to standard output
+        println!(
+            "Sending SMS to {} with content: {}",
+            recipient.as_ref(),
+            content
+        );
+
+        Ok(())
+    }
+}
@@ -1,7 +1,29 @@
 pub mod hashset_banned_token_store;
+pub mod hashmap_authorization_code_store;
+pub mod hashmap_client_store;
+pub mod hashmap_email_token_store;
+pub mod hashmap_passkey_challenge_store;
+pub mod hashmap_passkey_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_session_epoch_store;
+pub mod hashmap_session_store;
 pub mod hashmap_user_store;
+pub mod hashmap_totp_store;
+pub mod hashmap_trusted_device_store;
 pub mod hashmap_two_fa_code_store;
+pub mod http_sms_client;
 pub mod mock_email_client;
+pub mod mock_sms_client;
+pub mod postgres_client_store;
+pub mod postgres_passkey_store;
+pub mod postgres_session_store;
+pub mod postgres_totp_store;
+pub mod postgres_trusted_device_store;
 pub mod postgres_user_store;
+pub mod redis_authorization_code_store;
 pub mod redis_banned_token_store;
+pub mod redis_email_token_store;
+pub mod redis_passkey_challenge_store;
+pub mod redis_refresh_token_store;
+pub mod redis_session_epoch_store;
 pub mod redis_two_fa_code_store;
\ No newline at end of file
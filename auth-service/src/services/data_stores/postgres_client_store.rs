@@ -0,0 +1,104 @@
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::domain::data_stores::{Client, ClientSecret, ClientStore, ClientStoreError};
+
+pub struct PostgresClientStore {
+    pool: PgPool,
+}
+
+impl PostgresClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn fetch_client(&self, client_id: &str) -> Result<ClientRow, ClientStoreError> {
+        sqlx::query_as!(
+            ClientRow,
+            "select id, name, secret_hash, redirect_uris, scopes, first_party from clients where id = $1",
+            client_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| ClientStoreError::UnexpectedError)?
+        .ok_or(ClientStoreError::ClientNotFound)
+    }
+}
+
+#[derive(Debug)]
+struct ClientRow {
+    id: String,
+    name: String,
+    secret_hash: Option<String>,
+    redirect_uris: Vec<String>,
+    scopes: Vec<String>,
+    first_party: bool,
+}
+
+impl From<ClientRow> for Client {
+    fn from(row: ClientRow) -> Self {
+        Client {
+            id: row.id,
+            name: row.name,
+            redirect_uris: row.redirect_uris,
+            scopes: row.scopes,
+            first_party: row.first_party,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientStore for PostgresClientStore {
+    async fn add_client(
+        &mut self,
+        client: Client,
+        secret: Option<ClientSecret>,
+    ) -> Result<(), ClientStoreError> {
+        let result = sqlx::query!(
+            r#"
+            insert into clients (id, name, secret_hash, redirect_uris, scopes, first_party)
+            values ($1, $2, $3, $4, $5, $6)
+            on conflict (id) do nothing
+            "#,
+            client.id,
+            client.name,
+            secret.as_ref().map(compute_secret_hash),
+            &client.redirect_uris,
+            &client.scopes,
+            client.first_party
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| ClientStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ClientStoreError::ClientAlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<Client, ClientStoreError> {
+        self.fetch_client(client_id).await.map(Client::from)
+    }
+
+    async fn validate_client(
+        &self,
+        client_id: &str,
+        secret: Option<&ClientSecret>,
+    ) -> Result<Client, ClientStoreError> {
+        let row = self.fetch_client(client_id).await?;
+
+        if row.secret_hash != secret.map(compute_secret_hash) {
+            return Err(ClientStoreError::InvalidCredentials);
+        }
+
+        Ok(row.into())
+    }
+}
+
+// Client secrets are long and random, so unlike passwords a fast unsalted
+// hash is enough and keeps per-request client authentication cheap.
+fn compute_secret_hash(secret: &ClientSecret) -> String {
+    hex::encode(Sha256::digest(secret.as_ref().as_bytes()))
+}
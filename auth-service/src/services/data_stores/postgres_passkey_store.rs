@@ -0,0 +1,147 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{Passkey, PasskeyStore, PasskeyStoreError},
+    Email,
+};
+
+pub struct PostgresPasskeyStore {
+    pool: PgPool,
+}
+
+impl PostgresPasskeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[derive(Debug)]
+struct PasskeyRow {
+    credential_id: String,
+    email: String,
+    public_key: Vec<u8>,
+    sign_count: i64,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<PasskeyRow> for Passkey {
+    type Error = PasskeyStoreError;
+
+    fn try_from(row: PasskeyRow) -> Result<Self, Self::Error> {
+        Ok(Passkey {
+            credential_id: row.credential_id,
+            email: Email::parse(row.email).map_err(|_| PasskeyStoreError::UnexpectedError)?,
+            public_key: row.public_key,
+            sign_count: row
+                .sign_count
+                .try_into()
+                .map_err(|_| PasskeyStoreError::UnexpectedError)?,
+            created_at: row.created_at,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for PostgresPasskeyStore {
+    async fn add_passkey(&mut self, passkey: Passkey) -> Result<(), PasskeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            insert into passkeys (credential_id, email, public_key, sign_count, created_at)
+            values ($1, $2, $3, $4, $5)
+            on conflict (credential_id) do nothing
+            "#,
+            passkey.credential_id,
+            passkey.email.as_ref(),
+            passkey.public_key,
+            i64::from(passkey.sign_count),
+            passkey.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| PasskeyStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(PasskeyStoreError::PasskeyAlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    async fn get_passkey(&self, credential_id: &str) -> Result<Passkey, PasskeyStoreError> {
+        let row = sqlx::query_as!(
+            PasskeyRow,
+            r#"
+            select credential_id, email, public_key, sign_count, created_at
+            from passkeys
+            where credential_id = $1
+            "#,
+            credential_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| PasskeyStoreError::UnexpectedError)?;
+
+        match row {
+            Some(row) => row.try_into(),
+            None => Err(PasskeyStoreError::PasskeyNotFound),
+        }
+    }
+
+    async fn list_passkeys(&self, email: &Email) -> Result<Vec<Passkey>, PasskeyStoreError> {
+        let rows = sqlx::query_as!(
+            PasskeyRow,
+            r#"
+            select credential_id, email, public_key, sign_count, created_at
+            from passkeys
+            where email = $1
+            order by created_at
+            "#,
+            email.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|_| PasskeyStoreError::UnexpectedError)?;
+
+        rows.into_iter().map(Passkey::try_from).collect()
+    }
+
+    async fn update_sign_count(
+        &mut self,
+        credential_id: &str,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError> {
+        let result = sqlx::query!(
+            "update passkeys set sign_count = $2 where credential_id = $1",
+            credential_id,
+            i64::from(sign_count)
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| PasskeyStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(PasskeyStoreError::PasskeyNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn change_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), PasskeyStoreError> {
+        // Usually a no-op, as the users table cascades the change
+        sqlx::query!(
+            "update passkeys set email = $2 where email = $1",
+            email.as_ref(),
+            new_email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| PasskeyStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
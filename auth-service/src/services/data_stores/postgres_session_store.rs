@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{Session, SessionStore, SessionStoreError},
+    Email,
+};
+
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[derive(Debug)]
+struct SessionRow {
+    id: String,
+    email: String,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl TryFrom<SessionRow> for Session {
+    type Error = SessionStoreError;
+
+    fn try_from(row: SessionRow) -> Result<Self, Self::Error> {
+        Ok(Session {
+            id: row.id,
+            email: Email::parse(row.email).map_err(|_| SessionStoreError::UnexpectedError)?,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            ip_address: row.ip_address,
+            user_agent: row.user_agent,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            r#"
+            insert into sessions (id, email, created_at, expires_at, ip_address, user_agent)
+            values ($1, $2, $3, $4, $5, $6)
+            "#,
+            session.id,
+            session.email.as_ref(),
+            session.created_at,
+            session.expires_at,
+            session.ip_address,
+            session.user_agent
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        let row = sqlx::query_as!(
+            SessionRow,
+            r#"
+            select id, email, created_at, expires_at, ip_address, user_agent
+            from sessions
+            where id = $1 and expires_at > now()
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        match row {
+            Some(row) => row.try_into(),
+            None => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn list_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let rows = sqlx::query_as!(
+            SessionRow,
+            r#"
+            select id, email, created_at, expires_at, ip_address, user_agent
+            from sessions
+            where email = $1 and expires_at > now()
+            order by created_at desc
+            "#,
+            email.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        rows.into_iter().map(Session::try_from).collect()
+    }
+
+    async fn extend_session(
+        &mut self,
+        id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            "update sessions set expires_at = $2 where id = $1",
+            id,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn revoke_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            "delete from sessions where id = $1 and email = $2",
+            id,
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn revoke_all_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        sqlx::query!("delete from sessions where email = $1", email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{TotpSecret, TotpStore, TotpStoreError},
+    Email,
+};
+
+pub struct PostgresTotpStore {
+    pool: PgPool,
+}
+
+impl PostgresTotpStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpStore for PostgresTotpStore {
+    async fn set_pending_secret(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), TotpStoreError> {
+        sqlx::query!(
+            r#"
+            insert into totp_secrets (email, pending_secret)
+            values ($1, $2)
+            on conflict (email) do update set pending_secret = excluded.pending_secret
+            "#,
+            email.as_ref(),
+            secret.encode()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TotpStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn get_pending_secret(&self, email: &Email) -> Result<TotpSecret, TotpStoreError> {
+        let secret = sqlx::query_scalar!(
+            "select pending_secret from totp_secrets where email = $1",
+            email.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| TotpStoreError::UnexpectedError)?
+        .flatten()
+        .ok_or(TotpStoreError::SecretNotFound)?;
+
+        TotpSecret::parse(secret).map_err(|_| TotpStoreError::UnexpectedError)
+    }
+
+    async fn activate_secret(
+        &mut self,
+        email: &Email,
+        time_step: u64,
+    ) -> Result<(), TotpStoreError> {
+        let result = sqlx::query!(
+            r#"
+            update totp_secrets
+            set secret = pending_secret, pending_secret = null, last_used_step = $2
+            where email = $1 and pending_secret is not null
+            "#,
+            email.as_ref(),
+            time_step as i64
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TotpStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpStoreError::SecretNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn get_secret(&self, email: &Email) -> Result<TotpSecret, TotpStoreError> {
+        let secret = sqlx::query_scalar!(
+            "select secret from totp_secrets where email = $1",
+            email.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| TotpStoreError::UnexpectedError)?
+        .flatten()
+        .ok_or(TotpStoreError::SecretNotFound)?;
+
+        TotpSecret::parse(secret).map_err(|_| TotpStoreError::UnexpectedError)
+    }
+
+    async fn use_time_step(
+        &mut self,
+        email: &Email,
+        time_step: u64,
+    ) -> Result<(), TotpStoreError> {
+        // Check and update in one statement so two concurrent requests can't
+        // both redeem the same code
+        let result = sqlx::query!(
+            r#"
+            update totp_secrets
+            set last_used_step = $2
+            where email = $1
+              and secret is not null
+              and (last_used_step is null or last_used_step < $2)
+            "#,
+            email.as_ref(),
+            time_step as i64
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TotpStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(match self.get_secret(email).await {
+                Ok(_) => TotpStoreError::CodeAlreadyUsed,
+                Err(e) => e,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpStoreError> {
+        sqlx::query!("delete from totp_secrets where email = $1", email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| TotpStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn change_email(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), TotpStoreError> {
+        // Usually a no-op, as the users table cascades the change
+        sqlx::query!(
+            "update totp_secrets set email = $2 where email = $1",
+            email.as_ref(),
+            new_email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TotpStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
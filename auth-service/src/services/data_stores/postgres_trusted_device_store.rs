@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{TrustedDevice, TrustedDeviceStore, TrustedDeviceStoreError},
+    Email,
+};
+
+pub struct PostgresTrustedDeviceStore {
+    pool: PgPool,
+}
+
+impl PostgresTrustedDeviceStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[derive(Debug)]
+struct TrustedDeviceRow {
+    id: String,
+    email: String,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl TryFrom<TrustedDeviceRow> for TrustedDevice {
+    type Error = TrustedDeviceStoreError;
+
+    fn try_from(row: TrustedDeviceRow) -> Result<Self, Self::Error> {
+        Ok(TrustedDevice {
+            id: row.id,
+            email: Email::parse(row.email).map_err(|_| TrustedDeviceStoreError::UnexpectedError)?,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            ip_address: row.ip_address,
+            user_agent: row.user_agent,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustedDeviceStore for PostgresTrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError> {
+        sqlx::query!(
+            r#"
+            insert into trusted_devices (id, email, created_at, expires_at, ip_address, user_agent)
+            values ($1, $2, $3, $4, $5, $6)
+            "#,
+            device.id,
+            device.email.as_ref(),
+            device.created_at,
+            device.expires_at,
+            device.ip_address,
+            device.user_agent
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn get_device(&self, id: &str) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+        let row = sqlx::query_as!(
+            TrustedDeviceRow,
+            r#"
+            select id, email, created_at, expires_at, ip_address, user_agent
+            from trusted_devices
+            where id = $1 and expires_at > now()
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        match row {
+            Some(row) => row.try_into(),
+            None => Err(TrustedDeviceStoreError::DeviceNotFound),
+        }
+    }
+
+    async fn list_devices(
+        &self,
+        email: &Email,
+    ) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError> {
+        let rows = sqlx::query_as!(
+            TrustedDeviceRow,
+            r#"
+            select id, email, created_at, expires_at, ip_address, user_agent
+            from trusted_devices
+            where email = $1 and expires_at > now()
+            order by created_at desc
+            "#,
+            email.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        rows.into_iter().map(TrustedDevice::try_from).collect()
+    }
+
+    async fn revoke_device(
+        &mut self,
+        email: &Email,
+        id: &str,
+    ) -> Result<(), TrustedDeviceStoreError> {
+        let result = sqlx::query!(
+            "delete from trusted_devices where id = $1 and email = $2",
+            id,
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(TrustedDeviceStoreError::DeviceNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn revoke_all_devices(&mut self, email: &Email) -> Result<(), TrustedDeviceStoreError> {
+        sqlx::query!(
+            "delete from trusted_devices where email = $1",
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
@@ -5,11 +5,12 @@ use argon2::{
     PasswordVerifier, Version,
 };
 
+use sha2::{Digest, Sha256};
 use sqlx::PgPool;
 
 use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+    data_stores::{RecoveryCode, TwoFACode, UserStore, UserStoreError},
+    CodeChannel, Email, EmailToken, Password, PhoneNumber, User,
 };
 
 pub struct PostgresUserStore {
@@ -27,6 +28,9 @@ struct UserRow {
     email: String,
     password_hash: String,
     requires_2fa: bool,
+    phone_number: Option<String>,
+    code_channel: String,
+    email_verified: bool,
 }
 
 #[async_trait::async_trait]
@@ -49,10 +53,14 @@ impl UserStore for PostgresUserStore {
                 .map_err(|_| UserStoreError::UnexpectedError)?;
 
             sqlx::query!(
-                "insert into users (email, password_hash, requires_2fa) values ($1, $2, $3)",
+                r#"
+                insert into users (email, password_hash, requires_2fa, email_verified)
+                values ($1, $2, $3, $4)
+                "#,
                 user.email.as_ref(),
                 password_hash.to_string(),
-                user.requires_2fa
+                user.requires_2fa,
+                user.email_verified
             )
             .execute(&self.pool)
             .await
@@ -65,7 +73,11 @@ impl UserStore for PostgresUserStore {
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         let user = sqlx::query_as!(
             UserRow,
-            "select email, password_hash, requires_2fa from users where email = $1",
+            r#"
+            select email, password_hash, requires_2fa, phone_number, code_channel, email_verified
+            from users
+            where email = $1
+            "#,
             email.as_ref()
         )
         .fetch_optional(&self.pool)
@@ -77,6 +89,14 @@ impl UserStore for PostgresUserStore {
                 email: Email::parse(user.email).unwrap(),
                 password: Password::parse(user.password_hash).unwrap(),
                 requires_2fa: user.requires_2fa,
+                phone_number: user
+                    .phone_number
+                    .map(PhoneNumber::parse)
+                    .transpose()
+                    .map_err(|_| UserStoreError::UnexpectedError)?,
+                code_channel: CodeChannel::parse(&user.code_channel)
+                    .map_err(|_| UserStoreError::UnexpectedError)?,
+                email_verified: user.email_verified,
             }),
             None => Err(UserStoreError::UserNotFound),
         }
@@ -90,7 +110,11 @@ impl UserStore for PostgresUserStore {
 
         let user = sqlx::query_as!(
             UserRow,
-            "select email, password_hash, requires_2fa from users where email = $1",
+            r#"
+            select email, password_hash, requires_2fa, phone_number, code_channel, email_verified
+            from users
+            where email = $1
+            "#,
             email.as_ref()
         )
         .fetch_optional(&self.pool)
@@ -108,6 +132,315 @@ impl UserStore for PostgresUserStore {
             None => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(password.as_ref())
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            "update users set password_hash = $2 where email = $1",
+            email.as_ref(),
+            password_hash
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "update users set requires_2fa = $2 where email = $1",
+            email.as_ref(),
+            requires_2fa
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn set_code_channel(
+        &mut self,
+        email: &Email,
+        channel: CodeChannel,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "update users set code_channel = $2 where email = $1",
+            email.as_ref(),
+            channel.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "update users set email_verified = true where email = $1",
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn set_pending_email(
+        &mut self,
+        email: &Email,
+        new_email: Email,
+        token: &EmailToken,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            update users
+            set pending_email = $2, pending_email_token = $3
+            where email = $1
+            "#,
+            email.as_ref(),
+            new_email.as_ref(),
+            token.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn change_email(
+        &mut self,
+        email: &Email,
+        token: &EmailToken,
+    ) -> Result<Email, UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let pending = sqlx::query!(
+            r#"
+            select pending_email, pending_email_token
+            from users
+            where email = $1
+            for update
+            "#,
+            email.as_ref()
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        let new_email = match (pending.pending_email, pending.pending_email_token) {
+            (Some(new_email), Some(expected_token)) if expected_token == token.as_ref() => {
+                new_email
+            }
+            _ => return Err(UserStoreError::InvalidCredentials),
+        };
+
+        // Sessions, TOTP secrets, passkeys and trusted devices follow
+        // through their foreign keys
+        sqlx::query!(
+            r#"
+            update users
+            set email = pending_email, pending_email = null, pending_email_token = null
+            where email = $1
+            "#,
+            email.as_ref()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            _ => UserStoreError::UnexpectedError,
+        })?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        Email::parse(new_email).map_err(|_| UserStoreError::UnexpectedError)
+    }
+
+    async fn set_pending_phone_number(
+        &mut self,
+        email: &Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            update users
+            set pending_phone_number = $2, pending_phone_code = $3
+            where email = $1
+            "#,
+            email.as_ref(),
+            phone_number.as_ref(),
+            code.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn confirm_phone_number(
+        &mut self,
+        email: &Email,
+        code: &TwoFACode,
+    ) -> Result<PhoneNumber, UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let pending = sqlx::query!(
+            r#"
+            select pending_phone_number, pending_phone_code
+            from users
+            where email = $1
+            for update
+            "#,
+            email.as_ref()
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        let verified_number = pending.and_then(|pending| {
+            (pending.pending_phone_code.as_deref() == Some(code.as_ref()))
+                .then_some(pending.pending_phone_number)
+                .flatten()
+        });
+
+        // Whatever the code, the pending number is used up
+        sqlx::query!(
+            r#"
+            update users
+            set phone_number = coalesce($2, phone_number),
+                pending_phone_number = null,
+                pending_phone_code = null
+            where email = $1
+            "#,
+            email.as_ref(),
+            verified_number
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        match verified_number {
+            Some(number) => PhoneNumber::parse(number).map_err(|_| UserStoreError::UnexpectedError),
+            None => Err(UserStoreError::InvalidCredentials),
+        }
+    }
+
+    async fn set_recovery_codes(
+        &mut self,
+        email: &Email,
+        codes: &[RecoveryCode],
+    ) -> Result<(), UserStoreError> {
+        let hashes: Vec<String> = codes.iter().map(compute_recovery_code_hash).collect();
+
+        let result = sqlx::query!(
+            "update users set recovery_code_hashes = $2 where email = $1",
+            email.as_ref(),
+            &hashes
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn use_recovery_code(
+        &mut self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<(), UserStoreError> {
+        // Find and remove in one statement so a code can't be used twice
+        // by concurrent requests
+        let result = sqlx::query!(
+            r#"
+            update users
+            set recovery_code_hashes = array_remove(recovery_code_hashes, $2)
+            where email = $1 and $2 = any(recovery_code_hashes)
+            "#,
+            email.as_ref(),
+            compute_recovery_code_hash(code)
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+
+        Ok(())
+    }
+}
+
+// Recovery codes carry 80 random bits, so a fast hash resists guessing.
+// Leaving it unsalted is what lets `use_recovery_code` match in SQL.
+fn compute_recovery_code_hash(code: &RecoveryCode) -> String {
+    hex::encode(Sha256::digest(code.as_ref().as_bytes()))
 }
 
 async fn compute_password_hash(password: &str) -> Result<String, Box<dyn Error>> {
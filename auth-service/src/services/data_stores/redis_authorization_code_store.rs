@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{
+        AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError,
+        AuthorizationGrant,
+    },
+    Email,
+};
+
+pub struct RedisAuthorizationCodeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisAuthorizationCodeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for RedisAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let stored_grant = StoredGrant {
+            client_id: grant.client_id,
+            redirect_uri: grant.redirect_uri,
+            code_challenge: grant.code_challenge,
+            scope: grant.scope,
+            nonce: grant.nonce,
+            email: grant.email.as_ref().to_owned(),
+            ip_address: grant.ip_address,
+            user_agent: grant.user_agent,
+        };
+        let serialized_grant = serde_json::to_string(&stored_grant)
+            .map_err(|_| AuthorizationCodeStoreError::UnexpectedError)?;
+
+        self.conn
+            .write()
+            .await
+            .set_ex::<_, _, ()>(get_key(&code), serialized_grant, AUTHORIZATION_CODE_TTL_SECONDS)
+            .map_err(|_| AuthorizationCodeStoreError::UnexpectedError)
+    }
+
+    async fn take_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        let key = get_key(code);
+
+        // Read and delete in one transaction so two requests racing with the
+        // same code can't both redeem it
+        let (value,): (Option<String>,) = redis::pipe()
+            .atomic()
+            .get(&key)
+            .del(&key)
+            .ignore()
+            .query(&mut *self.conn.write().await)
+            .map_err(|_| AuthorizationCodeStoreError::UnexpectedError)?;
+
+        let value = value.ok_or(AuthorizationCodeStoreError::CodeNotFound)?;
+        let grant: StoredGrant = serde_json::from_str(&value)
+            .map_err(|_| AuthorizationCodeStoreError::UnexpectedError)?;
+
+        Ok(AuthorizationGrant {
+            client_id: grant.client_id,
+            redirect_uri: grant.redirect_uri,
+            code_challenge: grant.code_challenge,
+            scope: grant.scope,
+            nonce: grant.nonce,
+            email: Email::parse(grant.email)
+                .map_err(|_| AuthorizationCodeStoreError::UnexpectedError)?,
+            ip_address: grant.ip_address,
+            user_agent: grant.user_agent,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredGrant {
+    client_id: String,
+    redirect_uri: String,
+    code_challenge: String,
+    scope: Option<String>,
+    #[serde(default)]
+    nonce: Option<String>,
+    email: String,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+}
+
+/// Codes are redeemed by the client right after the redirect, so they only
+/// need to live long enough for that round trip.
+const AUTHORIZATION_CODE_TTL_SECONDS: u64 = 60;
+const AUTHORIZATION_CODE_PREFIX: &str = "authorization_code:";
+
+fn get_key(code: &AuthorizationCode) -> String {
+    format!("{}{}", AUTHORIZATION_CODE_PREFIX, code.as_ref())
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{EmailToken, EmailTokenPurpose, EmailTokenStore, EmailTokenStoreError},
+    Email,
+};
+
+pub struct RedisEmailTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisEmailTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTokenStore for RedisEmailTokenStore {
+    async fn add_token(
+        &mut self,
+        token: EmailToken,
+        email: Email,
+        purpose: EmailTokenPurpose,
+        ttl_seconds: u64,
+    ) -> Result<(), EmailTokenStoreError> {
+        self.conn
+            .write()
+            .await
+            .set_ex::<_, _, ()>(get_key(&token, purpose), email.as_ref(), ttl_seconds)
+            .map_err(|_| EmailTokenStoreError::UnexpectedError)
+    }
+
+    async fn take_token(
+        &mut self,
+        token: &EmailToken,
+        purpose: EmailTokenPurpose,
+    ) -> Result<Email, EmailTokenStoreError> {
+        let key = get_key(token, purpose);
+
+        // Read and delete in one transaction so a link clicked twice at once
+        // is only honoured once
+        let (value,): (Option<String>,) = redis::pipe()
+            .atomic()
+            .get(&key)
+            .del(&key)
+            .ignore()
+            .query(&mut *self.conn.write().await)
+            .map_err(|_| EmailTokenStoreError::UnexpectedError)?;
+
+        let email = value.ok_or(EmailTokenStoreError::TokenNotFound)?;
+        Email::parse(email).map_err(|_| EmailTokenStoreError::UnexpectedError)
+    }
+}
+
+const EMAIL_TOKEN_PREFIX: &str = "email_token:";
+
+fn get_key(token: &EmailToken, purpose: EmailTokenPurpose) -> String {
+    format!(
+        "{}{}:{}",
+        EMAIL_TOKEN_PREFIX,
+        purpose.as_ref(),
+        token.as_ref()
+    )
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{
+        PasskeyCeremony, PasskeyChallenge, PasskeyChallengeStore, PasskeyChallengeStoreError,
+    },
+    Email,
+};
+
+pub struct RedisPasskeyChallengeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPasskeyChallengeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyChallengeStore for RedisPasskeyChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: PasskeyChallenge,
+        ceremony: PasskeyCeremony,
+    ) -> Result<(), PasskeyChallengeStoreError> {
+        let stored_ceremony = match ceremony {
+            PasskeyCeremony::Registration(email) => StoredCeremony {
+                registering_email: Some(email.as_ref().to_owned()),
+            },
+            PasskeyCeremony::Authentication => StoredCeremony {
+                registering_email: None,
+            },
+        };
+        let serialized_ceremony = serde_json::to_string(&stored_ceremony)
+            .map_err(|_| PasskeyChallengeStoreError::UnexpectedError)?;
+
+        self.conn
+            .write()
+            .await
+            .set_ex::<_, _, ()>(
+                get_key(&challenge),
+                serialized_ceremony,
+                PASSKEY_CHALLENGE_TTL_SECONDS,
+            )
+            .map_err(|_| PasskeyChallengeStoreError::UnexpectedError)
+    }
+
+    async fn take_challenge(
+        &mut self,
+        challenge: &PasskeyChallenge,
+    ) -> Result<PasskeyCeremony, PasskeyChallengeStoreError> {
+        let key = get_key(challenge);
+
+        // Read and delete in one transaction so a challenge can't be
+        // answered twice
+        let (value,): (Option<String>,) = redis::pipe()
+            .atomic()
+            .get(&key)
+            .del(&key)
+            .ignore()
+            .query(&mut *self.conn.write().await)
+            .map_err(|_| PasskeyChallengeStoreError::UnexpectedError)?;
+
+        let value = value.ok_or(PasskeyChallengeStoreError::ChallengeNotFound)?;
+        let ceremony: StoredCeremony = serde_json::from_str(&value)
+            .map_err(|_| PasskeyChallengeStoreError::UnexpectedError)?;
+
+        match ceremony.registering_email {
+            Some(email) => Ok(PasskeyCeremony::Registration(
+                Email::parse(email).map_err(|_| PasskeyChallengeStoreError::UnexpectedError)?,
+            )),
+            None => Ok(PasskeyCeremony::Authentication),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredCeremony {
+    registering_email: Option<String>,
+}
+
+/// Matches the timeout we give the browser for the ceremony.
+const PASSKEY_CHALLENGE_TTL_SECONDS: u64 = 300;
+const PASSKEY_CHALLENGE_PREFIX: &str = "passkey_challenge:";
+
+fn get_key(challenge: &PasskeyChallenge) -> String {
+    format!("{}{}", PASSKEY_CHALLENGE_PREFIX, challenge.as_ref())
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{
+            RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError,
+        },
+        Email,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    async fn set_record(
+        &self,
+        token: &RefreshToken,
+        record: StoredRefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let serialized_record =
+            serde_json::to_string(&record).map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        let ttl = REFRESH_TOKEN_TTL_SECONDS as u64;
+
+        self.conn
+            .write()
+            .await
+            .set_ex::<_, _, ()>(get_token_key(token), serialized_record, ttl)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)
+    }
+
+    async fn get_record(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<StoredRefreshToken, RefreshTokenStoreError> {
+        let value = self
+            .conn
+            .write()
+            .await
+            .get::<_, Option<String>>(get_token_key(token))
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        serde_json::from_str(&value).map_err(|_| RefreshTokenStoreError::UnexpectedError)
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: String,
+        epoch: u64,
+        audience: String,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let record = StoredRefreshToken {
+            email: email.as_ref().to_owned(),
+            family_id,
+            epoch,
+            audience,
+            used: false,
+        };
+        self.set_record(&token, record).await
+    }
+
+    async fn get_token(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let record = self.get_record(token).await?;
+
+        let revoked = self
+            .conn
+            .write()
+            .await
+            .exists::<_, bool>(get_family_key(&record.family_id))
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        if revoked {
+            return Err(RefreshTokenStoreError::TokenNotFound);
+        }
+
+        Ok(RefreshTokenRecord {
+            email: Email::parse(record.email)
+                .map_err(|_| RefreshTokenStoreError::UnexpectedError)?,
+            family_id: record.family_id,
+            epoch: record.epoch,
+            audience: record.audience,
+            used: record.used,
+        })
+    }
+
+    async fn mark_token_used(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let mut record = self.get_record(token).await?;
+        record.used = true;
+        self.set_record(token, record).await
+    }
+
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        // A family can't outlive its newest token, so the marker only has to
+        // live as long as a single refresh token does.
+        let ttl = REFRESH_TOKEN_TTL_SECONDS as u64;
+        self.conn
+            .write()
+            .await
+            .set_ex::<_, _, ()>(get_family_key(family_id), true, ttl)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredRefreshToken {
+    email: String,
+    family_id: String,
+    #[serde(default)]
+    epoch: u64,
+    audience: String,
+    used: bool,
+}
+
+const REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token:";
+const REVOKED_FAMILY_KEY_PREFIX: &str = "revoked_refresh_token_family:";
+
+fn get_token_key(token: &RefreshToken) -> String {
+    format!("{}{}", REFRESH_TOKEN_KEY_PREFIX, token.as_ref())
+}
+
+fn get_family_key(family_id: &str) -> String {
+    format!("{}{}", REVOKED_FAMILY_KEY_PREFIX, family_id)
+}
@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{SessionEpochStore, SessionEpochStoreError},
+    Email,
+};
+
+pub struct RedisSessionEpochStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisSessionEpochStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionEpochStore for RedisSessionEpochStore {
+    async fn get_epoch(&self, email: &Email) -> Result<u64, SessionEpochStoreError> {
+        self.conn
+            .write()
+            .await
+            .get::<_, Option<u64>>(get_key(email))
+            .map(Option::unwrap_or_default)
+            .map_err(|_| SessionEpochStoreError::UnexpectedError)
+    }
+
+    async fn increment_epoch(&mut self, email: &Email) -> Result<u64, SessionEpochStoreError> {
+        // No TTL: losing the key would reset the epoch and revive every
+        // token that was revoked by bumping it.
+        self.conn
+            .write()
+            .await
+            .incr::<_, _, u64>(get_key(email), 1)
+            .map_err(|_| SessionEpochStoreError::UnexpectedError)
+    }
+}
+
+const SESSION_EPOCH_KEY_PREFIX: &str = "session_epoch:";
+
+fn get_key(email: &Email) -> String {
+    format!("{}{}", SESSION_EPOCH_KEY_PREFIX, email.as_ref())
+}
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use chrono::Utc;
 use redis::{Commands, Connection};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
@@ -29,27 +30,37 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     ) -> Result<(), TwoFACodeStoreError> {
         // TODO:
         // 1. Create a new key using the get_key helper function.
-        let key = get_key(&email);
-        // 2. Create a TwoFATuple instance.
-        let two_fa_tuple = TwoFATuple(login_attempt_id.as_ref().to_string(), code.as_ref().to_string());
-        // 3. Use serde_json::to_string to serialize the TwoFATuple instance into a JSON string. 
-        let serialized_two_fa_tuple = serde_json::to_string(&two_fa_tuple).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        let key = get_key(&login_attempt_id);
+        // 2. Create a TwoFAEntry instance.
+        let two_fa_entry = TwoFAEntry {
+            email: email.as_ref().to_string(),
+            code: Some(code.as_ref().to_string()),
+            failed_attempts: 0,
+            resends: 0,
+            last_sent_at: Utc::now().timestamp(),
+        };
+        // 3. Use serde_json::to_string to serialize the TwoFAEntry instance into a JSON string. 
+        let serialized_two_fa_entry = serde_json::to_string(&two_fa_entry).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
         // Return TwoFACodeStoreError::UnexpectedError if serialization fails.
         // 4. Call the set_ex command on the Redis connection to set a new key/value pair with an expiration time (TTL). 
-        // The value should be the serialized 2FA tuple.
+        // The value should be the serialized 2FA entry.
         // The expiration time should be set to TEN_MINUTES_IN_SECONDS.
         // Return TwoFACodeStoreError::UnexpectedError if casting fails or the call to set_ex fails.
+        #[allow(clippy::unnecessary_cast)]
         let ttl = TEN_MINUTES_IN_SECONDS as u64;
-        match self.conn.write().await.set_ex::<_, _, ()>(key, serialized_two_fa_tuple, ttl) {
+        match self.conn.write().await.set_ex::<_, _, ()>(key, serialized_two_fa_entry, ttl) {
             Ok(_) => Ok(()),
             Err(_) => Err(TwoFACodeStoreError::UnexpectedError),
         }
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
         // TODO:
         // 1. Create a new key using the get_key helper function.
-        let key = get_key(&email);
+        let key = get_key(login_attempt_id);
         // 2. Call the del command on the Redis connection to delete the 2FA code entry. 
         // Return TwoFACodeStoreError::UnexpectedError if the operation fails.
         match self.conn.write().await.del::<String, ()>(key) {
@@ -60,35 +71,119 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode), TwoFACodeStoreError> {
         // TODO:
         // 1. Create a new key using the get_key helper function.
-        let key = get_key(&email);
+        let key = get_key(login_attempt_id);
         // 2. Call the get command on the Redis connection to get the value stored for the key. 
         // Return TwoFACodeStoreError::LoginAttemptIdNotFound if the operation fails.
         match self.conn.write().await.get::<String, String>(key) {
             Ok(value) => {
-                let two_fa_tuple: TwoFATuple = serde_json::from_str(&value).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
-                let login_attempt_id = LoginAttemptId::parse(two_fa_tuple.0).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
-                let two_fa_code = TwoFACode::parse(two_fa_tuple.1).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
-                Ok((login_attempt_id, two_fa_code))
+                let two_fa_entry: TwoFAEntry = serde_json::from_str(&value).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+                let email = Email::parse(two_fa_entry.email).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+                let code = two_fa_entry.code.ok_or(TwoFACodeStoreError::TooManyAttempts)?;
+                let two_fa_code = TwoFACode::parse(code).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+                Ok((email, two_fa_code))
             }
             Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
-        // If the operation succeeds, call serde_json::from_str to parse the JSON string into a TwoFATuple. 
-        // Then, parse the login attempt ID string and 2FA code string into a LoginAttemptId and TwoFACode type respectively.
+        // If the operation succeeds, call serde_json::from_str to parse the JSON string into a TwoFAEntry. 
+        // Then, parse the email string and 2FA code string into an Email and TwoFACode type respectively.
         // Return TwoFACodeStoreError::UnexpectedError if parsing fails.
     }
+
+    async fn record_failed_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let key = get_key(login_attempt_id);
+        let mut conn = self.conn.write().await;
+
+        let mut two_fa_entry = get_entry(&mut conn, &key)?;
+
+        two_fa_entry.failed_attempts += 1;
+        let too_many_attempts = two_fa_entry.failed_attempts >= max_attempts;
+        if too_many_attempts {
+            two_fa_entry.code = None;
+        }
+
+        // Keep the code's original expiry rather than restarting the clock,
+        // and don't bring back a code that expired in the meantime
+        let serialized_two_fa_entry = serde_json::to_string(&two_fa_entry)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        redis::cmd("SET")
+            .arg(&key)
+            .arg(serialized_two_fa_entry)
+            .arg("XX")
+            .arg("KEEPTTL")
+            .query::<()>(&mut *conn)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        if too_many_attempts {
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+
+        Ok(())
+    }
+
+    async fn resend_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        code: TwoFACode,
+        cooldown_seconds: u64,
+        max_resends: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let key = get_key(login_attempt_id);
+        let mut conn = self.conn.write().await;
+
+        let mut two_fa_entry = get_entry(&mut conn, &key)?;
+
+        if two_fa_entry.code.is_none() {
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+        if two_fa_entry.resends >= max_resends {
+            return Err(TwoFACodeStoreError::TooManyResends);
+        }
+        let now = Utc::now().timestamp();
+        if now < two_fa_entry.last_sent_at + cooldown_seconds as i64 {
+            return Err(TwoFACodeStoreError::ResendTooSoon);
+        }
+
+        two_fa_entry.code = Some(code.as_ref().to_string());
+        two_fa_entry.resends += 1;
+        two_fa_entry.last_sent_at = now;
+
+        let serialized_two_fa_entry = serde_json::to_string(&two_fa_entry)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        conn.set_ex::<_, _, ()>(key, serialized_two_fa_entry, TEN_MINUTES_IN_SECONDS)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)
+    }
 }
 
 
     #[derive(Serialize, Deserialize)]
-    struct TwoFATuple(pub String, pub String);
+    struct TwoFAEntry {
+        email: String,
+        /// `None` once too many wrong guesses invalidated it.
+        code: Option<String>,
+        failed_attempts: u32,
+        resends: u32,
+        /// Unix timestamp of when the code was first sent or last resent.
+        last_sent_at: i64,
+    }
     
     const TEN_MINUTES_IN_SECONDS: u64 = 600;
     const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
     
-    fn get_key(email: &Email) -> String {
-        format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref())
+    fn get_key(login_attempt_id: &LoginAttemptId) -> String {
+        format!("{}{}", TWO_FA_CODE_PREFIX, login_attempt_id.as_ref())
+    }
+
+    fn get_entry(conn: &mut Connection, key: &str) -> Result<TwoFAEntry, TwoFACodeStoreError> {
+        let value: String = conn
+            .get(key)
+            .map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+        serde_json::from_str(&value).map_err(|_| TwoFACodeStoreError::UnexpectedError)
     }
@@ -2,12 +2,34 @@ pub mod data_stores;
 
 // Re-export moved modules so existing imports keep working
 pub use data_stores::{
+    hashmap_authorization_code_store,
+    hashmap_client_store,
+    hashmap_email_token_store,
+    hashmap_passkey_challenge_store,
+    hashmap_passkey_store,
+    hashmap_refresh_token_store,
+    hashmap_session_epoch_store,
+    hashmap_session_store,
+    hashmap_totp_store,
+    hashmap_trusted_device_store,
     hashmap_two_fa_code_store,
     hashmap_user_store,
     hashset_banned_token_store,
+    http_sms_client,
     mock_email_client,
+    mock_sms_client,
+    postgres_client_store,
+    postgres_passkey_store,
+    postgres_session_store,
+    postgres_totp_store,
+    postgres_trusted_device_store,
     postgres_user_store,
+    redis_authorization_code_store,
     redis_banned_token_store,
+    redis_email_token_store,
+    redis_passkey_challenge_store,
+    redis_refresh_token_store,
+    redis_session_epoch_store,
     redis_two_fa_code_store,
 };
 
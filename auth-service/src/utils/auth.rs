@@ -1,17 +1,449 @@
+use std::{collections::HashMap, str::FromStr};
+
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use chrono::Utc;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
-use serde::{Deserialize, Serialize};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::{Error, ErrorKind},
+    jwk::JwkSet,
+    Algorithm, TokenData, Validation,
+};
+use lazy_static::lazy_static;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    app_state::{
+        AppState, BannedTokenStoreType, RefreshTokenStoreType, SessionEpochStoreType,
+        SessionStoreType,
+    },
+    domain::{email::Email, LoginAttemptId, RefreshToken, Session, TrustedDevice, UserStoreError},
+};
+
+use super::{
+    constants::{
+        JWT_ALGORITHM, JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_ISSUER, JWT_KEYS_PATH,
+        JWT_PRIVATE_KEY_PATH, JWT_SECRET, PRE_AUTH_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME,
+        TRUSTED_DEVICE_COOKIE_NAME,
+    },
+    client_info::ClientInfo,
+    jwt_key::{JwtKey, JwtKeyError},
+};
+
+lazy_static! {
+    pub static ref KEY_RING: KeyRing = load_key_ring();
+}
+
+/// Kid given to the key built from `JWT_ALGORITHM` when no key ring file is
+/// configured.
+pub const DEFAULT_KEY_ID: &str = "default";
+
+/// All keys we accept tokens from: one active key that signs new tokens and
+/// any number of retired keys kept around so tokens they signed stay valid
+/// until they expire.
+///
+/// To rotate, add the new key as verification-only everywhere first, then
+/// make it active, then drop the old key once its tokens have expired.
+pub struct KeyRing {
+    active_kid: String,
+    keys: HashMap<String, JwtKey>,
+}
+
+#[derive(Debug)]
+pub enum KeyRingError {
+    DuplicateKeyId(String),
+    ActiveKeyCannotSign(String),
+    UnknownActiveKey(String),
+    InvalidKey(String, JwtKeyError),
+    InvalidConfig(String),
+}
+
+impl KeyRing {
+    pub fn new(active: JwtKey, verification_keys: Vec<JwtKey>) -> Result<Self, KeyRingError> {
+        if active.encoding_key().is_none() {
+            return Err(KeyRingError::ActiveKeyCannotSign(active.kid().to_owned()));
+        }
+
+        let active_kid = active.kid().to_owned();
+        let mut keys = HashMap::new();
+        for key in std::iter::once(active).chain(verification_keys) {
+            let kid = key.kid().to_owned();
+            if keys.insert(kid.clone(), key).is_some() {
+                return Err(KeyRingError::DuplicateKeyId(kid));
+            }
+        }
+
+        Ok(Self { active_kid, keys })
+    }
+
+    /// Builds a key ring from the JSON document `JWT_KEYS_PATH` points to.
+    /// Key paths are read from disk as given.
+    pub fn from_config(config: &str) -> Result<Self, KeyRingError> {
+        let config: KeyRingConfig =
+            serde_json::from_str(config).map_err(|e| KeyRingError::InvalidConfig(e.to_string()))?;
+
+        let mut active = None;
+        let mut verification_keys = Vec::new();
+        for key_config in config.keys {
+            let kid = key_config.kid.clone();
+            let key = key_config
+                .load()
+                .map_err(|e| KeyRingError::InvalidKey(kid.clone(), e))?;
+            if kid == config.active {
+                if active.replace(key).is_some() {
+                    return Err(KeyRingError::DuplicateKeyId(kid));
+                }
+            } else {
+                verification_keys.push(key);
+            }
+        }
+
+        let active = active.ok_or(KeyRingError::UnknownActiveKey(config.active))?;
+        Self::new(active, verification_keys)
+    }
+
+    pub fn signing_key(&self) -> &JwtKey {
+        &self.keys[&self.active_kid]
+    }
+
+    pub fn get(&self, kid: &str) -> Option<&JwtKey> {
+        self.keys.get(kid)
+    }
+
+    /// Public keys of every asymmetric key in the ring, so tokens signed by a
+    /// retired key can still be verified by relying services.
+    pub fn jwks(&self) -> JwkSet {
+        let mut keys: Vec<_> = self
+            .keys
+            .values()
+            .filter_map(JwtKey::jwk)
+            .cloned()
+            .collect();
+        keys.sort_by(|a, b| a.common.key_id.cmp(&b.common.key_id));
+        JwkSet { keys }
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, Error> {
+        let key = self.signing_key();
+        let mut header = jsonwebtoken::Header::new(key.algorithm());
+        header.kid = Some(key.kid().to_owned());
+
+        let encoding_key = key
+            .encoding_key()
+            .ok_or_else(|| Error::from(ErrorKind::InvalidKeyFormat))?;
+        encode(&header, claims, encoding_key)
+    }
+
+    /// Verifies `token` with the key named by its `kid`. Tokens without a
+    /// `kid` predate the key ring and are checked against the active key.
+    /// The algorithm always comes from our key, never from the token.
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        validation: &Validation,
+    ) -> Result<TokenData<T>, Error> {
+        let header = decode_header(token)?;
+        let key = match header.kid {
+            Some(kid) => self
+                .get(&kid)
+                .ok_or_else(|| Error::from(ErrorKind::InvalidToken))?,
+            None => self.signing_key(),
+        };
+
+        let mut validation = validation.clone();
+        validation.algorithms = vec![key.algorithm()];
+        decode::<T>(token, key.decoding_key(), &validation)
+    }
+}
+
+#[derive(Deserialize)]
+struct KeyRingConfig {
+    active: String,
+    keys: Vec<KeyConfig>,
+}
+
+#[derive(Deserialize)]
+struct KeyConfig {
+    kid: String,
+    algorithm: String,
+    secret: Option<String>,
+    private_key_path: Option<String>,
+    public_key_path: Option<String>,
+}
+
+impl KeyConfig {
+    fn load(&self) -> Result<JwtKey, JwtKeyError> {
+        let algorithm = Algorithm::from_str(&self.algorithm)
+            .map_err(|_| JwtKeyError::UnsupportedAlgorithm(self.algorithm.clone()))?;
+
+        if algorithm == Algorithm::HS256 {
+            return match &self.secret {
+                Some(secret) if !secret.is_empty() => {
+                    Ok(JwtKey::hmac(&self.kid, secret.as_bytes()))
+                }
+                _ => Err(JwtKeyError::InvalidKey("missing secret".to_owned())),
+            };
+        }
+
+        match (&self.private_key_path, &self.public_key_path) {
+            (Some(path), _) => JwtKey::from_private_key_pem(&self.kid, algorithm, &read_pem(path)?),
+            (None, Some(path)) => {
+                JwtKey::from_public_key_pem(&self.kid, algorithm, &read_pem(path)?)
+            }
+            (None, None) => Err(JwtKeyError::InvalidKey("missing key path".to_owned())),
+        }
+    }
+}
+
+fn read_pem(path: &str) -> Result<String, JwtKeyError> {
+    std::fs::read_to_string(path).map_err(|e| JwtKeyError::InvalidKey(format!("{}: {}", path, e)))
+}
+
+fn load_key_ring() -> KeyRing {
+    if let Some(path) = JWT_KEYS_PATH.as_ref() {
+        let config = std::fs::read_to_string(path).expect("Failed to read JWT_KEYS_PATH.");
+        return KeyRing::from_config(&config).expect("JWT_KEYS_PATH is not a valid key ring.");
+    }
+
+    let algorithm =
+        Algorithm::from_str(&JWT_ALGORITHM).expect("JWT_ALGORITHM is not a known algorithm.");
+
+    let key = if algorithm == Algorithm::HS256 {
+        JwtKey::hmac(DEFAULT_KEY_ID, JWT_SECRET.as_bytes())
+    } else {
+        let pem = std::fs::read_to_string(JWT_PRIVATE_KEY_PATH.as_str())
+            .expect("Failed to read the key at JWT_PRIVATE_KEY_PATH.");
+        JwtKey::from_private_key_pem(DEFAULT_KEY_ID, algorithm, &pem)
+            .expect("JWT_PRIVATE_KEY_PATH must contain a private key matching JWT_ALGORITHM.")
+    };
+
+    KeyRing::new(key, Vec::new()).expect("Failed to build the key ring.")
+}
+
+/// Starts a new session for `email` in the app named by `audience`: records
+/// it in the session store and returns the auth and refresh cookies that
+/// belong to it.
+pub async fn start_session(
+    email: &Email,
+    audience: &str,
+    client: ClientInfo,
+    state: &AppState,
+) -> Result<(Cookie<'static>, Cookie<'static>), GenerateTokenError> {
+    let expires_at = session_expiry(Utc::now())?;
+    let (session, epoch) = record_session(email, client, expires_at, state).await?;
+
+    let auth_cookie = generate_auth_cookie(email, epoch, &session.id, audience)?;
+    let refresh_cookie = generate_refresh_cookie(
+        email,
+        &session.id,
+        epoch,
+        audience,
+        state.refresh_token_store.clone(),
+    )
+    .await?;
+
+    Ok((auth_cookie, refresh_cookie))
+}
 
-use crate::{app_state::BannedTokenStoreType, domain::email::Email};
+/// Records a new session for `email` in the session store. Returns it
+/// along with the user's current session epoch, which tokens for the
+/// session must carry. Fails with `UserNotFound` if the account has moved
+/// to another address since the login started.
+pub async fn record_session(
+    email: &Email,
+    client: ClientInfo,
+    expires_at: chrono::DateTime<Utc>,
+    state: &AppState,
+) -> Result<(Session, u64), GenerateTokenError> {
+    let epoch = state
+        .session_epoch_store
+        .read()
+        .await
+        .get_epoch(email)
+        .await
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    let session = Session {
+        id: uuid::Uuid::new_v4().to_string(),
+        email: email.clone(),
+        created_at: Utc::now(),
+        expires_at,
+        ip_address: client.ip_address,
+        user_agent: client.user_agent,
+    };
+
+    // Held until the session is recorded, so an email change can't commit
+    // in between and miss it when revoking the old address's sessions
+    let user_store = state.user_store.read().await;
+    match user_store.get_user(email).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => return Err(GenerateTokenError::UserNotFound),
+        Err(_) => return Err(GenerateTokenError::UnexpectedError),
+    }
+
+    state
+        .session_store
+        .write()
+        .await
+        .add_session(session.clone())
+        .await
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    Ok((session, epoch))
+}
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+/// A session lives as long as its newest refresh token.
+pub fn session_expiry(
+    from: chrono::DateTime<Utc>,
+) -> Result<chrono::DateTime<Utc>, GenerateTokenError> {
+    chrono::Duration::try_seconds(REFRESH_TOKEN_TTL_SECONDS)
+        .and_then(|ttl| from.checked_add_signed(ttl))
+        .ok_or(GenerateTokenError::UnexpectedError)
+}
 
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>, GenerateTokenError> {
-    let token = generate_auth_token(email)?;
+/// `epoch` must be the user's current session epoch, see
+/// `SessionEpochStore`, `session_id` the session the token is issued for and
+/// `audience` the app it is issued to.
+pub fn generate_auth_cookie(
+    email: &Email,
+    epoch: u64,
+    session_id: &str,
+    audience: &str,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let token = generate_auth_token(email, epoch, session_id, audience)?;
     Ok(create_auth_cookie(token))
 }
 
+/// Mints a new refresh token in `family_id`, records it in the store and
+/// wraps it in a cookie. Pass a fresh family id for a new login and the
+/// current one when rotating. Access tokens minted from it are issued to
+/// `audience`.
+pub async fn generate_refresh_cookie(
+    email: &Email,
+    family_id: &str,
+    epoch: u64,
+    audience: &str,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let token = RefreshToken::default();
+
+    refresh_token_store
+        .write()
+        .await
+        .add_token(
+            token.clone(),
+            email.clone(),
+            family_id.to_owned(),
+            epoch,
+            audience.to_owned(),
+        )
+        .await
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    Ok(create_refresh_cookie(token))
+}
+
+/// Issued after the password step of a 2FA login. It only proves the
+/// password was right for this login attempt and is accepted by nothing but
+/// `/verify-2fa`.
+pub fn generate_pre_auth_cookie(
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let claims = PreAuthClaims {
+        sub: email.as_ref().to_owned(),
+        iss: JWT_ISSUER.to_owned(),
+        exp: expiry_timestamp(PRE_AUTH_TOKEN_TTL_SECONDS)?,
+        aud: PRE_AUTH_AUDIENCE.to_owned(),
+        login_attempt_id: login_attempt_id.as_ref().to_owned(),
+    };
+
+    let token = KEY_RING
+        .encode(&claims)
+        .map_err(GenerateTokenError::TokenError)?;
+
+    let cookie = Cookie::build((PRE_AUTH_COOKIE_NAME, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .max_age(time::Duration::seconds(PRE_AUTH_TOKEN_TTL_SECONDS))
+        .build();
+
+    Ok(cookie)
+}
+
+pub fn validate_pre_auth_token(token: &str) -> Result<PreAuthClaims, jsonwebtoken::errors::Error> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[PRE_AUTH_AUDIENCE]);
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+
+    KEY_RING
+        .decode::<PreAuthClaims>(token, &validation)
+        .map(|data| data.claims)
+}
+
+/// Records the browser `client` describes as trusted by `email` and
+/// returns the device cookie that lets it skip 2FA on later logins.
+pub async fn trust_device(
+    email: &Email,
+    client: ClientInfo,
+    state: &AppState,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let now = Utc::now();
+    let device = TrustedDevice {
+        id: uuid::Uuid::new_v4().to_string(),
+        email: email.clone(),
+        created_at: now,
+        expires_at: chrono::Duration::try_seconds(TRUSTED_DEVICE_TTL_SECONDS)
+            .and_then(|ttl| now.checked_add_signed(ttl))
+            .ok_or(GenerateTokenError::UnexpectedError)?,
+        ip_address: client.ip_address,
+        user_agent: client.user_agent,
+    };
+
+    let claims = TrustedDeviceClaims {
+        sub: email.as_ref().to_owned(),
+        iss: JWT_ISSUER.to_owned(),
+        exp: expiry_timestamp(TRUSTED_DEVICE_TTL_SECONDS)?,
+        aud: TRUSTED_DEVICE_AUDIENCE.to_owned(),
+        jti: device.id.clone(),
+    };
+
+    let token = KEY_RING
+        .encode(&claims)
+        .map_err(GenerateTokenError::TokenError)?;
+
+    state
+        .trusted_device_store
+        .write()
+        .await
+        .add_device(device)
+        .await
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    let cookie = Cookie::build((TRUSTED_DEVICE_COOKIE_NAME, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .max_age(time::Duration::seconds(TRUSTED_DEVICE_TTL_SECONDS))
+        .build();
+
+    Ok(cookie)
+}
+
+/// Only checks the signature and expiry. Whether the device is still
+/// trusted is up to the trusted-device store.
+pub fn validate_trusted_device_token(
+    token: &str,
+) -> Result<TrustedDeviceClaims, jsonwebtoken::errors::Error> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[TRUSTED_DEVICE_AUDIENCE]);
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+
+    KEY_RING
+        .decode::<TrustedDeviceClaims>(token, &validation)
+        .map(|data| data.claims)
+}
+
 fn create_auth_cookie(token: String) -> Cookie<'static> {
     let cookie = Cookie::build((JWT_COOKIE_NAME, token))
         .path("/")
@@ -22,37 +454,171 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
+fn create_refresh_cookie(token: RefreshToken) -> Cookie<'static> {
+    let cookie = Cookie::build((REFRESH_TOKEN_COOKIE_NAME, token.as_ref().to_owned()))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        .build();
+
+    cookie
+}
+
 #[derive(Debug)]
 pub enum GenerateTokenError {
     TokenError(jsonwebtoken::errors::Error),
+    UserNotFound,
     UnexpectedError,
 }
 
 pub const TOKEN_TTL_SECONDS: i64 = 600;
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24 * 14;
+pub const PRE_AUTH_TOKEN_TTL_SECONDS: i64 = 300;
+pub const TRUSTED_DEVICE_TTL_SECONDS: i64 = 60 * 60 * 24 * 30;
 
-fn generate_auth_token(email: &Email) -> Result<String, GenerateTokenError> {
-    let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
-        .ok_or(GenerateTokenError::UnexpectedError)?;
+pub const CLIENT_CREDENTIALS_GRANT: &str = "client_credentials";
+
+/// Audience of pre-auth tokens. It is never in `JWT_AUDIENCE`, so
+/// `validate_token` rejects them as access tokens.
+const PRE_AUTH_AUDIENCE: &str = "verify-2fa";
+
+/// Audience of device cookies, which likewise aren't access tokens.
+const TRUSTED_DEVICE_AUDIENCE: &str = "trusted-device";
+
+pub(crate) fn expiry_timestamp(ttl_seconds: i64) -> Result<usize, GenerateTokenError> {
+    let delta =
+        chrono::Duration::try_seconds(ttl_seconds).ok_or(GenerateTokenError::UnexpectedError)?;
 
     let exp = Utc::now()
         .checked_add_signed(delta)
         .ok_or(GenerateTokenError::UnexpectedError)?
         .timestamp();
 
-    let exp: usize = exp
+    exp.try_into()
+        .map_err(|_| GenerateTokenError::UnexpectedError)
+}
+
+pub(crate) fn current_timestamp() -> Result<usize, GenerateTokenError> {
+    Utc::now()
+        .timestamp()
         .try_into()
-        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+        .map_err(|_| GenerateTokenError::UnexpectedError)
+}
+
+fn generate_auth_token(
+    email: &Email,
+    epoch: u64,
+    session_id: &str,
+    audience: &str,
+) -> Result<String, GenerateTokenError> {
+    let claims = access_token_claims(email, epoch, session_id, audience)?;
+    create_token(&claims).map_err(GenerateTokenError::TokenError)
+}
+
+/// Access token issued to an OAuth client on the user's behalf. Its
+/// audience is the client itself, so first-party apps turn it away; it is
+/// good for the client's own backend and `/userinfo`, within its scopes.
+pub fn generate_client_access_token(
+    email: &Email,
+    epoch: u64,
+    session_id: &str,
+    client_id: &str,
+    scope: Option<&str>,
+) -> Result<String, GenerateTokenError> {
+    let claims = Claims {
+        client_id: Some(client_id.to_owned()),
+        scope: scope.map(str::to_owned),
+        ..access_token_claims(email, epoch, session_id, client_id)?
+    };
+    create_token(&claims).map_err(GenerateTokenError::TokenError)
+}
+
+fn access_token_claims(
+    email: &Email,
+    epoch: u64,
+    session_id: &str,
+    audience: &str,
+) -> Result<Claims, GenerateTokenError> {
+    let now = current_timestamp()?;
+    let exp = expiry_timestamp(TOKEN_TTL_SECONDS)?;
 
     let sub = email.as_ref().to_owned();
 
-    let claims = Claims { sub, exp };
+    Ok(Claims {
+        sub,
+        iss: JWT_ISSUER.to_owned(),
+        aud: audience.to_owned(),
+        exp,
+        iat: now,
+        nbf: now,
+        epoch,
+        jti: session_id.to_owned(),
+        scope: None,
+        client_id: None,
+        gty: None,
+    })
+}
+
+/// Access token for a registered machine client acting on its own behalf,
+/// to call the app named by `audience`. It belongs to no user or session,
+/// so it only dies by expiring.
+pub fn generate_client_credentials_token(
+    client_id: &str,
+    scope: Option<&str>,
+    audience: &str,
+) -> Result<String, GenerateTokenError> {
+    let now = current_timestamp()?;
 
+    let claims = Claims {
+        sub: client_id.to_owned(),
+        iss: JWT_ISSUER.to_owned(),
+        aud: audience.to_owned(),
+        exp: expiry_timestamp(TOKEN_TTL_SECONDS)?,
+        iat: now,
+        nbf: now,
+        epoch: 0,
+        jti: uuid::Uuid::new_v4().to_string(),
+        scope: scope.map(str::to_owned),
+        client_id: Some(client_id.to_owned()),
+        gty: Some(CLIENT_CREDENTIALS_GRANT.to_owned()),
+    };
     create_token(&claims).map_err(GenerateTokenError::TokenError)
 }
 
+/// The app a first-party token is issued to: the one the client asked for,
+/// which must be listed in `JWT_AUDIENCE`, or else the first one listed.
+pub fn first_party_audience(requested: Option<&str>) -> Option<String> {
+    match requested {
+        Some(audience) => JWT_AUDIENCE
+            .iter()
+            .find(|known| known.as_str() == audience)
+            .cloned(),
+        None => Some(JWT_AUDIENCE[0].clone()),
+    }
+}
+
+/// Which audiences `validate_token` accepts.
+#[derive(Clone, Copy, Debug)]
+pub enum ExpectedAudience<'a> {
+    /// Only tokens issued to this app.
+    Exactly(&'a str),
+    /// Tokens issued to any app in `JWT_AUDIENCE`. Only auth-service's own
+    /// routes accept these, since a user logged in to any of our apps
+    /// manages their account here.
+    FirstParty,
+    /// Any audience, for introspection, which reports it for the caller to
+    /// check.
+    Any,
+}
+
+/// Validates an access token issued to the `audience` it names.
 pub async fn validate_token(
     token: &str,
+    audience: ExpectedAudience<'_>,
     banned_token_store: BannedTokenStoreType,
+    session_epoch_store: SessionEpochStoreType,
+    session_store: SessionStoreType,
 ) -> Result<Claims, jsonwebtoken::errors::Error> {
     match banned_token_store.read().await.contains_token(token).await {
         Ok(value) => {
@@ -69,26 +635,107 @@ pub async fn validate_token(
         }
     }
 
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
+    let claims = KEY_RING
+        .decode::<Claims>(token, &access_token_validation(audience))
+        .map(|data| data.claims)?;
+
+    // Machine tokens have no user, and so no epoch or session, behind them
+    if claims.is_client_token() {
+        return match claims.client_id.as_deref() {
+            Some(client_id) if client_id == claims.sub => Ok(claims),
+            _ => Err(Error::from(ErrorKind::InvalidSubject)),
+        };
+    }
+
+    // Tokens from before the user's last "log out everywhere" are dead
+    let email = Email::parse(claims.sub.clone())
+        .map_err(|_| Error::from(ErrorKind::InvalidSubject))?;
+    let current_epoch = session_epoch_store
+        .read()
+        .await
+        .get_epoch(&email)
+        .await
+        .map_err(|_| Error::from(ErrorKind::InvalidToken))?;
+    if claims.epoch < current_epoch {
+        return Err(Error::from(ErrorKind::InvalidToken));
+    }
+
+    // The session must not have been revoked or have expired
+    match session_store.read().await.get_session(&claims.jti).await {
+        Ok(session) if session.email == email => Ok(claims),
+        _ => Err(Error::from(ErrorKind::InvalidToken)),
+    }
+}
+
+fn access_token_validation(audience: ExpectedAudience) -> Validation {
+    let mut validation = Validation::default();
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.validate_nbf = true;
+    match audience {
+        ExpectedAudience::Exactly(audience) => validation.set_audience(&[audience]),
+        ExpectedAudience::FirstParty => validation.set_audience(&JWT_AUDIENCE),
+        ExpectedAudience::Any => validation.validate_aud = false,
+    }
+    validation
 }
 
 fn create_token(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
-    )
+    KEY_RING.encode(claims)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
+    pub iss: String,
+    /// The one app the token is issued to.
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    pub nbf: usize,
+    /// Session epoch the token was issued in. Missing on tokens minted
+    /// before epochs existed, which is the same as epoch 0.
+    #[serde(default)]
+    pub epoch: u64,
+    /// Id of the session the token belongs to. Tokens minted by `/refresh`
+    /// keep the id of the session they refresh.
+    pub jti: String,
+    /// Space-separated scopes granted to the token. First-party logins
+    /// are not scoped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// OAuth client the token was issued to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Grant the token was minted by. Only set on `client_credentials`
+    /// tokens, whose `sub` is the client id rather than a user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gty: Option<String>,
+}
+
+impl Claims {
+    pub fn is_client_token(&self) -> bool {
+        self.gty.as_deref() == Some(CLIENT_CREDENTIALS_GRANT)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PreAuthClaims {
+    pub sub: String,
+    pub iss: String,
     pub exp: usize,
+    pub aud: String,
+    pub login_attempt_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrustedDeviceClaims {
+    pub sub: String,
+    pub iss: String,
+    pub exp: usize,
+    pub aud: String,
+    /// Id of the device in the trusted-device store.
+    pub jti: String,
 }
 
 #[cfg(test)]
@@ -97,15 +744,69 @@ mod tests {
     use tokio::sync::RwLock;
 
     use crate::{
-        domain::BannedTokenStore, services::hashset_banned_token_store::HashsetBannedTokenStore,
+        domain::{BannedTokenStore, RefreshTokenStore, SessionEpochStore, SessionStore},
+        services::{
+            hashmap_refresh_token_store::HashmapRefreshTokenStore,
+            hashmap_session_epoch_store::HashmapSessionEpochStore,
+            hashmap_session_store::HashmapSessionStore,
+            hashset_banned_token_store::HashsetBannedTokenStore,
+        },
     };
 
     use super::*;
 
+    #[derive(Default)]
+    struct TestStores {
+        banned_token_store: Arc<RwLock<HashsetBannedTokenStore>>,
+        session_epoch_store: Arc<RwLock<HashmapSessionEpochStore>>,
+        session_store: Arc<RwLock<HashmapSessionStore>>,
+    }
+
+    impl TestStores {
+        async fn add_session(&self, email: &Email) -> String {
+            let now = Utc::now();
+            let session = Session {
+                id: uuid::Uuid::new_v4().to_string(),
+                email: email.clone(),
+                created_at: now,
+                expires_at: session_expiry(now).unwrap(),
+                ip_address: None,
+                user_agent: None,
+            };
+            let id = session.id.clone();
+            self.session_store
+                .write()
+                .await
+                .add_session(session)
+                .await
+                .unwrap();
+            id
+        }
+
+        async fn validate(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+            self.validate_for(token, ExpectedAudience::FirstParty).await
+        }
+
+        async fn validate_for(
+            &self,
+            token: &str,
+            audience: ExpectedAudience<'_>,
+        ) -> Result<Claims, jsonwebtoken::errors::Error> {
+            validate_token(
+                token,
+                audience,
+                self.banned_token_store.clone(),
+                self.session_epoch_store.clone(),
+                self.session_store.clone(),
+            )
+            .await
+        }
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(&email, 0, "session", &JWT_AUDIENCE[0]).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -124,20 +825,56 @@ mod tests {
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_generate_refresh_cookie() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let cookie = generate_refresh_cookie(
+            &email,
+            "family",
+            0,
+            &JWT_AUDIENCE[0],
+            refresh_token_store.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        );
+
+        let token = RefreshToken::parse(cookie.value().to_owned()).unwrap();
+        let record = refresh_token_store
+            .read()
+            .await
+            .get_token(&token)
+            .await
+            .unwrap();
+        assert_eq!(record.email, email);
+        assert_eq!(record.family_id, "family");
+        assert!(!record.used);
+    }
+
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&email, 0, "session", &JWT_AUDIENCE[0]).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
+        let stores = TestStores::default();
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await.unwrap();
+        let session_id = stores.add_session(&email).await;
+        let token = generate_auth_token(&email, 0, &session_id, &JWT_AUDIENCE[0]).unwrap();
+        let result = stores.validate(&token).await.unwrap();
         assert_eq!(result.sub, "test@example.com");
+        assert_eq!(result.jti, session_id);
 
         let exp = Utc::now()
             .checked_add_signed(chrono::Duration::try_minutes(9).expect("valid duration"))
@@ -147,22 +884,383 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_validate_client_credentials_token() {
+        let stores = TestStores::default();
+        let token =
+            generate_client_credentials_token("worker", Some("reports:read"), &JWT_AUDIENCE[0])
+                .unwrap();
+
+        let claims = stores.validate(&token).await.unwrap();
+        assert!(claims.is_client_token());
+        assert_eq!(claims.sub, "worker");
+        assert_eq!(claims.client_id.as_deref(), Some("worker"));
+        assert_eq!(claims.scope.as_deref(), Some("reports:read"));
+
+        // A client token must name itself as the client
+        let now = Utc::now().timestamp() as usize;
+        let forged = KEY_RING
+            .encode(&Claims {
+                sub: "test@example.com".to_owned(),
+                exp: now + 600,
+                ..claims
+            })
+            .unwrap();
+        assert!(stores.validate(&forged).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_client_access_token() {
+        let stores = TestStores::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let session_id = stores.add_session(&email).await;
+        let token =
+            generate_client_access_token(&email, 0, &session_id, "client", Some("openid"))
+                .unwrap();
+
+        // Issued to the client, not to our own apps
+        let result = stores.validate(&token).await;
+        assert_eq!(result.unwrap_err().into_kind(), ErrorKind::InvalidAudience);
+
+        let claims = stores
+            .validate_for(&token, ExpectedAudience::Exactly("client"))
+            .await
+            .unwrap();
+        assert_eq!(claims.aud, "client");
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.client_id.as_deref(), Some("client"));
+        assert!(!claims.is_client_token());
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
-        let token = "invalid_token".to_owned();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await;
+        let stores = TestStores::default();
+        let result = stores.validate("invalid_token").await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
+        let stores = TestStores::default();
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let mut hs = HashsetBannedTokenStore::default();
-        hs.add_token(token.clone()).await.unwrap();
-        let banned_token_store = Arc::new(RwLock::new(hs));
-        let result = validate_token(&token, banned_token_store).await;
+        let session_id = stores.add_session(&email).await;
+        let token = generate_auth_token(&email, 0, &session_id, &JWT_AUDIENCE[0]).unwrap();
+        stores
+            .banned_token_store
+            .write()
+            .await
+            .add_token(token.clone())
+            .await
+            .unwrap();
+        let result = stores.validate(&token).await;
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_validate_token_from_previous_epoch() {
+        let stores = TestStores::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let session_id = stores.add_session(&email).await;
+        let old_token = generate_auth_token(&email, 0, &session_id, &JWT_AUDIENCE[0]).unwrap();
+
+        let epoch = stores
+            .session_epoch_store
+            .write()
+            .await
+            .increment_epoch(&email)
+            .await
+            .unwrap();
+
+        assert!(stores.validate(&old_token).await.is_err());
+
+        let new_token = generate_auth_token(&email, epoch, &session_id, &JWT_AUDIENCE[0]).unwrap();
+        let result = stores.validate(&new_token).await.unwrap();
+        assert_eq!(result.epoch, epoch);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_revoked_session() {
+        let stores = TestStores::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let session_id = stores.add_session(&email).await;
+        let token = generate_auth_token(&email, 0, &session_id, &JWT_AUDIENCE[0]).unwrap();
+        assert!(stores.validate(&token).await.is_ok());
+
+        stores
+            .session_store
+            .write()
+            .await
+            .revoke_session(&email, &session_id)
+            .await
+            .unwrap();
+        assert!(stores.validate(&token).await.is_err());
+
+        // A token can't borrow another user's session either
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        let session_id = stores.add_session(&email).await;
+        let token = generate_auth_token(&other, 0, &session_id, &JWT_AUDIENCE[0]).unwrap();
+        assert!(stores.validate(&token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_checks_audience() {
+        let stores = TestStores::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let session_id = stores.add_session(&email).await;
+        let token = generate_auth_token(&email, 0, &session_id, &JWT_AUDIENCE[0]).unwrap();
+
+        let claims = stores
+            .validate_for(&token, ExpectedAudience::Exactly(&JWT_AUDIENCE[0]))
+            .await
+            .unwrap();
+        assert_eq!(claims.iss, *JWT_ISSUER);
+        assert_eq!(claims.aud, JWT_AUDIENCE[0]);
+        assert_eq!(claims.iat, claims.nbf);
+
+        let result = stores
+            .validate_for(&token, ExpectedAudience::Exactly("another-app"))
+            .await;
+        assert_eq!(result.unwrap_err().into_kind(), ErrorKind::InvalidAudience);
+
+        // A token for an app we don't know is only good for introspection
+        let token = generate_auth_token(&email, 0, &session_id, "another-app").unwrap();
+        let result = stores.validate(&token).await;
+        assert_eq!(result.unwrap_err().into_kind(), ErrorKind::InvalidAudience);
+        let claims = stores
+            .validate_for(&token, ExpectedAudience::Any)
+            .await
+            .unwrap();
+        assert_eq!(claims.aud, "another-app");
+    }
+
+    #[test]
+    fn test_first_party_audience() {
+        assert_eq!(first_party_audience(None).as_ref(), Some(&JWT_AUDIENCE[0]));
+        assert_eq!(
+            first_party_audience(Some(&JWT_AUDIENCE[0])).as_ref(),
+            Some(&JWT_AUDIENCE[0])
+        );
+        assert_eq!(first_party_audience(Some("another-app")), None);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_checks_issuer_and_not_before() {
+        let stores = TestStores::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let session_id = stores.add_session(&email).await;
+        let now = Utc::now().timestamp() as usize;
+        let claims = Claims {
+            sub: email.as_ref().to_owned(),
+            iss: JWT_ISSUER.to_owned(),
+            aud: JWT_AUDIENCE[0].clone(),
+            exp: now + 600,
+            iat: now,
+            nbf: now,
+            epoch: 0,
+            jti: session_id,
+            scope: None,
+            client_id: None,
+            gty: None,
+        };
+
+        let foreign_token = KEY_RING
+            .encode(&Claims {
+                iss: "someone-else".to_owned(),
+                ..claims.clone()
+            })
+            .unwrap();
+        assert_eq!(
+            stores.validate(&foreign_token).await.unwrap_err().into_kind(),
+            ErrorKind::InvalidIssuer
+        );
+
+        let early_token = KEY_RING
+            .encode(&Claims {
+                nbf: now + 300,
+                ..claims.clone()
+            })
+            .unwrap();
+        assert_eq!(
+            stores.validate(&early_token).await.unwrap_err().into_kind(),
+            ErrorKind::ImmatureSignature
+        );
+
+        let token = KEY_RING.encode(&claims).unwrap();
+        assert!(stores.validate(&token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pre_auth_token_is_bound_to_login_attempt() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        let cookie = generate_pre_auth_cookie(&email, &login_attempt_id).unwrap();
+        assert_eq!(cookie.name(), PRE_AUTH_COOKIE_NAME);
+        assert_eq!(cookie.http_only(), Some(true));
+
+        let claims = validate_pre_auth_token(cookie.value()).unwrap();
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.login_attempt_id, login_attempt_id.as_ref());
+    }
+
+    #[tokio::test]
+    async fn test_pre_auth_token_is_not_an_access_token() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let cookie = generate_pre_auth_cookie(&email, &LoginAttemptId::default()).unwrap();
+        let stores = TestStores::default();
+        assert!(stores.validate(cookie.value()).await.is_err());
+
+        let token = generate_auth_token(&email, 0, "session", &JWT_AUDIENCE[0]).unwrap();
+        assert!(validate_pre_auth_token(&token).is_err());
+    }
+
+    #[test]
+    fn test_generated_token_carries_active_kid() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = generate_auth_token(&email, 0, "session", &JWT_AUDIENCE[0]).unwrap();
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some(KEY_RING.signing_key().kid()));
+    }
+
+    #[test]
+    fn test_key_ring_verifies_tokens_from_retired_keys() {
+        let claims = Claims {
+            sub: "test@example.com".to_owned(),
+            iss: JWT_ISSUER.to_owned(),
+            aud: JWT_AUDIENCE[0].clone(),
+            exp: 4_102_444_800,
+            iat: 0,
+            nbf: 0,
+            epoch: 0,
+            jti: "session".to_owned(),
+            scope: None,
+            client_id: None,
+            gty: None,
+        };
+
+        let old_ring = KeyRing::new(JwtKey::hmac("old", b"old-secret"), Vec::new()).unwrap();
+        let old_token = old_ring.encode(&claims).unwrap();
+
+        let new_ring = KeyRing::new(
+            JwtKey::hmac("new", b"new-secret"),
+            vec![JwtKey::hmac("old", b"old-secret")],
+        )
+        .unwrap();
+        let new_token = new_ring.encode(&claims).unwrap();
+
+        assert_eq!(
+            decode_header(&new_token).unwrap().kid.as_deref(),
+            Some("new")
+        );
+        assert_eq!(
+            new_ring
+                .decode::<Claims>(&old_token, &access_token_validation(ExpectedAudience::FirstParty))
+                .unwrap()
+                .claims
+                .sub,
+            claims.sub
+        );
+        assert_eq!(
+            new_ring
+                .decode::<Claims>(&new_token, &access_token_validation(ExpectedAudience::FirstParty))
+                .unwrap()
+                .claims
+                .sub,
+            claims.sub
+        );
+
+        // Once the old key is dropped its tokens are rejected
+        let rotated_ring = KeyRing::new(JwtKey::hmac("new", b"new-secret"), Vec::new()).unwrap();
+        assert!(rotated_ring
+            .decode::<Claims>(&old_token, &access_token_validation(ExpectedAudience::FirstParty))
+            .is_err());
+        assert!(old_ring
+            .decode::<Claims>(&new_token, &access_token_validation(ExpectedAudience::FirstParty))
+            .is_err());
+    }
+
+    #[test]
+    fn test_key_ring_rejects_kid_signed_with_another_key() {
+        let claims = Claims {
+            sub: "test@example.com".to_owned(),
+            iss: JWT_ISSUER.to_owned(),
+            aud: JWT_AUDIENCE[0].clone(),
+            exp: 4_102_444_800,
+            iat: 0,
+            nbf: 0,
+            epoch: 0,
+            jti: "session".to_owned(),
+            scope: None,
+            client_id: None,
+            gty: None,
+        };
+        let forged_ring = KeyRing::new(JwtKey::hmac("old", b"forged"), Vec::new()).unwrap();
+        let forged_token = forged_ring.encode(&claims).unwrap();
+
+        let ring = KeyRing::new(
+            JwtKey::hmac("new", b"new-secret"),
+            vec![JwtKey::hmac("old", b"old-secret")],
+        )
+        .unwrap();
+        assert!(ring
+            .decode::<Claims>(&forged_token, &access_token_validation(ExpectedAudience::FirstParty))
+            .is_err());
+    }
+
+    #[test]
+    fn test_key_ring_from_config() {
+        let config = serde_json::json!({
+            "active": "2024-06",
+            "keys": [
+                { "kid": "2024-01", "algorithm": "HS256", "secret": "old-secret" },
+                { "kid": "2024-06", "algorithm": "HS256", "secret": "new-secret" }
+            ]
+        })
+        .to_string();
+
+        let ring = KeyRing::from_config(&config).unwrap();
+        assert_eq!(ring.signing_key().kid(), "2024-06");
+        assert!(ring.get("2024-01").is_some());
+        assert!(ring.jwks().keys.is_empty());
+    }
+
+    #[test]
+    fn test_key_ring_from_invalid_config() {
+        let unknown_active = serde_json::json!({
+            "active": "missing",
+            "keys": [{ "kid": "2024-01", "algorithm": "HS256", "secret": "secret" }]
+        })
+        .to_string();
+        assert!(matches!(
+            KeyRing::from_config(&unknown_active),
+            Err(KeyRingError::UnknownActiveKey(_))
+        ));
+
+        let duplicate_kid = serde_json::json!({
+            "active": "2024-01",
+            "keys": [
+                { "kid": "2024-01", "algorithm": "HS256", "secret": "secret" },
+                { "kid": "2024-01", "algorithm": "HS256", "secret": "other" }
+            ]
+        })
+        .to_string();
+        assert!(matches!(
+            KeyRing::from_config(&duplicate_kid),
+            Err(KeyRingError::DuplicateKeyId(_))
+        ));
+
+        let missing_key = serde_json::json!({
+            "active": "2024-01",
+            "keys": [{ "kid": "2024-01", "algorithm": "RS256" }]
+        })
+        .to_string();
+        assert!(matches!(
+            KeyRing::from_config(&missing_key),
+            Err(KeyRingError::InvalidKey(_, _))
+        ));
+
+        assert!(matches!(
+            KeyRing::from_config("not json"),
+            Err(KeyRingError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,63 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Client, ClientSecret},
+};
+
+/// A registered service calling a back-channel endpoint. Clients
+/// authenticate with HTTP Basic, using their client id and secret as the
+/// username and password.
+pub struct AuthenticatedClient(pub Client);
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthenticatedClient {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let (client_id, secret) =
+            basic_credentials(&parts.headers)?.ok_or(AuthAPIError::InvalidClient)?;
+
+        state
+            .client_store
+            .read()
+            .await
+            .validate_client(&client_id, Some(&secret))
+            .await
+            .map(Self)
+            .map_err(|_| AuthAPIError::InvalidClient)
+    }
+}
+
+/// Client id and secret from an `Authorization: Basic` header, or `None`
+/// when there is no such header.
+pub fn basic_credentials(
+    headers: &HeaderMap,
+) -> Result<Option<(String, ClientSecret)>, AuthAPIError> {
+    let Some(header) = headers.get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+
+    header
+        .to_str()
+        .ok()
+        .and_then(parse_basic_credentials)
+        .map(Some)
+        .ok_or(AuthAPIError::InvalidClient)
+}
+
+fn parse_basic_credentials(header: &str) -> Option<(String, ClientSecret)> {
+    let encoded = header.strip_prefix("Basic ")?.trim();
+    let decoded = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+    let (client_id, secret) = decoded.split_once(':')?;
+    let secret = ClientSecret::parse(secret.to_owned()).ok()?;
+    Some((client_id.to_owned(), secret))
+}
@@ -0,0 +1,94 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
+};
+use axum_extra::extract::CookieJar;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email},
+    utils::{
+        auth::{validate_token, Claims, ExpectedAudience},
+        constants::JWT_COOKIE_NAME,
+    },
+};
+
+/// Access token sent as `Authorization: Bearer <token>` or, for browsers, in
+/// the `jwt` cookie. The header wins when both are present.
+pub struct AuthToken(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthToken
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        token_from_headers(&parts.headers, JWT_COOKIE_NAME).map(Self)
+    }
+}
+
+/// The caller of a route that requires a valid access token.
+pub struct AuthenticatedUser {
+    pub email: Email,
+    pub claims: Claims,
+    pub token: String,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let AuthToken(token) = AuthToken::from_request_parts(parts, state).await?;
+
+        let claims = validate_token(
+            &token,
+            ExpectedAudience::FirstParty,
+            state.banned_token_store.clone(),
+            state.session_epoch_store.clone(),
+            state.session_store.clone(),
+        )
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        // Tokens issued to OAuth clients, whether for a user or for the
+        // client itself, are not first-party logins
+        if claims.client_id.is_some() {
+            return Err(AuthAPIError::InvalidToken);
+        }
+
+        let email = Email::parse(claims.sub.clone()).map_err(|_| AuthAPIError::InvalidToken)?;
+
+        Ok(Self {
+            email,
+            claims,
+            token,
+        })
+    }
+}
+
+/// Reads a token from the `Authorization: Bearer` header, falling back to
+/// the cookie named `cookie_name`.
+pub fn token_from_headers(headers: &HeaderMap, cookie_name: &str) -> Result<String, AuthAPIError> {
+    if let Some(header) = headers.get(AUTHORIZATION) {
+        let token = header
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .ok_or(AuthAPIError::MalformedToken)?;
+        return Ok(token.to_owned());
+    }
+
+    CookieJar::from_headers(headers)
+        .get(cookie_name)
+        .map(|cookie| cookie.value().to_owned())
+        .ok_or(AuthAPIError::MissingToken)
+}
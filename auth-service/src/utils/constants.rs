@@ -4,8 +4,25 @@ use std::env as std_env;
 
 lazy_static! {
     pub static ref JWT_SECRET: String = set_token();
+    pub static ref JWT_ALGORITHM: String = set_jwt_algorithm();
+    pub static ref JWT_PRIVATE_KEY_PATH: String = set_jwt_private_key_path();
+    pub static ref JWT_KEYS_PATH: Option<String> = set_jwt_keys_path();
+    pub static ref JWT_ISSUER: String = set_jwt_issuer();
+    pub static ref JWT_AUDIENCE: Vec<String> = set_jwt_audience();
+    pub static ref OIDC_ENABLED: bool = set_oidc_enabled();
     pub static ref DATABASE_URL: String = set_database_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
+    pub static ref TOTP_ISSUER: String = set_totp_issuer();
+    pub static ref TOTP_DRIFT_STEPS: u64 = set_totp_drift_steps();
+    pub static ref TWO_FA_MAX_ATTEMPTS: u32 = set_two_fa_max_attempts();
+    pub static ref TWO_FA_RESEND_COOLDOWN_SECONDS: u64 = set_two_fa_resend_cooldown_seconds();
+    pub static ref TWO_FA_MAX_RESENDS: u32 = set_two_fa_max_resends();
+    pub static ref WEBAUTHN_RP_ID: String = set_webauthn_rp_id();
+    pub static ref WEBAUTHN_ORIGIN: String = set_webauthn_origin();
+    pub static ref SMS_PROVIDER_URL: Option<String> = set_sms_provider_url();
+    pub static ref SMS_PROVIDER_AUTH_TOKEN: String = set_sms_provider_auth_token();
+    pub static ref SMS_SENDER: String = set_sms_sender();
+    pub static ref PUBLIC_URL: String = set_public_url();
 }
 
 fn set_token() -> String {
@@ -18,6 +35,66 @@ fn set_token() -> String {
     secret
 }
 
+fn set_jwt_algorithm() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_ALGORITHM_ENV_VAR).unwrap_or(DEFAULT_JWT_ALGORITHM.to_owned())
+}
+
+fn set_jwt_private_key_path() -> String {
+    dotenv().ok();
+    let path = std_env::var(env::JWT_PRIVATE_KEY_PATH_ENV_VAR)
+        .expect("JWT_PRIVATE_KEY_PATH must be set when signing with an asymmetric algorithm.");
+
+    if path.is_empty() {
+        panic!("JWT_PRIVATE_KEY_PATH must not be empty.");
+    }
+    path
+}
+
+fn set_jwt_keys_path() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::JWT_KEYS_PATH_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())
+}
+
+fn set_jwt_issuer() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_ISSUER_ENV_VAR)
+        .ok()
+        .filter(|issuer| !issuer.is_empty())
+        .unwrap_or(DEFAULT_JWT_ISSUER.to_owned())
+}
+
+/// Comma-separated list of the services our access tokens are meant for.
+fn set_jwt_audience() -> Vec<String> {
+    dotenv().ok();
+    let audience: Vec<String> = std_env::var(env::JWT_AUDIENCE_ENV_VAR)
+        .unwrap_or(DEFAULT_JWT_AUDIENCE.to_owned())
+        .split(',')
+        .map(str::trim)
+        .filter(|audience| !audience.is_empty())
+        .map(str::to_owned)
+        .collect();
+
+    if audience.is_empty() {
+        panic!("JWT_AUDIENCE must name at least one audience.");
+    }
+    audience
+}
+
+/// Whether we act as an OpenID Connect provider: grant the `openid` scope,
+/// issue ID tokens and serve discovery and `/userinfo`.
+fn set_oidc_enabled() -> bool {
+    dotenv().ok();
+    match std_env::var(env::OIDC_ENABLED_ENV_VAR) {
+        Ok(enabled) if !enabled.is_empty() => enabled
+            .parse()
+            .expect("OIDC_ENABLED must be true or false."),
+        _ => false,
+    }
+}
+
 fn set_database_url() -> String {
     dotenv().ok();
     let database_url = std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set.");
@@ -33,14 +110,160 @@ fn set_redis_host() -> String {
     std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
 }
 
+/// Name authenticator apps show next to the account.
+fn set_totp_issuer() -> String {
+    dotenv().ok();
+    std_env::var(env::TOTP_ISSUER_ENV_VAR)
+        .ok()
+        .filter(|issuer| !issuer.is_empty())
+        .unwrap_or(DEFAULT_TOTP_ISSUER.to_owned())
+}
+
+/// How many 30-second steps either side of the current one a TOTP code may
+/// come from.
+fn set_totp_drift_steps() -> u64 {
+    dotenv().ok();
+    match std_env::var(env::TOTP_DRIFT_STEPS_ENV_VAR) {
+        Ok(steps) if !steps.is_empty() => steps
+            .parse()
+            .expect("TOTP_DRIFT_STEPS must be a non-negative integer."),
+        _ => DEFAULT_TOTP_DRIFT_STEPS,
+    }
+}
+
+/// How many wrong 2FA or recovery codes a login attempt survives before it
+/// is invalidated and the user has to log in again.
+fn set_two_fa_max_attempts() -> u32 {
+    dotenv().ok();
+    match std_env::var(env::TWO_FA_MAX_ATTEMPTS_ENV_VAR) {
+        Ok(attempts) if !attempts.is_empty() => match attempts.parse() {
+            Ok(attempts) if attempts > 0 => attempts,
+            _ => panic!("TWO_FA_MAX_ATTEMPTS must be a positive integer."),
+        },
+        _ => DEFAULT_TWO_FA_MAX_ATTEMPTS,
+    }
+}
+
+/// Minimum time between two resends of the emailed 2FA code.
+fn set_two_fa_resend_cooldown_seconds() -> u64 {
+    dotenv().ok();
+    match std_env::var(env::TWO_FA_RESEND_COOLDOWN_SECONDS_ENV_VAR) {
+        Ok(seconds) if !seconds.is_empty() => seconds
+            .parse()
+            .expect("TWO_FA_RESEND_COOLDOWN_SECONDS must be a non-negative integer."),
+        _ => DEFAULT_TWO_FA_RESEND_COOLDOWN_SECONDS,
+    }
+}
+
+/// How many times the emailed 2FA code can be resent for one login attempt.
+fn set_two_fa_max_resends() -> u32 {
+    dotenv().ok();
+    match std_env::var(env::TWO_FA_MAX_RESENDS_ENV_VAR) {
+        Ok(resends) if !resends.is_empty() => resends
+            .parse()
+            .expect("TWO_FA_MAX_RESENDS must be a non-negative integer."),
+        _ => DEFAULT_TWO_FA_MAX_RESENDS,
+    }
+}
+
+/// Domain passkeys are bound to. Must be the host of `WEBAUTHN_ORIGIN` or
+/// a parent domain of it.
+fn set_webauthn_rp_id() -> String {
+    dotenv().ok();
+    std_env::var(env::WEBAUTHN_RP_ID_ENV_VAR)
+        .ok()
+        .filter(|rp_id| !rp_id.is_empty())
+        .unwrap_or(DEFAULT_WEBAUTHN_RP_ID.to_owned())
+}
+
+/// Origin of the login page, which browsers report in every ceremony.
+fn set_webauthn_origin() -> String {
+    dotenv().ok();
+    std_env::var(env::WEBAUTHN_ORIGIN_ENV_VAR)
+        .ok()
+        .filter(|origin| !origin.is_empty())
+        .unwrap_or(DEFAULT_WEBAUTHN_ORIGIN.to_owned())
+}
+
+/// Base URL of the SMS provider's API. Without one, texts are only logged.
+fn set_sms_provider_url() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::SMS_PROVIDER_URL_ENV_VAR)
+        .ok()
+        .filter(|url| !url.is_empty())
+}
+
+fn set_sms_provider_auth_token() -> String {
+    dotenv().ok();
+    let token = std_env::var(env::SMS_PROVIDER_AUTH_TOKEN_ENV_VAR)
+        .expect("SMS_PROVIDER_AUTH_TOKEN must be set when SMS_PROVIDER_URL is.");
+
+    if token.is_empty() {
+        panic!("SMS_PROVIDER_AUTH_TOKEN must not be empty.");
+    }
+    token
+}
+
+/// Base URL users reach the service at, for links in the emails we send.
+fn set_public_url() -> String {
+    dotenv().ok();
+    std_env::var(env::PUBLIC_URL_ENV_VAR)
+        .ok()
+        .filter(|url| !url.is_empty())
+        .map(|url| url.trim_end_matches('/').to_owned())
+        .unwrap_or(DEFAULT_PUBLIC_URL.to_owned())
+}
+
+/// Number or alphanumeric sender id texts are sent from.
+fn set_sms_sender() -> String {
+    dotenv().ok();
+    std_env::var(env::SMS_SENDER_ENV_VAR)
+        .ok()
+        .filter(|sender| !sender.is_empty())
+        .unwrap_or(DEFAULT_SMS_SENDER.to_owned())
+}
+
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_ALGORITHM_ENV_VAR: &str = "JWT_ALGORITHM";
+    pub const JWT_PRIVATE_KEY_PATH_ENV_VAR: &str = "JWT_PRIVATE_KEY_PATH";
+    pub const JWT_KEYS_PATH_ENV_VAR: &str = "JWT_KEYS_PATH";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCE_ENV_VAR: &str = "JWT_AUDIENCE";
+    pub const OIDC_ENABLED_ENV_VAR: &str = "OIDC_ENABLED";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
+    pub const TOTP_ISSUER_ENV_VAR: &str = "TOTP_ISSUER";
+    pub const TOTP_DRIFT_STEPS_ENV_VAR: &str = "TOTP_DRIFT_STEPS";
+    pub const TWO_FA_MAX_ATTEMPTS_ENV_VAR: &str = "TWO_FA_MAX_ATTEMPTS";
+    pub const TWO_FA_RESEND_COOLDOWN_SECONDS_ENV_VAR: &str = "TWO_FA_RESEND_COOLDOWN_SECONDS";
+    pub const TWO_FA_MAX_RESENDS_ENV_VAR: &str = "TWO_FA_MAX_RESENDS";
+    pub const WEBAUTHN_RP_ID_ENV_VAR: &str = "WEBAUTHN_RP_ID";
+    pub const WEBAUTHN_ORIGIN_ENV_VAR: &str = "WEBAUTHN_ORIGIN";
+    pub const SMS_PROVIDER_URL_ENV_VAR: &str = "SMS_PROVIDER_URL";
+    pub const SMS_PROVIDER_AUTH_TOKEN_ENV_VAR: &str = "SMS_PROVIDER_AUTH_TOKEN";
+    pub const SMS_SENDER_ENV_VAR: &str = "SMS_SENDER";
+    pub const PUBLIC_URL_ENV_VAR: &str = "PUBLIC_URL";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+pub const PRE_AUTH_COOKIE_NAME: &str = "pre_auth";
+pub const TRUSTED_DEVICE_COOKIE_NAME: &str = "trusted_device";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const DEFAULT_JWT_ALGORITHM: &str = "HS256";
+pub const DEFAULT_JWT_ISSUER: &str = "auth-service";
+pub const DEFAULT_JWT_AUDIENCE: &str = "app-service";
+pub const DEFAULT_TOTP_ISSUER: &str = "auth-service";
+pub const DEFAULT_TOTP_DRIFT_STEPS: u64 = 1;
+pub const DEFAULT_TWO_FA_MAX_ATTEMPTS: u32 = 5;
+pub const DEFAULT_TWO_FA_RESEND_COOLDOWN_SECONDS: u64 = 30;
+pub const DEFAULT_TWO_FA_MAX_RESENDS: u32 = 3;
+pub const DEFAULT_WEBAUTHN_RP_ID: &str = "localhost";
+pub const DEFAULT_WEBAUTHN_ORIGIN: &str = "http://localhost:3000";
+pub const DEFAULT_SMS_SENDER: &str = "auth-service";
+pub const SMS_PROVIDER_TIMEOUT_MILLISECONDS: u64 = 10_000;
+pub const DEFAULT_PUBLIC_URL: &str = "http://localhost:3000";
 
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
@@ -1,2 +1,9 @@
 pub mod auth;
+pub mod authenticated_client;
+pub mod authenticated_user;
+pub mod client_info;
 pub mod constants;
+pub mod jwt_key;
+pub mod oidc;
+pub mod totp;
+pub mod webauthn;
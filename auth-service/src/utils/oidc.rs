@@ -0,0 +1,129 @@
+use jsonwebtoken::Algorithm;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::domain::Email;
+
+use super::{
+    auth::{current_timestamp, expiry_timestamp, GenerateTokenError, TOKEN_TTL_SECONDS, KEY_RING},
+    constants::JWT_ISSUER,
+};
+
+pub const OPENID_SCOPE: &str = "openid";
+pub const EMAIL_SCOPE: &str = "email";
+/// Accepted so standard clients can ask for it, but we hold no profile data
+/// beyond the email address yet.
+pub const PROFILE_SCOPE: &str = "profile";
+pub const SUPPORTED_SCOPES: [&str; 3] = [OPENID_SCOPE, EMAIL_SCOPE, PROFILE_SCOPE];
+
+/// Whether the space-separated `scope` contains `wanted`.
+pub fn has_scope(scope: Option<&str>, wanted: &str) -> bool {
+    scope.is_some_and(|scope| scope.split_whitespace().any(|s| s == wanted))
+}
+
+pub fn is_supported_scope(scope: &str) -> bool {
+    scope
+        .split_whitespace()
+        .all(|s| SUPPORTED_SCOPES.contains(&s))
+}
+
+/// Claims of an OpenID Connect ID token. Its audience is the client, so it
+/// is never accepted as an access token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+pub fn generate_id_token(
+    email: &Email,
+    client_id: &str,
+    nonce: Option<&str>,
+    scope: Option<&str>,
+) -> Result<String, GenerateTokenError> {
+    let claims = IdTokenClaims {
+        iss: JWT_ISSUER.to_owned(),
+        sub: email.as_ref().to_owned(),
+        aud: client_id.to_owned(),
+        exp: expiry_timestamp(TOKEN_TTL_SECONDS)?,
+        iat: current_timestamp()?,
+        nonce: nonce.map(str::to_owned),
+        email: has_scope(scope, EMAIL_SCOPE).then(|| email.as_ref().to_owned()),
+    };
+
+    KEY_RING
+        .encode(&claims)
+        .map_err(GenerateTokenError::TokenError)
+}
+
+/// Relying parties verify ID tokens against our published keys and our
+/// issuer URL, so an OpenID provider must sign with an asymmetric key and
+/// be reachable at an `https` issuer.
+pub fn check_oidc_config(algorithm: Algorithm, issuer: &str) -> Result<(), String> {
+    if matches!(
+        algorithm,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+        return Err(
+            "OIDC_ENABLED requires an asymmetric JWT_ALGORITHM such as RS256 or EdDSA.".to_owned(),
+        );
+    }
+
+    match Url::parse(issuer) {
+        Ok(url)
+            if url.scheme() == "https"
+                && url.host().is_some()
+                && url.query().is_none()
+                && url.fragment().is_none() => {}
+        _ => {
+            return Err(
+                "OIDC_ENABLED requires JWT_ISSUER to be an https URL without query or fragment."
+                    .to_owned(),
+            )
+        }
+    }
+
+    Ok(())
+}
+
+/// Absolute URL of one of our endpoints. OpenID Connect requires the issuer
+/// to be our public base URL, so that is what `JWT_ISSUER` must be set to.
+pub fn endpoint_url(path: &str) -> String {
+    format!("{}{}", JWT_ISSUER.trim_end_matches('/'), path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_scope() {
+        assert!(has_scope(Some("openid email"), "email"));
+        assert!(!has_scope(Some("openid emails"), "email"));
+        assert!(!has_scope(None, "openid"));
+    }
+
+    #[test]
+    fn test_check_oidc_config() {
+        assert!(check_oidc_config(Algorithm::RS256, "https://auth.example.com").is_ok());
+        assert!(check_oidc_config(Algorithm::EdDSA, "https://auth.example.com/").is_ok());
+        assert!(check_oidc_config(Algorithm::HS256, "https://auth.example.com").is_err());
+        assert!(check_oidc_config(Algorithm::RS256, "auth-service").is_err());
+        assert!(check_oidc_config(Algorithm::RS256, "http://auth.example.com").is_err());
+        assert!(check_oidc_config(Algorithm::RS256, "https://auth.example.com?a=b").is_err());
+    }
+
+    #[test]
+    fn test_is_supported_scope() {
+        assert!(is_supported_scope("openid email profile"));
+        assert!(is_supported_scope(""));
+        assert!(!is_supported_scope("openid admin"));
+    }
+}
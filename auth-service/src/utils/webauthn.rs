@@ -0,0 +1,311 @@
+//! The parts of WebAuthn Level 2 that passkey login needs: ES256
+//! credentials and no attestation. We trust the key the authenticator hands
+//! us at registration, as browsers do for passkeys.
+
+use ciborium::Value;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::domain::PasskeyChallenge;
+
+use super::constants::{WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID};
+
+/// COSE algorithm identifier for ECDSA with P-256 and SHA-256.
+pub const ES256: i64 = -7;
+
+pub const CREATE_CEREMONY: &str = "webauthn.create";
+pub const GET_CEREMONY: &str = "webauthn.get";
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// rpIdHash, flags and signCount
+const AUTHENTICATOR_DATA_HEADER_LENGTH: usize = 37;
+const AAGUID_LENGTH: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub enum WebAuthnError {
+    MalformedResponse,
+    WrongCeremony,
+    WrongOrigin,
+    WrongRelyingParty,
+    UserNotPresent,
+    UserNotVerified,
+    UnsupportedKey,
+    InvalidSignature,
+}
+
+#[derive(Deserialize)]
+struct CollectedClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+}
+
+/// Checks the browser ran `ceremony` on our origin and returns the
+/// challenge it answered.
+pub fn parse_client_data(
+    client_data_json: &[u8],
+    ceremony: &str,
+) -> Result<PasskeyChallenge, WebAuthnError> {
+    let client_data: CollectedClientData =
+        serde_json::from_slice(client_data_json).map_err(|_| WebAuthnError::MalformedResponse)?;
+
+    if client_data.ceremony != ceremony {
+        return Err(WebAuthnError::WrongCeremony);
+    }
+    if client_data.origin != *WEBAUTHN_ORIGIN {
+        return Err(WebAuthnError::WrongOrigin);
+    }
+
+    PasskeyChallenge::parse(client_data.challenge).map_err(|_| WebAuthnError::MalformedResponse)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AuthenticatorData {
+    pub user_verified: bool,
+    pub sign_count: u32,
+    pub credential: Option<AttestedCredential>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AttestedCredential {
+    pub credential_id: Vec<u8>,
+    /// Uncompressed SEC1 P-256 point.
+    pub public_key: Vec<u8>,
+}
+
+/// Parses authenticator data for our relying party. The user must have
+/// been present; callers decide whether they also need them verified.
+pub fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData, WebAuthnError> {
+    if data.len() < AUTHENTICATOR_DATA_HEADER_LENGTH {
+        return Err(WebAuthnError::MalformedResponse);
+    }
+    let (header, rest) = data.split_at(AUTHENTICATOR_DATA_HEADER_LENGTH);
+
+    if header[..32] != Sha256::digest(WEBAUTHN_RP_ID.as_bytes())[..] {
+        return Err(WebAuthnError::WrongRelyingParty);
+    }
+
+    let flags = header[32];
+    if flags & FLAG_USER_PRESENT == 0 {
+        return Err(WebAuthnError::UserNotPresent);
+    }
+    let sign_count = u32::from_be_bytes([header[33], header[34], header[35], header[36]]);
+
+    let credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+        Some(parse_attested_credential(rest)?)
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        user_verified: flags & FLAG_USER_VERIFIED != 0,
+        sign_count,
+        credential,
+    })
+}
+
+/// Extracts the authenticator data from an attestation object. The
+/// attestation statement itself is ignored.
+pub fn parse_attestation_object(data: &[u8]) -> Result<AuthenticatorData, WebAuthnError> {
+    let object: Value =
+        ciborium::from_reader(data).map_err(|_| WebAuthnError::MalformedResponse)?;
+
+    let authenticator_data = object
+        .as_map()
+        .and_then(|entries| {
+            entries
+                .iter()
+                .find(|(key, _)| key.as_text() == Some("authData"))
+        })
+        .and_then(|(_, value)| value.as_bytes())
+        .ok_or(WebAuthnError::MalformedResponse)?;
+
+    parse_authenticator_data(authenticator_data)
+}
+
+fn parse_attested_credential(data: &[u8]) -> Result<AttestedCredential, WebAuthnError> {
+    if data.len() < AAGUID_LENGTH + 2 {
+        return Err(WebAuthnError::MalformedResponse);
+    }
+    let data = &data[AAGUID_LENGTH..];
+
+    let id_length = u16::from_be_bytes([data[0], data[1]]) as usize;
+    let data = &data[2..];
+    if data.len() < id_length {
+        return Err(WebAuthnError::MalformedResponse);
+    }
+    let (credential_id, mut cose_key) = data.split_at(id_length);
+
+    // Extensions may follow the key; reading from the slice stops after it
+    let cose_key: Value =
+        ciborium::from_reader(&mut cose_key).map_err(|_| WebAuthnError::MalformedResponse)?;
+
+    Ok(AttestedCredential {
+        credential_id: credential_id.to_vec(),
+        public_key: parse_cose_key(&cose_key)?,
+    })
+}
+
+/// Converts a COSE EC2 key to SEC1, accepting only ES256 on P-256.
+fn parse_cose_key(key: &Value) -> Result<Vec<u8>, WebAuthnError> {
+    let entries = key.as_map().ok_or(WebAuthnError::MalformedResponse)?;
+    let get = |label: i64| {
+        entries
+            .iter()
+            .find(|(key, _)| key.as_integer() == Some(label.into()))
+            .map(|(_, value)| value)
+    };
+    let integer = |label: i64| get(label).and_then(Value::as_integer).map(i128::from);
+    let coordinate = |label: i64| {
+        get(label)
+            .and_then(Value::as_bytes)
+            .filter(|bytes| bytes.len() == 32)
+    };
+
+    // kty EC2, alg ES256, crv P-256
+    if integer(1) != Some(2) || integer(3) != Some(ES256.into()) || integer(-1) != Some(1) {
+        return Err(WebAuthnError::UnsupportedKey);
+    }
+    let (x, y) = coordinate(-2)
+        .zip(coordinate(-3))
+        .ok_or(WebAuthnError::UnsupportedKey)?;
+
+    let mut public_key = Vec::with_capacity(65);
+    public_key.push(0x04);
+    public_key.extend_from_slice(x);
+    public_key.extend_from_slice(y);
+
+    // Reject points that aren't on the curve now rather than at login
+    VerifyingKey::from_sec1_bytes(&public_key).map_err(|_| WebAuthnError::UnsupportedKey)?;
+
+    Ok(public_key)
+}
+
+/// Authenticators sign their data followed by the SHA-256 of the client
+/// data, with a DER-encoded ECDSA signature.
+pub fn verify_signature(
+    public_key: &[u8],
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+) -> Result<(), WebAuthnError> {
+    let verifying_key =
+        VerifyingKey::from_sec1_bytes(public_key).map_err(|_| WebAuthnError::UnsupportedKey)?;
+    let signature = Signature::from_der(signature).map_err(|_| WebAuthnError::InvalidSignature)?;
+    // Authenticators aren't required to produce low-S signatures
+    let signature = signature.normalize_s().unwrap_or(signature);
+
+    let mut signed_data = authenticator_data.to_vec();
+    signed_data.extend_from_slice(&Sha256::digest(client_data_json));
+
+    verifying_key
+        .verify(&signed_data, &signature)
+        .map_err(|_| WebAuthnError::InvalidSignature)
+}
+
+#[cfg(test)]
+mod tests {
+    use p256::ecdsa::{signature::Signer, SigningKey};
+
+    use super::*;
+
+    fn authenticator_data(rp_id: &str, flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_parse_authenticator_data() {
+        let data = authenticator_data(&WEBAUTHN_RP_ID, FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 7);
+
+        assert_eq!(
+            parse_authenticator_data(&data),
+            Ok(AuthenticatorData {
+                user_verified: true,
+                sign_count: 7,
+                credential: None,
+            })
+        );
+        assert_eq!(
+            parse_authenticator_data(&data[..36]),
+            Err(WebAuthnError::MalformedResponse)
+        );
+    }
+
+    #[test]
+    fn test_parse_authenticator_data_checks_relying_party_and_presence() {
+        let data = authenticator_data("evil.example.com", FLAG_USER_PRESENT, 0);
+        assert_eq!(
+            parse_authenticator_data(&data),
+            Err(WebAuthnError::WrongRelyingParty)
+        );
+
+        let data = authenticator_data(&WEBAUTHN_RP_ID, 0, 0);
+        assert_eq!(
+            parse_authenticator_data(&data),
+            Err(WebAuthnError::UserNotPresent)
+        );
+    }
+
+    #[test]
+    fn test_parse_cose_key_only_accepts_es256() {
+        let point = SigningKey::random(&mut rand::thread_rng())
+            .verifying_key()
+            .to_encoded_point(false);
+        let key = |alg: i64| {
+            Value::Map(vec![
+                (Value::from(1), Value::from(2)),
+                (Value::from(3), Value::from(alg)),
+                (Value::from(-1), Value::from(1)),
+                (Value::from(-2), Value::from(point.x().unwrap().to_vec())),
+                (Value::from(-3), Value::from(point.y().unwrap().to_vec())),
+            ])
+        };
+
+        assert_eq!(parse_cose_key(&key(ES256)), Ok(point.as_bytes().to_vec()));
+        // RS256
+        assert_eq!(
+            parse_cose_key(&key(-257)),
+            Err(WebAuthnError::UnsupportedKey)
+        );
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let signing_key = SigningKey::random(&mut rand::thread_rng());
+        let public_key = signing_key.verifying_key().to_encoded_point(false);
+        let data = authenticator_data(&WEBAUTHN_RP_ID, FLAG_USER_PRESENT, 1);
+        let client_data_json = br#"{"type":"webauthn.get"}"#;
+
+        let mut signed_data = data.clone();
+        signed_data.extend_from_slice(&Sha256::digest(client_data_json));
+        let signature: Signature = signing_key.sign(&signed_data);
+        let signature = signature.to_der();
+
+        assert_eq!(
+            verify_signature(
+                public_key.as_bytes(),
+                &data,
+                client_data_json,
+                signature.as_bytes()
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            verify_signature(
+                public_key.as_bytes(),
+                &data,
+                br#"{"type":"webauthn.create"}"#,
+                signature.as_bytes()
+            ),
+            Err(WebAuthnError::InvalidSignature)
+        );
+    }
+}
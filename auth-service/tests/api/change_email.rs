@@ -0,0 +1,221 @@
+use auth_service::routes::TwoFactorAuthResponse;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = login(app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+}
+
+async fn request_change(app: &TestApp, new_email: &str) -> String {
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "password": "password123",
+            "newEmail": new_email,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.email_change_token(new_email)
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_authenticated() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "password": "password123",
+            "newEmail": get_random_email(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_incorrect() {
+    let app = TestApp::new().await;
+    signup_and_login(&app).await;
+    let new_email = get_random_email();
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "password": "wrong password",
+            "newEmail": new_email,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(app
+        .email_client
+        .last_email_to(&new_email, "Confirm your new email address")
+        .is_none());
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_409_if_new_email_taken() {
+    let app = TestApp::new().await;
+    let taken_email = signup_and_login(&app).await;
+    signup_and_login(&app).await;
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "password": "password123",
+            "newEmail": taken_email,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_change_email_once_confirmed() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+    let new_email = get_random_email();
+
+    let token = request_change(&app, &new_email).await;
+    assert!(app
+        .email_client
+        .last_email_to(&email, "Email change requested")
+        .is_some());
+
+    // Nothing changes until the new address is confirmed
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = login(&app, &new_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.get_confirm_email_change(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(app
+        .email_client
+        .last_email_to(&email, "Email address changed")
+        .is_some());
+
+    // Sessions under the old address are logged out
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = login(&app, &new_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Each link works only once
+    let response = app.get_confirm_email_change(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_only_accept_latest_link() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+    let first_email = get_random_email();
+    let second_email = get_random_email();
+
+    let first_token = request_change(&app, &first_email).await;
+    let second_token = request_change(&app, &second_email).await;
+
+    let response = app.get_confirm_email_change(&first_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_confirm_email_change(&second_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = login(&app, &second_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_cancel_logins_in_progress_for_old_address() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+    let new_email = get_random_email();
+
+    let response = app
+        .post_2fa_enable(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = request_change(&app, &new_email).await;
+
+    // Someone gets past the password step for the old address
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let code = app
+        .email_client
+        .last_email_to(&email, "2FA required")
+        .expect("No 2FA code sent");
+
+    let response = app.get_confirm_email_change(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+            "rememberDevice": true,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_change_email_if_notification_fails() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+    let new_email = get_random_email();
+
+    let token = request_change(&app, &new_email).await;
+
+    app.email_client.set_failing(true);
+    let response = app.get_confirm_email_change(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.email_client.set_failing(false);
+
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = login(&app, &new_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
@@ -0,0 +1,188 @@
+use auth_service::{routes::TokenResponse, utils::constants::JWT_COOKIE_NAME};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    email
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+}
+
+/// Logs in and returns the auth token, as another device would hold it.
+/// The cookie jar ends up with this session until the next login.
+async fn login_on_other_device(app: &TestApp, email: &str) -> String {
+    let response = login(app, email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    auth_token
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_authenticated() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_current_password_incorrect() {
+    let app = TestApp::new().await;
+    let email = signup(&app).await;
+    login_on_other_device(&app, &email).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "wrong password",
+            "newPassword": "new password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_change_password_and_keep_sessions() {
+    let app = TestApp::new().await;
+    let email = signup(&app).await;
+    let other_device_token = login_on_other_device(&app, &email).await;
+    login_on_other_device(&app, &email).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_sessions_with_bearer(&other_device_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(app
+        .email_client
+        .last_email_to(&email, "Password changed")
+        .is_some());
+
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = login(&app, &email, "new password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_revoke_other_sessions_if_asked() {
+    let app = TestApp::new().await;
+    let email = signup(&app).await;
+    let other_device_token = login_on_other_device(&app, &email).await;
+    login_on_other_device(&app, &email).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new password123",
+            "revokeOtherSessions": true,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_sessions_with_bearer(&other_device_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The session that made the change stays logged in with its new tokens
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_new_tokens_if_asked() {
+    let app = TestApp::new().await;
+    let email = signup(&app).await;
+    let old_token = login_on_other_device(&app, &email).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new password123",
+            "revokeOtherSessions": true,
+            "returnToken": true,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+
+    let response = app.get_sessions_with_bearer(&old_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.get_sessions_with_bearer(&tokens.token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_refresh_with_token(&tokens.refresh_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_change_password_if_notification_fails() {
+    let app = TestApp::new().await;
+    let email = signup(&app).await;
+    login_on_other_device(&app, &email).await;
+
+    app.email_client.set_failing(true);
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.email_client.set_failing(false);
+
+    let response = login(&app, &email, "new password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
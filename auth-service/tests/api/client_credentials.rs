@@ -0,0 +1,160 @@
+use auth_service::{
+    routes::{AccessTokenResponse, IntrospectionResponse},
+    ErrorResponse,
+};
+
+use crate::helpers::TestApp;
+
+async fn request_token(
+    app: &TestApp,
+    client_id: &str,
+    client_secret: &str,
+    scope: Option<&str>,
+) -> reqwest::Response {
+    let mut form = vec![("grant_type", "client_credentials")];
+    if let Some(scope) = scope {
+        form.push(("scope", scope));
+    }
+    app.post_token(&form, Some((client_id, client_secret))).await
+}
+
+#[tokio::test]
+async fn should_issue_token_with_all_client_scopes_by_default() {
+    let app = TestApp::new().await;
+
+    let (client_id, client_secret) = app.register_client().await;
+
+    let response = request_token(&app, &client_id, &client_secret, None).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<AccessTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to AccessTokenResponse");
+    assert_eq!(body.scope.as_deref(), Some("reports:read reports:write"));
+    assert!(body.id_token.is_none());
+
+    // Accepted wherever user tokens are, including by app-service
+    let response = app
+        .post_verify_token(&serde_json::json!({
+            "token": body.access_token,
+            "audience": "app-service",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let introspection = app
+        .post_introspect(&client_id, &client_secret, &body.access_token)
+        .await
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert!(introspection.active);
+    assert_eq!(introspection.sub, Some(client_id.clone()));
+    assert_eq!(introspection.client_id, Some(client_id));
+    assert!(introspection.sid.is_none());
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_issue_token_with_requested_scopes() {
+    let app = TestApp::new().await;
+
+    let (client_id, client_secret) = app.register_client().await;
+
+    let body = request_token(&app, &client_id, &client_secret, Some("reports:read"))
+        .await
+        .json::<AccessTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to AccessTokenResponse");
+    assert_eq!(body.scope.as_deref(), Some("reports:read"));
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_scope_not_registered() {
+    let app = TestApp::new().await;
+
+    let (client_id, client_secret) = app.register_client().await;
+
+    let response = request_token(
+        &app,
+        &client_id,
+        &client_secret,
+        Some("reports:read admin"),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "invalid_scope".to_owned()
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_client_secret_wrong() {
+    let app = TestApp::new().await;
+
+    let (client_id, _) = app.register_client().await;
+
+    let response = request_token(&app, &client_id, &"x".repeat(64), None).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_400_for_public_clients() {
+    let app = TestApp::new().await;
+
+    let client_id = app.register_public_client().await;
+
+    let response = app
+        .post_token(
+            &[("grant_type", "client_credentials"), ("client_id", &client_id)],
+            None,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "unauthorized_client".to_owned()
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_not_accept_client_token_on_user_routes() {
+    let app = TestApp::new().await;
+
+    let (client_id, client_secret) = app.register_client().await;
+
+    let token = request_token(&app, &client_id, &client_secret, None)
+        .await
+        .json::<AccessTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to AccessTokenResponse")
+        .access_token;
+
+    let response = app.get_sessions_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Nor is there a user behind it to describe
+    let response = app.get_userinfo(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
@@ -1,27 +1,74 @@
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, EmailClientType, TwoFACodeStoreType},
+    app_state::{
+        AppState, BannedTokenStoreType, ClientStoreType, EmailClientType, SmsClientType,
+        TwoFACodeStoreType,
+    },
+    domain::{Client, ClientSecret, Email, EmailClient, PhoneNumber, SmsClient},
     get_postgres_pool, get_redis_client,
     services::{
-        hashset_banned_token_store::HashsetBannedTokenStore, mock_email_client::MockEmailClient,
-        postgres_user_store::PostgresUserStore,
+        hashmap_authorization_code_store::HashmapAuthorizationCodeStore,
+        hashmap_email_token_store::HashmapEmailTokenStore,
+        hashmap_passkey_challenge_store::HashmapPasskeyChallengeStore,
+        hashmap_refresh_token_store::HashmapRefreshTokenStore,
+        hashmap_session_epoch_store::HashmapSessionEpochStore,
+        hashset_banned_token_store::HashsetBannedTokenStore,
+        postgres_client_store::PostgresClientStore, postgres_passkey_store::PostgresPasskeyStore, postgres_session_store::PostgresSessionStore, postgres_totp_store::PostgresTotpStore, postgres_trusted_device_store::PostgresTrustedDeviceStore, postgres_user_store::PostgresUserStore,
         redis_two_fa_code_store::RedisTwoFACodeStore,
     },
-    utils::constants::{test, DATABASE_URL, REDIS_HOST_NAME},
+    utils::constants::{env, test, DATABASE_URL, REDIS_HOST_NAME},
     Application,
 };
 use reqwest::cookie::Jar;
 use sqlx::Connection;
 use sqlx::{postgres::PgPoolOptions, Executor, PgConnection, PgPool};
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, Once,
+};
 use tokio::sync::RwLock;
 use uuid::Uuid;
+pub const TEST_REDIRECT_URI: &str = "https://client.example.com/callback";
+pub const TEST_ISSUER: &str = "https://auth.example.com";
+pub const TEST_RESEND_COOLDOWN_SECONDS: u64 = 1;
+
+static CONFIGURE_ENV: Once = Once::new();
+
+/// Tests run as an OpenID provider, which must sign with a published key
+/// under an https issuer. Set before anything reads the configuration.
+fn configure_env() {
+    CONFIGURE_ENV.call_once(|| {
+        std::env::set_var(env::OIDC_ENABLED_ENV_VAR, "true");
+        std::env::set_var(env::JWT_ALGORITHM_ENV_VAR, "RS256");
+        std::env::set_var(
+            env::JWT_PRIVATE_KEY_PATH_ENV_VAR,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/jwt_private_key.pem"),
+        );
+        std::env::set_var(env::JWT_ISSUER_ENV_VAR, TEST_ISSUER);
+        // Short enough for tests to wait out
+        std::env::set_var(
+            env::TWO_FA_RESEND_COOLDOWN_SECONDS_ENV_VAR,
+            TEST_RESEND_COOLDOWN_SECONDS.to_string(),
+        );
+    });
+}
+
+/// Waits until a 2FA code sent just now may be resent.
+pub async fn wait_for_resend_cooldown() {
+    tokio::time::sleep(std::time::Duration::from_millis(
+        TEST_RESEND_COOLDOWN_SECONDS * 1000 + 100,
+    ))
+    .await;
+}
+
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
     pub http_client: reqwest::Client,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
-    pub email_client: EmailClientType,
+    pub client_store: ClientStoreType,
+    pub email_client: Arc<RecordingEmailClient>,
+    pub sms_client: Arc<RecordingSmsClient>,
     pub db_name: String,
     pub clean_up_called: bool,
 }
@@ -36,19 +83,42 @@ impl Drop for TestApp {
 
 impl TestApp {
     pub async fn new() -> Self {
+        configure_env();
 
         let pg_pool = configure_postgresql().await;
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.1)));
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.1.clone())));
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
         let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(Arc::new(RwLock::new(configure_redis())))));
-        let email_client: EmailClientType = Arc::new(MockEmailClient {});
+        let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let session_epoch_store = Arc::new(RwLock::new(HashmapSessionEpochStore::default()));
+        let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.1.clone())));
+        let client_store = Arc::new(RwLock::new(PostgresClientStore::new(pg_pool.1.clone())));
+        let totp_store = Arc::new(RwLock::new(PostgresTotpStore::new(pg_pool.1.clone())));
+        let passkey_store = Arc::new(RwLock::new(PostgresPasskeyStore::new(pg_pool.1.clone())));
+        let trusted_device_store = Arc::new(RwLock::new(PostgresTrustedDeviceStore::new(pg_pool.1)));
+        let passkey_challenge_store = Arc::new(RwLock::new(HashmapPasskeyChallengeStore::default()));
+        let authorization_code_store = Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default()));
+        let email_token_store = Arc::new(RwLock::new(HashmapEmailTokenStore::default()));
+        let email_client = Arc::new(RecordingEmailClient::default());
+        let sms_client = Arc::new(RecordingSmsClient::default());
 
         let app_state = AppState::new(
             user_store,
             banned_token_store.clone(),
             two_fa_code_store.clone(),
-            email_client.clone(),
+            refresh_token_store,
+            session_epoch_store,
+            session_store,
+            client_store.clone(),
+            authorization_code_store,
+            totp_store,
+            passkey_store,
+            passkey_challenge_store,
+            trusted_device_store,
+            email_token_store,
+            email_client.clone() as EmailClientType,
+            sms_client.clone() as SmsClientType,
         );
 
         let app = Application::build(app_state, test::APP_ADDRESS)
@@ -74,12 +144,15 @@ impl TestApp {
             http_client,
             banned_token_store,
             two_fa_code_store,
+            client_store,
             email_client,
+            sms_client,
             db_name,
             clean_up_called: false,
         }
     }
 
+    #[allow(clippy::needless_borrows_for_generic_args)]
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
             .get(&format!("{}/", &self.address))
@@ -88,6 +161,32 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_openid_configuration(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/openid-configuration", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_userinfo(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/userinfo", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    #[allow(clippy::needless_borrows_for_generic_args)]
     pub async fn post_verify_token<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -100,7 +199,120 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_introspect(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        token: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/introspect", &self.address))
+            .basic_auth(client_id, Some(client_secret))
+            .form(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Registers a confidential client, allowed to call the back-channel
+    /// endpoints, and returns its client id and secret.
+    pub async fn register_client(&self) -> (String, String) {
+        let client_secret = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let client_id = self
+            .add_client(
+                Some(ClientSecret::parse(client_secret.clone()).unwrap()),
+                true,
+            )
+            .await;
+
+        (client_id, client_secret)
+    }
+
+    /// Registers a public client, such as a single-page app, and returns its
+    /// client id.
+    pub async fn register_public_client(&self) -> String {
+        self.add_client(None, true).await
+    }
+
+    /// Registers a confidential client that is not one of our own apps and
+    /// returns its client id.
+    pub async fn register_third_party_client(&self) -> String {
+        let client_secret = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        self.add_client(Some(ClientSecret::parse(client_secret).unwrap()), false)
+            .await
+    }
+
+    async fn add_client(&self, secret: Option<ClientSecret>, first_party: bool) -> String {
+        let client_id = format!("client-{}", Uuid::new_v4());
+
+        self.client_store
+            .write()
+            .await
+            .add_client(
+                Client {
+                    id: client_id.clone(),
+                    name: "Test client".to_owned(),
+                    redirect_uris: vec![TEST_REDIRECT_URI.to_owned()],
+                    scopes: vec!["reports:read".to_owned(), "reports:write".to_owned()],
+                    first_party,
+                },
+                secret,
+            )
+            .await
+            .expect("Failed to register client");
+
+        client_id
+    }
+
+    /// Sends the request with this app's cookies but without following the
+    /// redirect, so tests can inspect where it points.
+    pub async fn get_authorize(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        reqwest::Client::builder()
+            .cookie_provider(self.cookie_jar.clone())
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(format!("{}/authorize", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_token(
+        &self,
+        form: &[(&str, &str)],
+        client_credentials: Option<(&str, &str)>,
+    ) -> reqwest::Response {
+        let mut request = reqwest::Client::new()
+            .post(format!("{}/token", &self.address))
+            .form(form);
+        if let Some((client_id, client_secret)) = client_credentials {
+            request = request.basic_auth(client_id, Some(client_secret));
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    /// Signs up and follows the emailed verification link, since almost
+    /// every test needs an account that can log in.
     pub async fn post_signup<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let response = self.post_signup_unverified(body).await;
+
+        if response.status().as_u16() == 201 {
+            let body = serde_json::to_value(body).unwrap();
+            let token = self.verification_token(body["email"].as_str().unwrap());
+            let verified = self.get_verify_email(&token).await;
+            assert_eq!(verified.status().as_u16(), 200);
+        }
+
+        response
+    }
+
+    #[allow(clippy::needless_borrows_for_generic_args)]
+    pub async fn post_signup_unverified<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
     {
@@ -112,6 +324,118 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    /// The token from the last verification link emailed to `email`.
+    pub fn verification_token(&self, email: &str) -> String {
+        self.token_in_email(email, "Verify your email address", "token")
+    }
+
+    /// The token from the last password reset link emailed to `email`.
+    pub fn password_reset_token(&self, email: &str) -> Option<String> {
+        self.email_client
+            .last_email_to(email, "Reset your password")
+            .map(|_| self.token_in_email(email, "Reset your password", "reset_token"))
+    }
+
+    /// The token from the last confirmation link emailed to a new address.
+    pub fn email_change_token(&self, new_email: &str) -> String {
+        self.token_in_email(new_email, "Confirm your new email address", "token")
+    }
+
+    fn token_in_email(&self, email: &str, subject: &str, parameter: &str) -> String {
+        let content = self
+            .email_client
+            .last_email_to(email, subject)
+            .unwrap_or_else(|| panic!("No \"{}\" email sent", subject));
+        let link = content
+            .split_whitespace()
+            .find(|word| word.contains(&format!("?{}=", parameter)))
+            .expect("No link in email");
+
+        link.split_once(&format!("?{}=", parameter))
+            .unwrap()
+            .1
+            .to_owned()
+    }
+
+    pub async fn post_forgot_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/forgot-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_confirm_email_change(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/change-email/confirm", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/reset-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/verify-email", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    #[allow(clippy::needless_borrows_for_generic_args)]
     pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -124,6 +448,7 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    #[allow(clippy::needless_borrows_for_generic_args)]
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
             .post(&format!("{}/logout", &self.address))
@@ -132,6 +457,101 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout-all", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_trusted_devices(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/trusted-devices", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_trusted_device(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/trusted-devices/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_with_bearer(&self, token: &str) -> reqwest::Response {
+        // A client without a cookie store, like our mobile and CLI clients
+        reqwest::Client::new()
+            .post(format!("{}/logout", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions_with_bearer(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/sessions", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_2fa_with_bearer<Body>(
+        &self,
+        body: &Body,
+        token: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        reqwest::Client::new()
+            .post(format!("{}/verify-2fa", &self.address))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Refreshes the way a client without cookies does.
+    pub async fn post_refresh_with_token(&self, refresh_token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/refresh", &self.address))
+            .json(&serde_json::json!({ "refreshToken": refresh_token }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    #[allow(dead_code, clippy::needless_borrows_for_generic_args)]
     pub async fn post_verify_2_factor(&self) -> reqwest::Response {
         self.http_client
             .post(&format!("{}/verify-2fa", &self.address))
@@ -152,6 +572,150 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_resend_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_2fa_enable<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/enable", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_2fa_disable<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/disable", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_2fa_method<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/method", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_phone_number<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/phone-number", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_phone_number<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/phone-number/verify", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_enroll(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/2fa/totp/enroll", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_recovery_codes<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/recovery-codes", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_registration_start(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/passkeys/register/start", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_registration_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/register/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_login_start(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/passkeys/login/start", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_login_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/login/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn cleanup(mut self) {
         delete_database(&self.db_name).await;
         self.clean_up_called = true;
@@ -165,6 +729,83 @@ fn configure_redis() -> redis::Connection {
         .expect("Failed to get Redis connection")
 }
 
+/// Keeps every email so tests can follow the links sent in them.
+#[derive(Default)]
+pub struct RecordingEmailClient {
+    emails: Mutex<Vec<(Email, String, String)>>,
+    failing: AtomicBool,
+}
+
+impl RecordingEmailClient {
+    /// Makes every send fail until called again with `false`.
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::SeqCst);
+    }
+
+    /// The content of the most recent email to `email` with `subject`.
+    pub fn last_email_to(&self, email: &str, subject: &str) -> Option<String> {
+        let email = Email::parse(email.to_owned()).ok()?;
+        self.emails
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(recipient, sent_subject, _)| *recipient == email && sent_subject == subject)
+            .map(|(_, _, content)| content.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for RecordingEmailClient {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        subject: &str,
+        content: &str,
+    ) -> Result<(), String> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err("Email client is failing".to_owned());
+        }
+        self.emails.lock().unwrap().push((
+            recipient.clone(),
+            subject.to_owned(),
+            content.to_owned(),
+        ));
+        Ok(())
+    }
+}
+
+/// Keeps every text message so tests can read the codes sent to phones.
+#[derive(Default)]
+pub struct RecordingSmsClient {
+    messages: Mutex<Vec<(PhoneNumber, String)>>,
+}
+
+impl RecordingSmsClient {
+    /// The most recent message texted to `phone_number`.
+    pub fn last_message_to(&self, phone_number: &str) -> Option<String> {
+        let phone_number = PhoneNumber::parse(phone_number.to_owned()).ok()?;
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(recipient, _)| *recipient == phone_number)
+            .map(|(_, content)| content.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for RecordingSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<(), String> {
+        self.messages
+            .lock()
+            .unwrap()
+            .push((recipient.clone(), content.to_owned()));
+        Ok(())
+    }
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
@@ -0,0 +1,147 @@
+use auth_service::{
+    routes::{IntrospectionResponse, TokenResponse},
+    ErrorResponse,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "returnToken": true,
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    (random_email, token)
+}
+
+#[tokio::test]
+async fn should_return_401_if_client_credentials_missing_or_wrong() {
+    let app = TestApp::new().await;
+
+    let (_, token) = signup_and_login(&app).await;
+    let (client_id, _) = app.register_client().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/introspect", &app.address))
+        .form(&[("token", token.as_str())])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .headers()
+            .get("www-authenticate")
+            .and_then(|value| value.to_str().ok()),
+        Some("Basic")
+    );
+
+    let wrong_secret = "x".repeat(64);
+    let response = app.post_introspect(&client_id, &wrong_secret, &token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid client".to_owned()
+    );
+
+    let response = app.post_introspect("unknown", &wrong_secret, &token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_describe_active_token() {
+    let app = TestApp::new().await;
+
+    let (email, token) = signup_and_login(&app).await;
+    let (client_id, client_secret) = app.register_client().await;
+
+    let response = app.post_introspect(&client_id, &client_secret, &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+
+    assert!(body.active);
+    assert_eq!(body.sub, Some(email));
+    assert_eq!(body.token_type.as_deref(), Some("Bearer"));
+    assert!(body.exp.unwrap() > body.iat.unwrap());
+
+    let sessions = app
+        .get_sessions_with_bearer(&token)
+        .await
+        .json::<Vec<serde_json::Value>>()
+        .await
+        .expect("Could not deserialize sessions");
+    assert_eq!(body.sid.as_deref(), sessions[0]["id"].as_str());
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_report_invalid_token_as_inactive() {
+    let app = TestApp::new().await;
+
+    let (client_id, client_secret) = app.register_client().await;
+
+    let response = app
+        .post_introspect(&client_id, &client_secret, "invalid")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .expect("Could not deserialize response body");
+    assert_eq!(body, serde_json::json!({ "active": false }));
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_report_logged_out_token_as_inactive() {
+    let app = TestApp::new().await;
+
+    let (_, token) = signup_and_login(&app).await;
+    let (client_id, client_secret) = app.register_client().await;
+
+    let response = app.post_logout_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = app
+        .post_introspect(&client_id, &client_secret, &token)
+        .await
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert!(!body.active);
+    assert!(body.sub.is_none());
+
+    app.cleanup().await;
+}
@@ -0,0 +1,26 @@
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
+
+use crate::helpers::TestApp;
+
+#[tokio::test]
+async fn should_return_200_with_a_jwk_set() {
+    let app = TestApp::new().await;
+
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let jwks = response
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+
+    // Tests sign with an RSA key, so relying parties can verify ID tokens
+    assert_eq!(jwks.keys.len(), 1);
+    assert!(matches!(
+        jwks.keys[0].algorithm,
+        AlgorithmParameters::RSA(_)
+    ));
+
+    app.cleanup().await;
+}
@@ -1,8 +1,8 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_service::{
-    domain::{Email, TwoFACodeStore},
-    routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    domain::LoginAttemptId,
+    routes::{TokenResponse, TwoFactorAuthResponse},
+    utils::constants::{JWT_COOKIE_NAME, PRE_AUTH_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
     ErrorResponse,
 };
 
@@ -139,6 +139,42 @@ async fn should_return_401_if_incorrect_credentials() {
     app.cleanup().await;
 }
 
+#[tokio::test]
+async fn should_return_400_if_audience_unknown() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "audience": "another-app"
+    });
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(response.cookies().all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Unknown audience".to_owned()
+    );
+    app.cleanup().await;
+}
+
 #[tokio::test]
 async fn should_return_422_if_malformed_credentials() {
     let app = TestApp::new().await;
@@ -201,6 +237,21 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    // Only the password step is done, so no session may be issued yet
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME
+            && cookie.name() != REFRESH_TOKEN_COOKIE_NAME));
+
+    let pre_auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == PRE_AUTH_COOKIE_NAME)
+        .expect("No pre-auth cookie found");
+
+    assert!(!pre_auth_cookie.value().is_empty());
+
+    let pre_auth_token = pre_auth_cookie.value().to_owned();
+
     let json_body = response
         .json::<TwoFactorAuthResponse>()
         .await
@@ -211,9 +262,54 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     let (_, _two_fa_code) = {
         let store = app.two_fa_code_store.read().await;
         store
-            .get_code(&Email::parse(random_email.clone()).unwrap())
+            .get_code(&LoginAttemptId::parse(json_body.login_attempt_id.clone()).unwrap())
             .await
             .expect("2FA code not found")
     };
+
+    // The pre-auth token must not be accepted where an auth token is expected
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": pre_auth_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_token_in_body_if_requested() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
     app.cleanup().await;
 }
@@ -1,4 +1,4 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{routes::TokenResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse};
 use reqwest::Url;
 
 use crate::helpers::{get_random_email, TestApp};
@@ -179,3 +179,58 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
     );
     app.cleanup().await;
 }
+
+#[tokio::test]
+async fn should_return_200_if_valid_bearer_token() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app.post_logout_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_logout_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_authorization_header_is_not_bearer() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/logout", &app.address))
+        .basic_auth("user", Some("password"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 422);
+    app.cleanup().await;
+}
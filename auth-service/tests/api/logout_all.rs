@@ -0,0 +1,132 @@
+use auth_service::{
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+
+use crate::helpers::{get_random_email, TestApp};
+
+/// Logs in and returns the (auth token, refresh token) pair, as a separate
+/// device would hold it.
+async fn login(app: &TestApp, email: &str) -> (String, String) {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh token cookie found")
+        .value()
+        .to_owned();
+
+    (auth_token, refresh_token)
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let app = TestApp::new().await;
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_revoke_every_session_of_the_user() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let other_email = get_random_email();
+
+    for email in [&random_email, &other_email] {
+        let signup_body = serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        });
+        let response = app.post_signup(&signup_body).await;
+        assert_eq!(response.status().as_u16(), 201);
+    }
+
+    let (other_user_token, _) = login(&app, &other_email).await;
+    let (laptop_token, laptop_refresh_token) = login(&app, &random_email).await;
+    let (phone_token, _) = login(&app, &random_email).await;
+
+    // The phone's session is the one left in the cookie jar
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    for token in [&laptop_token, &phone_token] {
+        let response = app
+            .post_verify_token(&serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, laptop_refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Other users are unaffected
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": other_user_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Logging in again starts a fresh, valid session
+    let (new_token, _) = login(&app, &random_email).await;
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
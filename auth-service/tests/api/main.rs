@@ -1,7 +1,25 @@
+mod change_email;
+mod change_password;
+mod client_credentials;
 mod helpers;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod oauth;
+mod passkeys;
+mod phone_number;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
+mod reset_password;
 mod root;
+mod sessions;
 mod signup;
+mod totp;
+mod trusted_devices;
+mod two_fa_settings;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
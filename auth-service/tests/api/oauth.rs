@@ -0,0 +1,659 @@
+use auth_service::{
+    routes::{AccessTokenResponse, IntrospectionResponse, ProviderMetadata, UserInfoResponse},
+    utils::{auth::KEY_RING, constants::JWT_ISSUER, oidc::IdTokenClaims},
+    ErrorResponse,
+};
+use jsonwebtoken::Validation;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, TestApp, TEST_ISSUER, TEST_REDIRECT_URI};
+
+struct Pkce {
+    verifier: String,
+    challenge: String,
+}
+
+impl Pkce {
+    fn new() -> Self {
+        let verifier = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        Self {
+            verifier,
+            challenge,
+        }
+    }
+}
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    random_email
+}
+
+fn location(response: &reqwest::Response) -> String {
+    assert!(
+        response.status().is_redirection(),
+        "Expected a redirect, got {}",
+        response.status()
+    );
+    response
+        .headers()
+        .get("location")
+        .expect("No location header")
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+fn query_param(url: &str, name: &str) -> Option<String> {
+    Url::parse(url)
+        .expect("Failed to parse redirect URL")
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+async fn authorize(app: &TestApp, client_id: &str, pkce: &Pkce) -> String {
+    authorize_with(app, client_id, pkce, &[("scope", "profile")]).await
+}
+
+async fn authorize_with(
+    app: &TestApp,
+    client_id: &str,
+    pkce: &Pkce,
+    extra_params: &[(&str, &str)],
+) -> String {
+    let mut query = vec![
+        ("response_type", "code"),
+        ("client_id", client_id),
+        ("redirect_uri", TEST_REDIRECT_URI),
+        ("state", "xyz"),
+        ("code_challenge", &pkce.challenge),
+        ("code_challenge_method", "S256"),
+    ];
+    query.extend_from_slice(extra_params);
+    let response = app.get_authorize(&query).await;
+
+    let location = location(&response);
+    assert!(location.starts_with(TEST_REDIRECT_URI));
+    assert_eq!(query_param(&location, "state").as_deref(), Some("xyz"));
+    query_param(&location, "code").expect("No code in redirect")
+}
+
+#[tokio::test]
+async fn should_return_400_if_client_or_redirect_uri_unknown() {
+    let app = TestApp::new().await;
+
+    let (client_id, _) = app.register_client().await;
+    let pkce = Pkce::new();
+
+    let test_cases = [
+        ("unknown", TEST_REDIRECT_URI),
+        (client_id.as_str(), "https://attacker.example.com/callback"),
+        // Redirect URIs must match exactly, not just share a prefix
+        (client_id.as_str(), "https://client.example.com/callback/../steal"),
+    ];
+
+    for (client_id, redirect_uri) in test_cases {
+        let response = app
+            .get_authorize(&[
+                ("response_type", "code"),
+                ("client_id", client_id),
+                ("redirect_uri", redirect_uri),
+                ("code_challenge", &pkce.challenge),
+                ("code_challenge_method", "S256"),
+            ])
+            .await;
+
+        assert_eq!(response.status().as_u16(), 400);
+        assert!(response.headers().get("location").is_none());
+    }
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_redirect_with_error_if_client_not_first_party() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let client_id = app.register_third_party_client().await;
+    let pkce = Pkce::new();
+
+    let response = app
+        .get_authorize(&[
+            ("response_type", "code"),
+            ("client_id", &client_id),
+            ("redirect_uri", TEST_REDIRECT_URI),
+            ("state", "xyz"),
+            ("code_challenge", &pkce.challenge),
+            ("code_challenge_method", "S256"),
+        ])
+        .await;
+
+    // Without a consent screen, a logged-in user must not hand out codes
+    let location = location(&response);
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("unauthorized_client")
+    );
+    assert_eq!(query_param(&location, "state").as_deref(), Some("xyz"));
+    assert!(query_param(&location, "code").is_none());
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_redirect_to_login_if_not_logged_in() {
+    let app = TestApp::new().await;
+
+    let (client_id, _) = app.register_client().await;
+    let pkce = Pkce::new();
+
+    let response = app
+        .get_authorize(&[
+            ("response_type", "code"),
+            ("client_id", &client_id),
+            ("redirect_uri", TEST_REDIRECT_URI),
+            ("code_challenge", &pkce.challenge),
+            ("code_challenge_method", "S256"),
+        ])
+        .await;
+
+    let location = location(&response);
+    let next = query_param(&format!("http://localhost{}", location), "next")
+        .expect("No next parameter");
+    assert!(location.starts_with("/?"));
+    assert!(next.starts_with("/authorize?"));
+    assert!(next.contains(&client_id));
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_redirect_with_error_if_pkce_missing() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let (client_id, _) = app.register_client().await;
+
+    for method in ["plain", ""] {
+        let response = app
+            .get_authorize(&[
+                ("response_type", "code"),
+                ("client_id", &client_id),
+                ("redirect_uri", TEST_REDIRECT_URI),
+                ("state", "xyz"),
+                ("code_challenge", "challenge"),
+                ("code_challenge_method", method),
+            ])
+            .await;
+
+        let location = location(&response);
+        assert!(location.starts_with(TEST_REDIRECT_URI));
+        assert_eq!(
+            query_param(&location, "error").as_deref(),
+            Some("invalid_request")
+        );
+        assert_eq!(query_param(&location, "state").as_deref(), Some("xyz"));
+        assert!(query_param(&location, "code").is_none());
+    }
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_exchange_code_for_access_token() {
+    let app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    let (client_id, client_secret) = app.register_client().await;
+    let pkce = Pkce::new();
+
+    let code = authorize(&app, &client_id, &pkce).await;
+
+    let form = [
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", TEST_REDIRECT_URI),
+        ("code_verifier", pkce.verifier.as_str()),
+    ];
+    let response = app
+        .post_token(&form, Some((&client_id, &client_secret)))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .headers()
+            .get("cache-control")
+            .and_then(|value| value.to_str().ok()),
+        Some("no-store")
+    );
+
+    let body = response
+        .json::<AccessTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to AccessTokenResponse");
+    assert_eq!(body.token_type, "Bearer");
+    assert_eq!(body.scope.as_deref(), Some("profile"));
+    assert!(body.id_token.is_none());
+
+    let introspection = app
+        .post_introspect(&client_id, &client_secret, &body.access_token)
+        .await
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert!(introspection.active);
+    assert_eq!(introspection.sub, Some(email));
+    assert_eq!(introspection.client_id, Some(client_id.clone()));
+    assert_eq!(introspection.scope.as_deref(), Some("profile"));
+
+    // Codes are single use
+    let response = app
+        .post_token(&form, Some((&client_id, &client_secret)))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "invalid_grant".to_owned()
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_code_verifier_wrong() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let (client_id, client_secret) = app.register_client().await;
+    let pkce = Pkce::new();
+
+    let code = authorize(&app, &client_id, &pkce).await;
+
+    let wrong_verifier = Pkce::new().verifier;
+    let response = app
+        .post_token(
+            &[
+                ("grant_type", "authorization_code"),
+                ("code", &code),
+                ("redirect_uri", TEST_REDIRECT_URI),
+                ("code_verifier", &wrong_verifier),
+            ],
+            Some((&client_id, &client_secret)),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "invalid_grant".to_owned()
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_code_belongs_to_another_client() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let (client_id, _) = app.register_client().await;
+    let (other_client_id, other_client_secret) = app.register_client().await;
+    let pkce = Pkce::new();
+
+    let code = authorize(&app, &client_id, &pkce).await;
+
+    let response = app
+        .post_token(
+            &[
+                ("grant_type", "authorization_code"),
+                ("code", &code),
+                ("redirect_uri", TEST_REDIRECT_URI),
+                ("code_verifier", &pkce.verifier),
+            ],
+            Some((&other_client_id, &other_client_secret)),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_let_public_clients_redeem_codes_with_pkce_only() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let client_id = app.register_public_client().await;
+    let pkce = Pkce::new();
+
+    let code = authorize(&app, &client_id, &pkce).await;
+
+    let response = app
+        .post_token(
+            &[
+                ("grant_type", "authorization_code"),
+                ("client_id", &client_id),
+                ("code", &code),
+                ("redirect_uri", TEST_REDIRECT_URI),
+                ("code_verifier", &pkce.verifier),
+            ],
+            None,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<AccessTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to AccessTokenResponse");
+    let response = app
+        .post_verify_token(&serde_json::json!({
+            "token": body.access_token,
+            "audience": client_id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_only_accept_client_access_tokens_for_that_client() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let (client_id, client_secret) = app.register_client().await;
+    let pkce = Pkce::new();
+
+    let code = authorize(&app, &client_id, &pkce).await;
+    let body = exchange_code(&app, &client_id, &client_secret, &code, &pkce).await;
+
+    // Our own apps and routes don't take tokens issued to a client
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": body.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.get_sessions_with_bearer(&body.access_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_logout_with_bearer(&body.access_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_confidential_client_omits_secret() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let (client_id, _) = app.register_client().await;
+    let pkce = Pkce::new();
+
+    let code = authorize(&app, &client_id, &pkce).await;
+
+    let response = app
+        .post_token(
+            &[
+                ("grant_type", "authorization_code"),
+                ("client_id", &client_id),
+                ("code", &code),
+                ("redirect_uri", TEST_REDIRECT_URI),
+                ("code_verifier", &pkce.verifier),
+            ],
+            None,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "invalid_client".to_owned()
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_grant_type_unsupported() {
+    let app = TestApp::new().await;
+
+    let (client_id, client_secret) = app.register_client().await;
+
+    let response = app
+        .post_token(
+            &[("grant_type", "password")],
+            Some((&client_id, &client_secret)),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "unsupported_grant_type".to_owned()
+    );
+
+    app.cleanup().await;
+}
+
+async fn exchange_code(
+    app: &TestApp,
+    client_id: &str,
+    client_secret: &str,
+    code: &str,
+    pkce: &Pkce,
+) -> AccessTokenResponse {
+    let response = app
+        .post_token(
+            &[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", TEST_REDIRECT_URI),
+                ("code_verifier", &pkce.verifier),
+            ],
+            Some((client_id, client_secret)),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<AccessTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to AccessTokenResponse")
+}
+
+#[tokio::test]
+async fn should_publish_openid_configuration() {
+    let app = TestApp::new().await;
+
+    let response = app.get_openid_configuration().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let metadata = response
+        .json::<ProviderMetadata>()
+        .await
+        .expect("Could not deserialize response body to ProviderMetadata");
+    assert_eq!(metadata.issuer, JWT_ISSUER.as_str());
+    assert_eq!(metadata.issuer, TEST_ISSUER);
+    assert_eq!(metadata.id_token_signing_alg_values_supported, vec!["RS256"]);
+    assert!(metadata.authorization_endpoint.ends_with("/authorize"));
+    assert!(metadata.token_endpoint.ends_with("/token"));
+    assert!(metadata.userinfo_endpoint.ends_with("/userinfo"));
+    assert!(metadata.jwks_uri.ends_with("/.well-known/jwks.json"));
+    assert!(metadata.scopes_supported.contains(&"openid".to_owned()));
+    assert_eq!(metadata.code_challenge_methods_supported, vec!["S256"]);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_issue_id_token_for_openid_scope() {
+    let app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    let (client_id, client_secret) = app.register_client().await;
+    let pkce = Pkce::new();
+
+    let code = authorize_with(
+        &app,
+        &client_id,
+        &pkce,
+        &[("scope", "openid email"), ("nonce", "n-0S6_WzA2Mj")],
+    )
+    .await;
+    let body = exchange_code(&app, &client_id, &client_secret, &code, &pkce).await;
+
+    let id_token = body.id_token.expect("No ID token in response");
+    let mut validation = Validation::default();
+    validation.set_audience(&[&client_id]);
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    let claims = KEY_RING
+        .decode::<IdTokenClaims>(&id_token, &validation)
+        .expect("ID token is not valid")
+        .claims;
+    assert_eq!(claims.sub, email);
+    assert_eq!(claims.email, Some(email.clone()));
+    assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+
+    // An ID token is not an access token
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": id_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.get_userinfo(&body.access_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let userinfo = response
+        .json::<UserInfoResponse>()
+        .await
+        .expect("Could not deserialize response body to UserInfoResponse");
+    assert_eq!(userinfo.sub, email);
+    assert_eq!(userinfo.email, Some(email));
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_only_return_claims_allowed_by_scope_from_userinfo() {
+    let app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    let (client_id, client_secret) = app.register_client().await;
+    let pkce = Pkce::new();
+
+    let code = authorize_with(&app, &client_id, &pkce, &[("scope", "openid")]).await;
+    let body = exchange_code(&app, &client_id, &client_secret, &code, &pkce).await;
+
+    let userinfo = app
+        .get_userinfo(&body.access_token)
+        .await
+        .json::<UserInfoResponse>()
+        .await
+        .expect("Could not deserialize response body to UserInfoResponse");
+    assert_eq!(userinfo.sub, email);
+    assert!(userinfo.email.is_none());
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_403_from_userinfo_without_openid_scope() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "returnToken": true,
+    });
+    let token = app
+        .post_login(&login_body)
+        .await
+        .json::<auth_service::routes::TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app.get_userinfo(&token).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "insufficient_scope".to_owned()
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_redirect_with_error_if_scope_unsupported() {
+    let app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let (client_id, _) = app.register_client().await;
+    let pkce = Pkce::new();
+
+    let response = app
+        .get_authorize(&[
+            ("response_type", "code"),
+            ("client_id", &client_id),
+            ("redirect_uri", TEST_REDIRECT_URI),
+            ("scope", "openid admin"),
+            ("code_challenge", &pkce.challenge),
+            ("code_challenge_method", "S256"),
+        ])
+        .await;
+
+    let location = location(&response);
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("invalid_scope")
+    );
+
+    app.cleanup().await;
+}
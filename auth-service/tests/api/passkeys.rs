@@ -0,0 +1,327 @@
+use auth_service::{
+    routes::{PasskeyCreationOptions, PasskeyRequestOptions, TokenResponse},
+    utils::constants::{JWT_COOKIE_NAME, WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use sha2::{Digest, Sha256};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const FLAGS_USER_PRESENT_AND_VERIFIED: u8 = 0x05;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// Stands in for a platform authenticator: one ES256 credential that
+/// answers challenges the way a browser would hand them to us.
+struct SoftwareAuthenticator {
+    credential_id: Vec<u8>,
+    signing_key: SigningKey,
+    sign_count: u32,
+}
+
+impl SoftwareAuthenticator {
+    fn new() -> Self {
+        Self {
+            credential_id: rand::random::<[u8; 16]>().to_vec(),
+            signing_key: SigningKey::random(&mut rand::thread_rng()),
+            sign_count: 0,
+        }
+    }
+
+    fn id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn client_data(ceremony: &str, challenge: &str) -> Vec<u8> {
+        serde_json::json!({
+            "type": ceremony,
+            "challenge": challenge,
+            "origin": *WEBAUTHN_ORIGIN,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn authenticator_data(&self, flags: u8) -> Vec<u8> {
+        let mut data = Sha256::digest(WEBAUTHN_RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+
+    /// Response to `navigator.credentials.create()` with "none" attestation.
+    fn register(&self, challenge: &str) -> serde_json::Value {
+        let point = self.signing_key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(-7)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::from(point.x().unwrap().to_vec())),
+            (Value::from(-3), Value::from(point.y().unwrap().to_vec())),
+        ]);
+
+        let mut authenticator_data = self
+            .authenticator_data(FLAGS_USER_PRESENT_AND_VERIFIED | FLAG_ATTESTED_CREDENTIAL_DATA);
+        authenticator_data.extend_from_slice(&[0; 16]);
+        authenticator_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        authenticator_data.extend_from_slice(&self.credential_id);
+        ciborium::into_writer(&cose_key, &mut authenticator_data).unwrap();
+
+        let attestation_object = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(vec![])),
+            (Value::from("authData"), Value::from(authenticator_data)),
+        ]);
+        let mut attestation_object_bytes = Vec::new();
+        ciborium::into_writer(&attestation_object, &mut attestation_object_bytes).unwrap();
+
+        serde_json::json!({
+            "id": self.id(),
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(Self::client_data("webauthn.create", challenge)),
+                "attestationObject": URL_SAFE_NO_PAD.encode(attestation_object_bytes),
+            },
+        })
+    }
+
+    /// Response to `navigator.credentials.get()`, bumping the counter first.
+    fn sign(&mut self, challenge: &str) -> serde_json::Value {
+        self.sign_count += 1;
+        self.sign_without_counting(challenge)
+    }
+
+    fn sign_without_counting(&self, challenge: &str) -> serde_json::Value {
+        let client_data_json = Self::client_data("webauthn.get", challenge);
+        let authenticator_data = self.authenticator_data(FLAGS_USER_PRESENT_AND_VERIFIED);
+
+        let mut signed_data = authenticator_data.clone();
+        signed_data.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature: Signature = self.signing_key.sign(&signed_data);
+
+        serde_json::json!({
+            "id": self.id(),
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data_json),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(authenticator_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+            },
+        })
+    }
+}
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+async fn start_registration(app: &TestApp) -> PasskeyCreationOptions {
+    let response = app.post_passkey_registration_start().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<PasskeyCreationOptions>()
+        .await
+        .expect("Could not deserialize response body to PasskeyCreationOptions")
+}
+
+async fn register(app: &TestApp, authenticator: &SoftwareAuthenticator) {
+    let options = start_registration(app).await;
+
+    let response = app
+        .post_passkey_registration_finish(&authenticator.register(&options.challenge))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn start_login(app: &TestApp) -> String {
+    let response = app.post_passkey_login_start().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let options = response
+        .json::<PasskeyRequestOptions>()
+        .await
+        .expect("Could not deserialize response body to PasskeyRequestOptions");
+    assert_eq!(options.rp_id, *WEBAUTHN_RP_ID);
+
+    options.challenge
+}
+
+#[tokio::test]
+async fn should_require_authentication_to_register() {
+    let app = TestApp::new().await;
+
+    let response = app.post_passkey_registration_start().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_log_in_with_registered_passkey() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    let options = start_registration(&app).await;
+    assert_eq!(options.user.name, email);
+    assert_eq!(options.pub_key_cred_params[0].alg, -7);
+
+    let mut authenticator = SoftwareAuthenticator::new();
+    let response = app
+        .post_passkey_registration_finish(&authenticator.register(&options.challenge))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Registered passkeys are excluded from further registrations
+    let options = start_registration(&app).await;
+    assert_eq!(options.exclude_credentials[0].id, authenticator.id());
+
+    app.post_logout().await;
+
+    let challenge = start_login(&app).await;
+    let mut assertion = authenticator.sign(&challenge);
+    assertion["returnToken"] = serde_json::json!(true);
+
+    let response = app.post_passkey_login_finish(&assertion).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    assert!(!auth_cookie.is_empty());
+
+    let body = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_eq!(body.token, auth_cookie);
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_409_if_passkey_already_registered() {
+    let app = TestApp::new().await;
+    signup_and_login(&app).await;
+
+    let authenticator = SoftwareAuthenticator::new();
+    register(&app, &authenticator).await;
+
+    let options = start_registration(&app).await;
+    let response = app
+        .post_passkey_registration_finish(&authenticator.register(&options.challenge))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_passkey_unknown() {
+    let app = TestApp::new().await;
+
+    let challenge = start_login(&app).await;
+    let response = app
+        .post_passkey_login_finish(&SoftwareAuthenticator::new().sign(&challenge))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_challenge_reused() {
+    let app = TestApp::new().await;
+    signup_and_login(&app).await;
+
+    let mut authenticator = SoftwareAuthenticator::new();
+    register(&app, &authenticator).await;
+
+    let challenge = start_login(&app).await;
+    let response = app
+        .post_passkey_login_finish(&authenticator.sign(&challenge))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_passkey_login_finish(&authenticator.sign(&challenge))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // A registration challenge can't be used to log in
+    let options = start_registration(&app).await;
+    let response = app
+        .post_passkey_login_finish(&authenticator.sign(&options.challenge))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_sign_count_does_not_increase() {
+    let app = TestApp::new().await;
+    signup_and_login(&app).await;
+
+    let mut authenticator = SoftwareAuthenticator::new();
+    register(&app, &authenticator).await;
+
+    let challenge = start_login(&app).await;
+    let response = app
+        .post_passkey_login_finish(&authenticator.sign(&challenge))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // A clone of the credential would repeat the counter
+    let challenge = start_login(&app).await;
+    let response = app
+        .post_passkey_login_finish(&authenticator.sign_without_counting(&challenge))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_signature_invalid() {
+    let app = TestApp::new().await;
+    signup_and_login(&app).await;
+
+    let mut authenticator = SoftwareAuthenticator::new();
+    register(&app, &authenticator).await;
+
+    let challenge = start_login(&app).await;
+    let mut assertion = authenticator.sign(&challenge);
+    assertion["response"]["clientDataJSON"] = serde_json::json!(URL_SAFE_NO_PAD.encode(
+        SoftwareAuthenticator::client_data("webauthn.get", &start_login(&app).await)
+    ));
+
+    let response = app.post_passkey_login_finish(&assertion).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
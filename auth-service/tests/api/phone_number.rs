@@ -0,0 +1,178 @@
+use auth_service::{routes::TwoFactorAuthResponse, ErrorResponse};
+
+use crate::helpers::{get_random_email, wait_for_resend_cooldown, TestApp};
+
+const PHONE_NUMBER: &str = "+44 7700 900123";
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+/// The code at the end of the last message texted to `PHONE_NUMBER`.
+fn texted_code(app: &TestApp) -> String {
+    app.sms_client
+        .last_message_to(PHONE_NUMBER)
+        .expect("No text message sent")
+        .rsplit(' ')
+        .next()
+        .unwrap()
+        .to_owned()
+}
+
+async fn add_phone_number(app: &TestApp) {
+    let response = app
+        .post_phone_number(&serde_json::json!({
+            "password": "password123",
+            "phoneNumber": PHONE_NUMBER,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_400_if_phone_number_invalid() {
+    let app = TestApp::new().await;
+    signup_and_login(&app).await;
+
+    for phone_number in ["07700 900123", "+0 7700 900123", "+44 77OO 900123", "+1234"] {
+        let response = app
+            .post_phone_number(&serde_json::json!({
+                "password": "password123",
+                "phoneNumber": phone_number,
+            }))
+            .await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Accepted phone number {}",
+            phone_number
+        );
+    }
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_verification_code_incorrect() {
+    let app = TestApp::new().await;
+    signup_and_login(&app).await;
+
+    add_phone_number(&app).await;
+    let code = texted_code(&app);
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": wrong_code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The wrong guess threw the pending number away
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_switching_to_sms_without_verified_phone() {
+    let app = TestApp::new().await;
+    signup_and_login(&app).await;
+
+    // Added but never verified
+    add_phone_number(&app).await;
+
+    let response = app
+        .post_2fa_method(&serde_json::json!({
+            "password": "password123",
+            "method": "sms",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Phone number not verified".to_owned()
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_send_login_codes_by_sms() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    add_phone_number(&app).await;
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": texted_code(&app) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_2fa_method(&serde_json::json!({
+            "password": "password123",
+            "method": "sms",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.post_logout().await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(body.method, "sms");
+
+    // Resent codes go to the phone too
+    wait_for_resend_cooldown().await;
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": body.login_attempt_id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": body.login_attempt_id,
+            "2FACode": texted_code(&app),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
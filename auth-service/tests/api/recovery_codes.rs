@@ -0,0 +1,212 @@
+use auth_service::{
+    routes::{RecoveryCodesResponse, SignupResponse, TwoFactorAuthResponse},
+    utils::constants::TWO_FA_MAX_ATTEMPTS,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_with_2fa(app: &TestApp) -> (String, Vec<String>) {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let body = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse");
+
+    (email, body.recovery_codes)
+}
+
+async fn verify_with(app: &TestApp, email: &str, code: &str) -> reqwest::Response {
+    let login_attempt_id = start_login(app, email).await;
+    verify_attempt(app, email, &login_attempt_id, code).await
+}
+
+async fn start_login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    body.login_attempt_id
+}
+
+async fn verify_attempt(
+    app: &TestApp,
+    email: &str,
+    login_attempt_id: &str,
+    code: &str,
+) -> reqwest::Response {
+    app.post_verify_2fa(&serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn should_log_in_with_recovery_code_once() {
+    let app = TestApp::new().await;
+    let (email, recovery_codes) = signup_with_2fa(&app).await;
+
+    let response = verify_with(&app, &email, &recovery_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = verify_with(&app, &email, &recovery_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Typed without dashes and in lowercase
+    let typed = recovery_codes[1].replace('-', "").to_lowercase();
+    let response = verify_with(&app, &email, &typed).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_recovery_code_unknown() {
+    let app = TestApp::new().await;
+    let (email, _) = signup_with_2fa(&app).await;
+
+    let response = verify_with(&app, &email, "AAAA-AAAA-AAAA-AAAA").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_429_after_too_many_wrong_recovery_codes() {
+    let app = TestApp::new().await;
+    let (email, recovery_codes) = signup_with_2fa(&app).await;
+
+    let login_attempt_id = start_login(&app, &email).await;
+    for _ in 1..*TWO_FA_MAX_ATTEMPTS {
+        let response = verify_attempt(&app, &email, &login_attempt_id, "AAAA-AAAA-AAAA-AAAA").await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = verify_attempt(&app, &email, &login_attempt_id, "AAAA-AAAA-AAAA-AAAA").await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    // The login attempt is dead, even for a valid recovery code
+    let response = verify_attempt(&app, &email, &login_attempt_id, &recovery_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    // Which is still good for a new one
+    let response = verify_with(&app, &email, &recovery_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_require_authentication_to_regenerate() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_recovery_codes(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_invalidate_previous_codes_when_regenerated() {
+    let app = TestApp::new().await;
+    let (email, old_codes) = signup_with_2fa(&app).await;
+
+    let response = verify_with(&app, &email, &old_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_recovery_codes(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(app
+        .email_client
+        .last_email_to(&email, "Recovery codes regenerated")
+        .is_some());
+
+    let new_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+    assert_eq!(new_codes.len(), 10);
+
+    let response = verify_with(&app, &email, &old_codes[1]).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = verify_with(&app, &email, &new_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_incorrect() {
+    let app = TestApp::new().await;
+    let (email, codes) = signup_with_2fa(&app).await;
+
+    let response = verify_with(&app, &email, &codes[0]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_recovery_codes(&serde_json::json!({ "password": "wrongpassword" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The old codes keep working
+    let response = verify_with(&app, &email, &codes[1]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_2fa_not_enabled() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_recovery_codes(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
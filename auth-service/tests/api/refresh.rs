@@ -0,0 +1,237 @@
+use auth_service::{
+    routes::TokenResponse,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn login_and_get_refresh_token(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh token cookie found");
+
+    refresh_cookie.value().to_owned()
+}
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_refresh_token() {
+    let app = TestApp::new().await;
+
+    for token in ["invalid", "9c5f0b1e-5a2e-4f64-9d5b-6d8d3b0e6b1a"] {
+        set_refresh_cookie(&app, token);
+
+        let response = app.post_refresh().await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Failed for token: {}",
+            token
+        );
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid auth token".to_owned()
+        );
+    }
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_rotate_tokens() {
+    let app = TestApp::new().await;
+
+    let old_refresh_token = login_and_get_refresh_token(&app).await;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh token cookie found");
+
+    assert!(!refresh_cookie.value().is_empty());
+    assert_ne!(refresh_cookie.value(), old_refresh_token);
+
+    // The rotated token can be used in turn
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_rotate_tokens_sent_in_body() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "returnToken": true,
+    });
+
+    let old_tokens = app
+        .post_login(&login_body)
+        .await
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+
+    let response = app.post_refresh_with_token(&old_tokens.refresh_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_ne!(new_tokens.refresh_token, old_tokens.refresh_token);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_tokens.token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Rotation and reuse detection work the same as with the cookie
+    let response = app.post_refresh_with_token(&old_tokens.refresh_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_refresh_with_token(&new_tokens.refresh_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_revoke_family_if_rotated_token_is_reused() {
+    let app = TestApp::new().await;
+
+    let old_refresh_token = login_and_get_refresh_token(&app).await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh token cookie found")
+        .value()
+        .to_owned();
+    let new_auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    // Replay the token that was already rotated
+    set_refresh_cookie(&app, &old_refresh_token);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The session is revoked, so access tokens minted from it stop working
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_auth_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The legitimate holder of the newest token is logged out as well
+    set_refresh_cookie(&app, &new_refresh_token);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_not_refresh_after_logout() {
+    let app = TestApp::new().await;
+
+    let refresh_token = login_and_get_refresh_token(&app).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_refresh_cookie(&app, &refresh_token);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
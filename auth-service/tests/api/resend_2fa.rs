@@ -0,0 +1,142 @@
+use auth_service::{
+    domain::{LoginAttemptId, TwoFACode},
+    routes::TwoFactorAuthResponse,
+    ErrorResponse,
+};
+
+use crate::helpers::{get_random_email, wait_for_resend_cooldown, TestApp};
+
+async fn signup_and_login_with_2fa(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+async fn get_code(app: &TestApp, login_attempt_id: &str) -> TwoFACode {
+    app.two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(login_attempt_id.to_owned()).unwrap())
+        .await
+        .expect("2FA code not found")
+        .1
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_pre_auth_token_missing() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "email": get_random_email(),
+            "loginAttemptId": LoginAttemptId::default().as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_login_attempt_id_does_not_match() {
+    let app = TestApp::new().await;
+
+    let email = get_random_email();
+    signup_and_login_with_2fa(&app, &email).await;
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": LoginAttemptId::default().as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_replace_code_and_enforce_cooldown() {
+    let app = TestApp::new().await;
+
+    let email = get_random_email();
+    let login_attempt_id = signup_and_login_with_2fa(&app, &email).await;
+    let old_code = get_code(&app, &login_attempt_id).await;
+
+    let resend_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+    });
+    // The cooldown runs from when the code was first sent
+    let response = app.post_resend_2fa(&resend_body).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    wait_for_resend_cooldown().await;
+    let response = app.post_resend_2fa(&resend_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_code = get_code(&app, &login_attempt_id).await;
+    if new_code != old_code {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": old_code.as_ref(),
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_resend_2fa(&resend_body).await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Please wait before requesting another code".to_owned()
+    );
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": new_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
@@ -0,0 +1,192 @@
+use auth_service::domain::EmailToken;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = login(app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+}
+
+async fn request_reset(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_forgot_password(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.password_reset_token(email)
+        .expect("No password reset email sent")
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_forgot_password(&serde_json::json!({ "emailAddress": get_random_email() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({ "token": EmailToken::default().as_ref() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_respond_the_same_for_unknown_accounts() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+
+    let response = app
+        .post_forgot_password(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(app.password_reset_token(&email).is_none());
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_respond_the_same_if_reset_email_fails() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    app.email_client.set_failing(true);
+    let response = app
+        .post_forgot_password(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.email_client.set_failing(false);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_reset_password_if_notification_fails() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    let token = request_reset(&app, &email).await;
+    app.email_client.set_failing(true);
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token,
+            "newPassword": "new password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.email_client.set_failing(false);
+
+    let response = login(&app, &email, "new password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_reset_password_and_log_out_everywhere() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    let token = request_reset(&app, &email).await;
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token,
+            "newPassword": "new password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The session from before the reset no longer works
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = login(&app, &email, "new password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Each link works only once
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token,
+            "newPassword": "another password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_keep_token_if_new_password_invalid() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    let token = request_reset(&app, &email).await;
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token,
+            "newPassword": "short",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token,
+            "newPassword": "new password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_not_accept_email_verification_token() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+
+    let response = app
+        .post_signup_unverified(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": app.verification_token(&email),
+            "newPassword": "new password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
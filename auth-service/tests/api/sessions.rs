@@ -0,0 +1,282 @@
+use auth_service::{
+    routes::SessionResponse,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+/// Logs in and returns the auth token, leaving the session's cookies in the
+/// jar.
+async fn login(app: &TestApp, email: &str) -> String {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    auth_cookie.value().to_owned()
+}
+
+fn set_auth_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME, token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+async fn get_sessions(app: &TestApp) -> Vec<SessionResponse> {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to sessions")
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.delete_session("some-session").await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let app = TestApp::new().await;
+
+    set_auth_cookie(&app, "invalid");
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.delete_session("some-session").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_list_active_sessions() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    login(&app, &random_email).await;
+    login(&app, &random_email).await;
+
+    let sessions = get_sessions(&app).await;
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions.iter().filter(|session| session.current).count(), 1);
+
+    let session = &sessions[0];
+    assert!(session.current, "Newest session should be listed first");
+    assert_eq!(session.ip_address.as_deref(), Some("127.0.0.1"));
+    assert!(!session.created_at.is_empty());
+
+    // Sessions of other users are not listed
+    let other_email = get_random_email();
+    signup(&app, &other_email).await;
+    login(&app, &other_email).await;
+
+    let sessions = get_sessions(&app).await;
+    assert_eq!(sessions.len(), 1);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_revoke_a_single_session() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let laptop_token = login(&app, &random_email).await;
+    let phone_token = login(&app, &random_email).await;
+
+    let sessions = get_sessions(&app).await;
+    let laptop_session = sessions
+        .iter()
+        .find(|session| !session.current)
+        .expect("Laptop session not listed");
+
+    let response = app.delete_session(&laptop_session.id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": laptop_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": phone_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = get_sessions(&app).await;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    // Revoking it again finds nothing
+    let response = app.delete_session(&laptop_session.id).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_log_out_when_revoking_current_session() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let token = login(&app, &random_email).await;
+
+    let sessions = get_sessions(&app).await;
+    let response = app.delete_session(&sessions[0].id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh token cookie found");
+    assert!(refresh_cookie.value().is_empty());
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_not_refresh_revoked_session() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    login(&app, &random_email).await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh token cookie found")
+        .value()
+        .to_owned();
+
+    // Revoke from a second session so the first one's cookies stay around
+    let sessions = get_sessions(&app).await;
+    let session_id = sessions[0].id.clone();
+    login(&app, &random_email).await;
+
+    let response = app.delete_session(&session_id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_another_users_session() {
+    let app = TestApp::new().await;
+
+    let victim_email = get_random_email();
+    signup(&app, &victim_email).await;
+    let victim_token = login(&app, &victim_email).await;
+    let victim_session = get_sessions(&app).await.remove(0);
+
+    let attacker_email = get_random_email();
+    signup(&app, &attacker_email).await;
+    login(&app, &attacker_email).await;
+
+    let response = app.delete_session(&victim_session.id).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": victim_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_list_sessions_with_bearer_token() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let token = login(&app, &random_email).await;
+
+    let response = app.get_sessions_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = response
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to sessions");
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    app.cleanup().await;
+}
@@ -18,17 +18,14 @@ async fn should_return_201_if_valid_input() {
 
     assert_eq!(response.status().as_u16(), 201);
 
-    let expected_response = SignupResponse {
-        message: "User created successfully!".to_owned(),
-    };
-
-    assert_eq!(
-        response
-            .json::<SignupResponse>()
-            .await
-            .expect("Could not deserialize response body to UserBody"),
-        expected_response
-    );
+    let response_body = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to UserBody");
+
+    assert_eq!(response_body.message, "User created successfully!".to_owned());
+    // Signing up with 2FA hands out recovery codes
+    assert_eq!(response_body.recovery_codes.len(), 10);
     app.cleanup().await;
 }
 
@@ -0,0 +1,316 @@
+use auth_service::{
+    domain::TotpSecret,
+    routes::{RecoveryCodesResponse, TotpEnrollmentResponse, TwoFactorAuthResponse},
+    utils::{
+        constants::TWO_FA_MAX_ATTEMPTS,
+        totp::{current_time_step, generate_code},
+    },
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp, requires_2fa: bool) -> String {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": requires_2fa
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+async fn enroll(app: &TestApp) -> TotpSecret {
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<TotpEnrollmentResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpEnrollmentResponse");
+    assert!(body.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(body.otpauth_uri.contains(&format!("secret={}", body.secret)));
+
+    TotpSecret::parse(body.secret).expect("Invalid TOTP secret")
+}
+
+/// Enrolls and confirms with the current code, which is then spent.
+async fn enroll_and_confirm(app: &TestApp) -> (TotpSecret, u64) {
+    let secret = enroll(app).await;
+    let time_step = current_time_step();
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({
+            "password": "password123",
+            "code": generate_code(&secret, time_step),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse");
+    assert_eq!(body.recovery_codes.len(), 10);
+
+    (secret, time_step)
+}
+
+async fn login_with_totp(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(body.method, "totp");
+
+    body.login_attempt_id
+}
+
+#[tokio::test]
+async fn should_require_authentication_to_enroll() {
+    let app = TestApp::new().await;
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_confirmation_code_wrong() {
+    let app = TestApp::new().await;
+    signup_and_login(&app, false).await;
+
+    // Nothing to confirm yet
+    let response = app
+        .post_totp_confirm(&serde_json::json!({
+            "password": "password123",
+            "code": "123456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let secret = enroll(&app).await;
+    let stale_code = generate_code(&secret, current_time_step() - 5);
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({
+            "password": "password123",
+            "code": stale_code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({
+            "password": "password123",
+            "code": "12345",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_require_password_to_confirm() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app, false).await;
+
+    let secret = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({
+            "password": "wrong-password",
+            "code": generate_code(&secret, current_time_step()),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Still logs in without a code, and nobody was told of a change
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(app
+        .email_client
+        .last_email_to(&email, "Authenticator app added")
+        .is_none());
+
+    enroll_and_confirm(&app).await;
+    assert!(app
+        .email_client
+        .last_email_to(&email, "Authenticator app added")
+        .is_some());
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_log_in_with_totp_code_once_confirmed() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app, false).await;
+
+    // Enrolling alone doesn't turn on 2FA
+    enroll(&app).await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let (secret, time_step) = enroll_and_confirm(&app).await;
+    let login_attempt_id = login_with_totp(&app, &email).await;
+
+    // The code used to confirm enrollment is spent
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": generate_code(&secret, time_step),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The next code is within the drift window
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": generate_code(&secret, time_step + 1),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_not_accept_totp_code_twice() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app, false).await;
+
+    let (secret, time_step) = enroll_and_confirm(&app).await;
+    let code = generate_code(&secret, time_step + 1);
+
+    let login_attempt_id = login_with_totp(&app, &email).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_attempt_id = login_with_totp(&app, &email).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_totp_code_outside_drift_window() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app, false).await;
+
+    let (secret, time_step) = enroll_and_confirm(&app).await;
+    let login_attempt_id = login_with_totp(&app, &email).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": generate_code(&secret, time_step + 5),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_429_after_too_many_wrong_totp_codes() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app, false).await;
+
+    let (secret, time_step) = enroll_and_confirm(&app).await;
+    let login_attempt_id = login_with_totp(&app, &email).await;
+
+    // There is no code to send, so nothing to resend either
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let wrong_guess = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": generate_code(&secret, time_step + 5),
+    });
+    for _ in 1..*TWO_FA_MAX_ATTEMPTS {
+        let response = app.post_verify_2fa(&wrong_guess).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_verify_2fa(&wrong_guess).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    // The login attempt is dead, even for the right code
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": generate_code(&secret, time_step + 1),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    // Logging in again starts a new count
+    let login_attempt_id = login_with_totp(&app, &email).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": generate_code(&secret, time_step + 1),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
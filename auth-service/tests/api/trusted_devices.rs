@@ -0,0 +1,155 @@
+use auth_service::{
+    domain::LoginAttemptId,
+    routes::{TrustedDeviceResponse, TwoFactorAuthResponse},
+    utils::constants::TRUSTED_DEVICE_COOKIE_NAME,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    email
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+}
+
+/// Logs in with the emailed code. Returns whether a device cookie was set.
+async fn login_with_2fa(app: &TestApp, email: &str, remember_device: bool) -> bool {
+    let response = login(app, email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let (_, code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(login_attempt_id.clone()).unwrap())
+        .await
+        .expect("2FA code not found");
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code.as_ref(),
+            "rememberDevice": remember_device,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let remembered = response
+        .cookies()
+        .any(|cookie| cookie.name() == TRUSTED_DEVICE_COOKIE_NAME && !cookie.value().is_empty());
+    remembered
+}
+
+async fn list_devices(app: &TestApp) -> Vec<TrustedDeviceResponse> {
+    let response = app.get_trusted_devices().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<Vec<TrustedDeviceResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<TrustedDeviceResponse>")
+}
+
+#[tokio::test]
+async fn should_skip_2fa_on_remembered_device() {
+    let app = TestApp::new().await;
+    let email = signup(&app).await;
+
+    assert!(login_with_2fa(&app, &email, true).await);
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let devices = list_devices(&app).await;
+    assert_eq!(devices.len(), 1);
+    assert!(devices[0].current);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_require_2fa_unless_device_remembered() {
+    let app = TestApp::new().await;
+    let email = signup(&app).await;
+
+    assert!(!login_with_2fa(&app, &email, false).await);
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert!(list_devices(&app).await.is_empty());
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_require_2fa_after_device_revoked() {
+    let app = TestApp::new().await;
+    let email = signup(&app).await;
+
+    assert!(login_with_2fa(&app, &email, true).await);
+    let id = list_devices(&app).await[0].id.clone();
+
+    let response = app.delete_trusted_device(&id).await;
+    assert_eq!(response.status().as_u16(), 204);
+    assert!(list_devices(&app).await.is_empty());
+
+    let response = app.delete_trusted_device(&id).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_not_skip_2fa_for_another_user() {
+    let app = TestApp::new().await;
+    let email = signup(&app).await;
+    let other_email = signup(&app).await;
+
+    assert!(login_with_2fa(&app, &email, true).await);
+
+    // Same browser, so the device cookie is sent along
+    let response = login(&app, &other_email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_authenticated() {
+    let app = TestApp::new().await;
+
+    let response = app.get_trusted_devices().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.delete_trusted_device("some-id").await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
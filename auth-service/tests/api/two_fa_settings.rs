@@ -0,0 +1,223 @@
+use auth_service::{
+    domain::TotpSecret,
+    routes::{RecoveryCodesResponse, TotpEnrollmentResponse, TwoFactorAuthResponse},
+    utils::totp::{current_time_step, generate_code},
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+/// Logs in again without finishing 2FA and returns the method asked for,
+/// if any. The session from before stays in the cookie jar.
+async fn login_method(app: &TestApp, email: &str) -> Option<String> {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+
+    match response.status().as_u16() {
+        200 => None,
+        206 => Some(
+            response
+                .json::<TwoFactorAuthResponse>()
+                .await
+                .expect("Could not deserialize response body to TwoFactorAuthResponse")
+                .method,
+        ),
+        status => panic!("Unexpected login status {}", status),
+    }
+}
+
+async fn enroll(app: &TestApp) -> TotpSecret {
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<TotpEnrollmentResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpEnrollmentResponse");
+
+    TotpSecret::parse(body.secret).expect("Invalid TOTP secret")
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_authenticated() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_2fa_enable(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_incorrect() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    let body = serde_json::json!({ "password": "wrongpassword" });
+
+    let response = app.post_2fa_enable(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_2fa_disable(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_2fa_method(&serde_json::json!({
+            "password": "wrongpassword",
+            "method": "email",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(login_method(&app, &email).await, None);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_enable_and_disable_2fa() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    let body = serde_json::json!({ "password": "password123" });
+
+    let response = app.post_2fa_enable(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse");
+    assert_eq!(codes.recovery_codes.len(), 10);
+    assert_eq!(login_method(&app, &email).await.as_deref(), Some("email"));
+
+    let response = app.post_2fa_disable(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(login_method(&app, &email).await, None);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_switch_between_totp_and_email() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    let secret = enroll(&app).await;
+    let response = app
+        .post_2fa_method(&serde_json::json!({
+            "password": "password123",
+            "method": "totp",
+            "code": generate_code(&secret, current_time_step()),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse");
+    assert_eq!(body.recovery_codes.len(), 10);
+    assert_eq!(login_method(&app, &email).await.as_deref(), Some("totp"));
+
+    let response = app
+        .post_2fa_method(&serde_json::json!({
+            "password": "password123",
+            "method": "email",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(login_method(&app, &email).await.as_deref(), Some("email"));
+
+    // Disabling also drops the authenticator app
+    let secret = enroll(&app).await;
+    let response = app
+        .post_2fa_method(&serde_json::json!({
+            "password": "password123",
+            "method": "totp",
+            "code": generate_code(&secret, current_time_step()),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_2fa_disable(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(login_method(&app, &email).await, None);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_method_invalid() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    let test_cases = [
+        serde_json::json!({ "password": "password123", "method": "sms" }),
+        // Switching to an authenticator app needs a code from it
+        serde_json::json!({ "password": "password123", "method": "totp" }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_2fa_method(test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    assert_eq!(login_method(&app, &email).await, None);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_totp_code_incorrect() {
+    let app = TestApp::new().await;
+    let email = signup_and_login(&app).await;
+
+    let secret = enroll(&app).await;
+    let response = app
+        .post_2fa_method(&serde_json::json!({
+            "password": "password123",
+            "method": "totp",
+            "code": generate_code(&secret, current_time_step() + 10),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(login_method(&app, &email).await, None);
+
+    app.cleanup().await;
+}
@@ -1,9 +1,12 @@
 use auth_service::{
-    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore},
-    routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    domain::LoginAttemptId,
+    routes::{TokenResponse, TwoFactorAuthResponse},
+    utils::constants::{
+        JWT_COOKIE_NAME, PRE_AUTH_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME, TWO_FA_MAX_ATTEMPTS,
+    },
     ErrorResponse,
 };
+use reqwest::Url;
 
 use crate::helpers::{get_random_email, TestApp};
 
@@ -107,12 +110,19 @@ async fn should_return_401_if_old_code() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    let pre_auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == PRE_AUTH_COOKIE_NAME)
+        .expect("No pre-auth cookie found")
+        .value()
+        .to_owned();
+
     let response_body = response
         .json::<TwoFactorAuthResponse>()
         .await
         .expect("Could not deserialize response body to TwoFactorAuthResponse");
 
-    let two_fa_code = app.two_fa_code_store.read().await.get_code(&Email::parse(random_email.clone()).unwrap()).await.expect("2FA code not found");
+    let two_fa_code = app.two_fa_code_store.read().await.get_code(&LoginAttemptId::parse(response_body.login_attempt_id.clone()).unwrap()).await.expect("2FA code not found");
 
     let two_fa_body = serde_json::json!({
         "email": random_email,
@@ -130,6 +140,9 @@ async fn should_return_401_if_old_code() {
 
     assert!(!auth_cookie.value().is_empty());
 
+    // Replay the whole step, pre-auth token included; the code is spent
+    set_pre_auth_cookie(&app, &pre_auth_token);
+
     let response = app.post_verify_2fa(&two_fa_body).await;
     assert_eq!(response.status().as_u16(), 401);
     app.cleanup().await;
@@ -167,7 +180,7 @@ async fn should_return_200_if_correct_code() {
         .await
         .expect("Could not deserialize response body to TwoFactorAuthResponse");
 
-    let two_fa_code = app.two_fa_code_store.read().await.get_code(&Email::parse(random_email.clone()).unwrap()).await.expect("2FA code not found");
+    let two_fa_code = app.two_fa_code_store.read().await.get_code(&LoginAttemptId::parse(response_body.login_attempt_id.clone()).unwrap()).await.expect("2FA code not found");
 
     let two_fa_body = serde_json::json!({
         "email": random_email,
@@ -184,5 +197,366 @@ async fn should_return_200_if_correct_code() {
         .expect("No auth cookie found");
 
     assert!(!auth_cookie.value().is_empty());
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh token cookie found");
+
+    assert!(!refresh_cookie.value().is_empty());
     app.cleanup().await;
-}
\ No newline at end of file
+}
+
+async fn signup_and_login_with_2fa(app: &TestApp, email: &str) -> TwoFactorAuthResponse {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+}
+
+fn set_pre_auth_cookie(app: &TestApp, value: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Strict; Path=/",
+            PRE_AUTH_COOKIE_NAME, value
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_pre_auth_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let response_body = signup_and_login_with_2fa(&app, &random_email).await;
+
+    // Drop the pre-auth cookie set by login
+    app.cookie_jar.add_cookie_str(
+        &format!("{}=; Max-Age=0; Path=/", PRE_AUTH_COOKIE_NAME),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let two_fa_code = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(response_body.login_attempt_id.clone()).unwrap())
+        .await
+        .expect("2FA code not found");
+
+    let two_fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": response_body.login_attempt_id,
+        "2FACode": two_fa_code.1.as_ref().to_string(),
+    });
+
+    let response = app.post_verify_2fa(&two_fa_body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_pre_auth_token() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let response_body = signup_and_login_with_2fa(&app, &random_email).await;
+
+    set_pre_auth_cookie(&app, "invalid");
+
+    let two_fa_code = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(response_body.login_attempt_id.clone()).unwrap())
+        .await
+        .expect("2FA code not found");
+
+    let two_fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": response_body.login_attempt_id,
+        "2FACode": two_fa_code.1.as_ref().to_string(),
+    });
+
+    let response = app.post_verify_2fa(&two_fa_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_pre_auth_token_belongs_to_another_user() {
+    let app = TestApp::new().await;
+
+    let victim_email = get_random_email();
+    let victim_login = signup_and_login_with_2fa(&app, &victim_email).await;
+
+    // The attacker completes the password step for their own account only
+    let attacker_email = get_random_email();
+    signup_and_login_with_2fa(&app, &attacker_email).await;
+
+    let victim_code = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(victim_login.login_attempt_id.clone()).unwrap())
+        .await
+        .expect("2FA code not found");
+
+    let two_fa_body = serde_json::json!({
+        "email": victim_email,
+        "loginAttemptId": victim_login.login_attempt_id,
+        "2FACode": victim_code.1.as_ref().to_string(),
+    });
+
+    let response = app.post_verify_2fa(&two_fa_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME);
+    assert!(auth_cookie.is_none());
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_complete_2fa_without_cookies_if_token_requested() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let response_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let pre_auth_token = response_body
+        .pre_auth_token
+        .expect("No pre-auth token in response body");
+
+    let two_fa_code = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(response_body.login_attempt_id.clone()).unwrap())
+        .await
+        .expect("2FA code not found");
+
+    let two_fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": response_body.login_attempt_id,
+        "2FACode": two_fa_code.1.as_ref().to_string(),
+        "returnToken": true,
+    });
+
+    let response = app
+        .post_verify_2fa_with_bearer(&two_fa_body, &pre_auth_token)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_429_and_invalidate_code_after_too_many_wrong_guesses() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let response_body = signup_and_login_with_2fa(&app, &random_email).await;
+
+    let two_fa_code = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(response_body.login_attempt_id.clone()).unwrap())
+        .await
+        .expect("2FA code not found")
+        .1;
+    let wrong_code = if two_fa_code.as_ref() == "000000" {
+        "111111"
+    } else {
+        "000000"
+    };
+
+    let wrong_guess = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": response_body.login_attempt_id,
+        "2FACode": wrong_code,
+    });
+    for _ in 1..*TWO_FA_MAX_ATTEMPTS {
+        let response = app.post_verify_2fa(&wrong_guess).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_verify_2fa(&wrong_guess).await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many failed attempts, please log in again".to_owned()
+    );
+
+    // The right code no longer works either
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": response_body.login_attempt_id,
+            "2FACode": two_fa_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    // Logging in again issues a fresh code
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    let response_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let two_fa_code = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(response_body.login_attempt_id.clone()).unwrap())
+        .await
+        .expect("2FA code not found")
+        .1;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": response_body.login_attempt_id,
+            "2FACode": two_fa_code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_complete_concurrent_login_attempts_independently() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Say a laptop and a phone, each with its own pre-auth token
+    let mut logins = Vec::new();
+    for _ in 0..2 {
+        let response = app
+            .post_login(&serde_json::json!({
+                "email": random_email,
+                "password": "password123",
+                "returnToken": true,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 206);
+
+        logins.push(
+            response
+                .json::<TwoFactorAuthResponse>()
+                .await
+                .expect("Could not deserialize response body to TwoFactorAuthResponse"),
+        );
+    }
+
+    for login in logins {
+        let login_attempt_id = LoginAttemptId::parse(login.login_attempt_id.clone()).unwrap();
+        let two_fa_code = app
+            .two_fa_code_store
+            .read()
+            .await
+            .get_code(&login_attempt_id)
+            .await
+            .expect("2FA code not found")
+            .1;
+
+        let response = app
+            .post_verify_2fa_with_bearer(
+                &serde_json::json!({
+                    "email": random_email,
+                    "loginAttemptId": login.login_attempt_id,
+                    "2FACode": two_fa_code.as_ref(),
+                }),
+                &login.pre_auth_token.expect("No pre-auth token in response body"),
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    app.cleanup().await;
+}
@@ -0,0 +1,100 @@
+use auth_service::{domain::EmailToken, ErrorResponse};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_unverified(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup_unverified(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    email
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn should_return_403_if_email_not_verified() {
+    let app = TestApp::new().await;
+    let email = signup_unverified(&app).await;
+    let first_token = app.verification_token(&email);
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Email address not verified".to_owned()
+    );
+
+    // Refused logins come with a fresh link
+    assert_ne!(app.verification_token(&email), first_token);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_verify_email_with_link_only_once() {
+    let app = TestApp::new().await;
+    let email = signup_unverified(&app).await;
+    let token = app.verification_token(&email);
+
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_verify_email_with_token_in_body() {
+    let app = TestApp::new().await;
+    let email = signup_unverified(&app).await;
+
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": app.verification_token(&email) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_token_unknown() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": EmailToken::default().as_ref() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": "" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.cleanup().await;
+}
@@ -1,4 +1,4 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME};
+use auth_service::utils::constants::{JWT_AUDIENCE, JWT_COOKIE_NAME};
 use crate::helpers::{get_random_email, TestApp};
 
 use serde_json::json;
@@ -43,6 +43,54 @@ async fn should_return_200_valid_token() {
     app.cleanup().await;
 }
 
+#[tokio::test]
+async fn should_only_accept_tokens_for_the_expected_audience() {
+    let app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let jwt_token = auth_cookie.value();
+
+    let request = json!({
+        "token": jwt_token,
+        "audience": JWT_AUDIENCE[0],
+    });
+
+    let output = app.post_verify_token(&request).await;
+    assert_eq!(output.status().as_u16(), 200);
+
+    // A token minted for our apps is no good to anyone else
+    let request = json!({
+        "token": jwt_token,
+        "audience": "another-app",
+    });
+
+    let output = app.post_verify_token(&request).await;
+    assert_eq!(output.status().as_u16(), 401);
+    app.cleanup().await;
+}
+
 #[tokio::test]
 async fn should_return_401_if_invalid_token() {
     let app = TestApp::new().await;